@@ -0,0 +1,416 @@
+use ethabi::{ParamType, Token};
+use openquest_types::{
+    from_fixed, merkle::hash_from_hex, parse_address, AnswerAudit, AttemptWindow, GradingPayload,
+    HackathonOffchainData, Participant, ParticipantAudit, Question, QuizOffchainData, QuizResponse,
+    RewardData, RewardType, TeamOffchainData, TokenAmount,
+};
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use ruint::aliases::U256;
+
+/// Grades a decoded payload into the response published in the notice, committing to its
+/// results with a Merkle root.
+pub fn grade_payload(payload: GradingPayload) -> QuizResponse {
+    let response = match payload {
+        GradingPayload::Quiz(quiz) => grade_quiz(quiz),
+        GradingPayload::Hackathon(hackathon) => grade_hackathon(&hackathon),
+    };
+    response.commit_results()
+}
+
+/// The per-answer grading of every quiz participant, published as reports so each can
+/// check their score. Hackathons are judged rather than graded and have none.
+pub fn audit_payload(payload: &GradingPayload) -> Vec<ParticipantAudit> {
+    match payload {
+        GradingPayload::Quiz(quiz) => audit_quiz(quiz),
+        GradingPayload::Hackathon(_) => Vec::new(),
+    }
+}
+
+pub fn audit_quiz(quiz: &QuizOffchainData) -> Vec<ParticipantAudit> {
+    quiz.participants
+        .iter()
+        .map(|participant| {
+            let late = quiz.attempt_window.is_late(participant);
+            let answers: Vec<AnswerAudit> = participant
+                .answered_questions
+                .iter()
+                .map(|answer| AnswerAudit {
+                    question_id: answer.question_id,
+                    answer: answer.answer.clone(),
+                    correct: quiz.questions.iter().any(|question| {
+                        question.id == answer.question_id
+                            && question.correct_answer == answer.answer
+                    }),
+                })
+                .collect();
+            let score = if late {
+                0
+            } else {
+                answers.iter().filter(|answer| answer.correct).count() as i64
+            };
+            ParticipantAudit {
+                quiz_uuid: quiz.uuid.clone(),
+                user_address: participant.wallet_address.clone(),
+                late,
+                answers,
+                score,
+            }
+        })
+        .collect()
+}
+
+pub fn grade_quiz(quiz: QuizOffchainData) -> QuizResponse {
+    let participants = grade_participants(
+        &quiz.questions,
+        quiz.participants.clone(),
+        &quiz.attempt_window,
+    );
+    let winners = calculate_reward_distribution(
+        participants.clone(),
+        quiz.total_reward,
+        quiz.reward_type.clone(),
+    );
+
+    let mut onchain_data: QuizResponse = QuizResponse {
+        uuid: quiz.uuid.clone(),
+        protocol: quiz.protocol.clone(),
+        results: Vec::new(),
+        results_root: None,
+    };
+
+    for participant in participants {
+        println!(
+            "Participant: {} Score: {}",
+            participant.user_uuid, participant.score
+        );
+        let mut user_reward = TokenAmount::ZERO;
+        for winner in winners.clone() {
+            if winner.user_uuid == participant.user_uuid {
+                user_reward = winner.reward;
+                break;
+            }
+        }
+
+        let user_data = RewardData {
+            user_address: participant.wallet_address.clone(),
+            reward_amount: user_reward,
+            leader_boar_addition: calculate_leaderboard_points(&quiz, participant.score as f64),
+            quiz_score: participant.score as f64,
+        };
+
+        onchain_data.results.push(user_data);
+    }
+
+    onchain_data
+}
+
+/// Ranks the submitted teams of every prize track by their average weighted judge score
+/// and pays each place's reward out equally to the members of the team holding it.
+pub fn grade_hackathon(hackathon: &HackathonOffchainData) -> QuizResponse {
+    let mut results: Vec<RewardData> = Vec::new();
+    let team_scores: Vec<(&TeamOffchainData, f64)> = hackathon
+        .teams
+        .iter()
+        .map(|team| (team, team_score(hackathon, team)))
+        .collect();
+
+    for (team, score) in team_scores.iter() {
+        for wallet in team.member_wallets.iter() {
+            results.push(RewardData {
+                user_address: wallet.clone(),
+                reward_amount: TokenAmount::ZERO,
+                leader_boar_addition: *score,
+                quiz_score: *score,
+            });
+        }
+    }
+
+    for track in hackathon.prize_tracks.iter() {
+        let mut ranked: Vec<&(&TeamOffchainData, f64)> = team_scores
+            .iter()
+            .filter(|(team, _)| team.track_ids.contains(&track.id) && !team.scores.is_empty())
+            .collect();
+        // Ties are broken by team uuid so every node settles to the same ranking.
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.uuid.cmp(&b.0.uuid)));
+
+        for ((team, score), reward) in ranked.into_iter().zip(track.rewards.iter()) {
+            println!(
+                "Track {}: team {} placed with score {} for {}",
+                track.id, team.uuid, score, reward
+            );
+            if team.member_wallets.is_empty() {
+                continue;
+            }
+            // Members split the prize exactly; leftover base units go to the first listed.
+            let shares = reward.split(&vec![1; team.member_wallets.len()]);
+            for (wallet, share) in team.member_wallets.iter().zip(shares) {
+                if let Some(result) = results.iter_mut().find(|r| &r.user_address == wallet) {
+                    result.reward_amount = result.reward_amount.saturating_add(share);
+                }
+            }
+        }
+    }
+
+    QuizResponse {
+        uuid: hackathon.uuid.clone(),
+        protocol: hackathon.protocol.clone(),
+        results,
+        results_root: None,
+    }
+}
+
+/// Average of the weighted percentages given by the hackathon's judges; marks from
+/// anyone outside the judge list are ignored.
+fn team_score(hackathon: &HackathonOffchainData, team: &TeamOffchainData) -> f64 {
+    let marks: Vec<f64> = team
+        .scores
+        .iter()
+        .filter(|score| hackathon.judges.contains(&score.judge_uuid))
+        .map(|score| score.weighted_percentage(&hackathon.rubric))
+        .collect();
+    if marks.is_empty() {
+        return 0.0;
+    }
+    marks.iter().sum::<f64>() / marks.len() as f64
+}
+
+pub fn grade_participants(
+    questions: &[Question],
+    mut participants: Vec<Participant>,
+    attempt_window: &AttemptWindow,
+) -> Vec<Participant> {
+    // Late submissions, and participants who joined but never submitted, are dropped
+    // before grading so they can neither score nor win rewards.
+    participants.retain(|participant| {
+        if !participant.has_submitted() {
+            println!("Excluding {}, who never submitted", participant.user_uuid);
+            return false;
+        }
+        let late = attempt_window.is_late(participant);
+        if late {
+            println!(
+                "Excluding late submission from {} (submitted at {}, deadline {})",
+                participant.user_uuid,
+                participant.submission_time,
+                attempt_window.deadline(participant.start_time)
+            );
+        }
+        !late
+    });
+    for participant in participants.iter_mut() {
+        let mut total_score = 0;
+        for quiz_answer in participant.answered_questions.iter() {
+            let question_id = quiz_answer.question_id;
+            let option_selected = &quiz_answer.answer;
+            // Answers to missing questions score nothing
+            if let Some(question) = questions.iter().find(|q| q.id == question_id) {
+                if question.correct_answer == *option_selected {
+                    total_score += 1;
+                }
+            }
+        }
+        participant.score = total_score;
+    }
+    participants
+}
+
+/// Pays `total_reward` out to at most five participants. Shares are exact integer splits
+/// of the pool; any base units left over go to the best-placed winners first.
+pub fn calculate_reward_distribution(
+    mut participants: Vec<Participant>,
+    total_reward: TokenAmount,
+    reward_type: RewardType,
+) -> Vec<Participant> {
+    match reward_type {
+        RewardType::DistributedByRankToTopFive => {
+            // Sort participants by score in descending order; the sort is stable, so ties
+            // keep the order they were submitted in on every node
+            participants.sort_by_key(|participant| std::cmp::Reverse(participant.score));
+            participants.truncate(5);
+
+            // Each distinct score is a rank; the best of `n` ranks weighs `n`, the worst 1
+            let mut scores: Vec<i64> = participants.iter().map(|p| p.score).collect();
+            scores.dedup();
+            let weights: Vec<u64> = participants
+                .iter()
+                .map(|participant| {
+                    let rank = scores
+                        .iter()
+                        .position(|score| *score == participant.score)
+                        .unwrap_or(0);
+                    (scores.len() - rank) as u64
+                })
+                .collect();
+
+            let final_winners = pay_out(participants, total_reward.split(&weights));
+            println!("Distributed rewards by rank to {:?}", final_winners);
+            final_winners
+        }
+        RewardType::DistributedEqullyToTopFive => {
+            // Sort participants by descending score and keep the top 5
+            participants.sort_by_key(|participant| std::cmp::Reverse(participant.score));
+            participants.truncate(5);
+
+            let shares = total_reward.split(&vec![1; participants.len()]);
+            let rewarded_participants = pay_out(participants, shares);
+            println!("Distributed rewards equally to {:?}", rewarded_participants);
+            rewarded_participants
+        }
+
+        RewardType::DistributedByLottery => {
+            let salt = format!(
+                "SaltFrom{}{}{}",
+                participants.len(),
+                total_reward,
+                reward_type.to_string()
+            );
+
+            // Shuffle participants using the salt
+            shuffle_with_salt(&mut participants, salt.as_bytes());
+
+            // Select the top 5 participants if possible
+            participants.truncate(5);
+
+            // Distribute rewards equally among the lottery winners
+            let shares = total_reward.split(&vec![1; participants.len()]);
+            let rewarded_lottery_winners = pay_out(participants, shares);
+            println!("Lottery winners: {:?}", rewarded_lottery_winners);
+            rewarded_lottery_winners
+        }
+    }
+}
+
+fn pay_out(participants: Vec<Participant>, shares: Vec<TokenAmount>) -> Vec<Participant> {
+    participants
+        .into_iter()
+        .zip(shares)
+        .map(|(mut participant, share)| {
+            participant.reward = share;
+            participant
+        })
+        .collect()
+}
+
+fn shuffle_with_salt<T>(vec: &mut [T], salt: &[u8]) {
+    // Derive a deterministic seed from the salt
+    let seed = {
+        let mut hash = 0u64;
+        for &byte in salt {
+            hash = hash.wrapping_mul(31).wrapping_add(byte as u64);
+        }
+        hash
+    };
+
+    // Create a seeded RNG using the derived seed
+    let mut rng = ChaCha20Rng::seed_from_u64(seed);
+    vec.shuffle(&mut rng);
+}
+
+pub fn calculate_leaderboard_points(quiz: &QuizOffchainData, user_score: f64) -> f64 {
+    let base_points = 10.0; // Base points for any quiz completion
+    let score_percentage = (user_score / quiz.questions.len() as f64) * 100.0;
+    let difficulty_multiplier = quiz.difficulty.multiplier();
+
+    let points_earned = base_points + (score_percentage * difficulty_multiplier);
+    println!("User Point Earned is: {}", points_earned);
+    points_earned
+}
+
+pub fn encode_audit(audit: &ParticipantAudit) -> Result<Vec<u8>, serde_json::Error> {
+    serde_json::to_vec(audit)
+}
+
+/// The notice's parameters, as Solidity decodes them with
+/// `abi.decode(notice, (string, string, RewardData[], bytes32))`.
+fn notice_params() -> Vec<ParamType> {
+    let result = ParamType::Tuple(vec![
+        ParamType::Address,
+        ParamType::Uint(256),
+        ParamType::Uint(256),
+        ParamType::Uint(256),
+    ]);
+    vec![
+        ParamType::String,
+        ParamType::String,
+        ParamType::Array(Box::new(result)),
+        ParamType::FixedBytes(32),
+    ]
+}
+
+fn to_abi_uint(value: U256) -> Token {
+    Token::Uint(ethabi::Uint::from_big_endian(&value.to_be_bytes::<32>()))
+}
+
+fn from_abi_uint(token: Token) -> Result<U256, String> {
+    let value = token.into_uint().ok_or("expected a uint256")?;
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    Ok(U256::from_be_bytes(bytes))
+}
+
+/// ABI-encodes the response as `(string uuid, string protocol, (address user, uint256
+/// reward, uint256 points, uint256 score)[] results, bytes32 resultsRoot)`, with points and
+/// score as fixed-point integers of `SCORE_DECIMALS` decimals.
+pub fn encode_quiz_response(response: QuizResponse) -> Result<Vec<u8>, String> {
+    let mut results = Vec::new();
+    for result in response.results.iter() {
+        let user = parse_address(&result.user_address)?;
+        results.push(Token::Tuple(vec![
+            Token::Address(ethabi::Address::from_slice(&user)),
+            to_abi_uint(result.reward_amount.base_units()),
+            to_abi_uint(result.fixed_points()),
+            to_abi_uint(result.fixed_score()),
+        ]));
+    }
+    let root = match response.results_root.as_deref() {
+        Some(root) => hash_from_hex(root)?,
+        None => [0; 32],
+    };
+    Ok(ethabi::encode(&[
+        Token::String(response.uuid),
+        Token::String(response.protocol),
+        Token::Array(results),
+        Token::FixedBytes(root.to_vec()),
+    ]))
+}
+
+/// Reads back a notice written by [`encode_quiz_response`].
+pub fn decode_quiz_response(notice: &[u8]) -> Result<QuizResponse, String> {
+    let mut params = ethabi::decode(&notice_params(), notice)
+        .map_err(|e| e.to_string())?
+        .into_iter();
+    let mut next = || params.next().ok_or("notice is missing a field");
+    let uuid = next()?.into_string().ok_or("expected the uuid")?;
+    let protocol = next()?.into_string().ok_or("expected the protocol")?;
+    let tokens = next()?.into_array().ok_or("expected the results")?;
+    let root = next()?
+        .into_fixed_bytes()
+        .ok_or("expected the results root")?;
+
+    let mut results = Vec::new();
+    for token in tokens {
+        let mut fields = token
+            .into_tuple()
+            .ok_or("expected a result tuple")?
+            .into_iter();
+        let mut field = || fields.next().ok_or("result is missing a field");
+        let user = field()?.into_address().ok_or("expected an address")?;
+        let reward = from_abi_uint(field()?)?;
+        let points = from_abi_uint(field()?)?;
+        let score = from_abi_uint(field()?)?;
+        results.push(RewardData {
+            user_address: format!("0x{}", hex::encode(user.as_bytes())),
+            reward_amount: TokenAmount::from_base_units(reward),
+            leader_boar_addition: from_fixed(points),
+            quiz_score: from_fixed(score),
+        });
+    }
+    Ok(QuizResponse {
+        uuid,
+        protocol,
+        results,
+        results_root: Some(format!("0x{}", hex::encode(root))),
+    })
+}
@@ -0,0 +1,8 @@
+//! Grading logic of the OpenQuest Coprocessor program.
+//!
+//! [`grading`] turns a decoded [`openquest_types::GradingPayload`] into the response
+//! published as a notice, and [`rollup`] drives it from the rollup HTTP server. The
+//! `dapp` binary only points [`rollup::run`] at `ROLLUP_HTTP_SERVER_URL`.
+
+pub mod grading;
+pub mod rollup;
@@ -0,0 +1,235 @@
+use crate::grading::{audit_payload, encode_audit, encode_quiz_response, grade_payload};
+use json::{object, JsonValue};
+use openquest_types::{
+    blob_ref, decode_payload, validate_payload, verify_blob, wire::MAX_DECOMPRESSED_LEN, BlobRef,
+    GradingPayload, InputProblem, ParticipantAudit, QuizResponse, RejectionReport,
+};
+use std::env;
+
+/// GIO domain the rollup server answers with the preimage of a keccak256 hash.
+const KECCAK256_PREIMAGE_DOMAIN: u16 = 0x2a;
+
+pub async fn handle_advance(
+    _client: &hyper::Client<hyper::client::HttpConnector>,
+    _server_addr: &str,
+    request: JsonValue,
+) -> Result<&'static str, Box<dyn std::error::Error>> {
+    println!("Received advance request data {}", &request);
+    let msg_sender = request["data"]["metadata"]["msg_sender"]
+        .as_str()
+        .unwrap_or("an unknown caller");
+    println!("caller is {}", msg_sender);
+    // let time_stamp: u128 = (request["data"]["metadata"]["timestamp"])
+    //     .to_string()
+    //     .parse::<u128>()
+    //     .expect("Invalid timestamp");
+
+    let (onchain_data, audits) = match grade_request(_client, _server_addr, &request).await {
+        Ok(graded) => graded,
+        Err(report) => return reject(_client, _server_addr, report).await,
+    };
+
+    let encoded_data = encode_quiz_response(onchain_data)?;
+    println!("ABI-encoded notice: {:?}", encoded_data);
+    let hex_encoded = hex::encode(encoded_data);
+    println!("Hex-encoded bytes: 0x{}", hex_encoded);
+
+    send_output(_client, _server_addr, "notice", &hex_encoded).await?;
+    // One report per participant, so each can see how their answers were graded.
+    for audit in audits.iter() {
+        let encoded_audit = encode_audit(audit)?;
+        send_output(_client, _server_addr, "report", &hex::encode(encoded_audit)).await?;
+    }
+    Ok("accept")
+}
+
+/// Grades a payload without settling anything and reports exactly what an advance
+/// request with the same payload would publish as its notice, so a quiz can be
+/// previewed before `gradeQuiz` is sent. Payloads that cannot be graded are rejected
+/// the same way an advance request rejects them.
+pub async fn handle_inspect(
+    _client: &hyper::Client<hyper::client::HttpConnector>,
+    _server_addr: &str,
+    request: JsonValue,
+) -> Result<&'static str, Box<dyn std::error::Error>> {
+    println!("Received inspect request data {}", &request);
+    let (preview, _) = match grade_request(_client, _server_addr, &request).await {
+        Ok(graded) => graded,
+        Err(report) => return reject(_client, _server_addr, report).await,
+    };
+    let preview = encode_quiz_response(preview)?;
+    send_output(_client, _server_addr, "report", &hex::encode(preview)).await?;
+    Ok("accept")
+}
+
+/// Reads, decodes and validates the payload of an advance or inspect request and grades
+/// it along with the audit of each participant, or lists what was wrong with it.
+async fn grade_request(
+    client: &hyper::Client<hyper::client::HttpConnector>,
+    server_addr: &str,
+    request: &JsonValue,
+) -> Result<(QuizResponse, Vec<ParticipantAudit>), RejectionReport> {
+    let payload_hex = request["data"]["payload"]
+        .as_str()
+        .ok_or(InputProblem::MissingPayload)?;
+    let payload = load_payload(client, server_addr, &remove_first_two_chars(payload_hex)).await?;
+    validate_payload(&payload)?;
+    let audits = audit_payload(&payload);
+    Ok((grade_payload(payload), audits))
+}
+
+/// Publishes `report` as a report and rejects the input it describes.
+async fn reject(
+    client: &hyper::Client<hyper::client::HttpConnector>,
+    server_addr: &str,
+    report: RejectionReport,
+) -> Result<&'static str, Box<dyn std::error::Error>> {
+    println!("Rejecting input: {}", report);
+    send_output(client, server_addr, "report", &hex::encode(report.encode())).await?;
+    Ok("reject")
+}
+
+/// Posts hex-encoded bytes to the rollup server's `/notice` or `/report`.
+async fn send_output(
+    client: &hyper::Client<hyper::client::HttpConnector>,
+    server_addr: &str,
+    kind: &str,
+    hex_encoded: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let output = object! { "payload" => format!("0x{}", hex_encoded) };
+    let request = hyper::Request::builder()
+        .method(hyper::Method::POST)
+        .uri(format!("{}/{}", server_addr, kind))
+        .header("Content-Type", "application/json")
+        .body(hyper::Body::from(output.dump()))?;
+    let response = client.request(request).await?;
+    println!("Sent {}, received status {}", kind, response.status());
+    Ok(())
+}
+
+fn remove_first_two_chars(s: &str) -> String {
+    if s.starts_with("0x") {
+        if let Some((index, _)) = s.char_indices().nth(2) {
+            s[index..].to_string()
+        } else {
+            String::new()
+        }
+    } else {
+        s.to_string()
+    }
+}
+
+/// Decodes the payload passed on-chain. A payload published off-chain is fetched first
+/// and only graded if it matches the hash it was referenced by.
+async fn load_payload(
+    client: &hyper::Client<hyper::client::HttpConnector>,
+    server_addr: &str,
+    payload_hex: &str,
+) -> Result<GradingPayload, InputProblem> {
+    let bytes = hex::decode(payload_hex).map_err(|e| InputProblem::InvalidHex {
+        message: e.to_string(),
+    })?;
+    let reference = match blob_ref(&bytes)? {
+        Some(reference) => reference,
+        None => return Ok(decode_payload(&bytes)?),
+    };
+    if reference.len > MAX_DECOMPRESSED_LEN {
+        return Err(InputProblem::BlobUnavailable {
+            message: format!("blob {} is too large", reference.hash_hex()),
+        });
+    }
+    let blob = fetch_blob(client, server_addr, &reference)
+        .await
+        .map_err(|e| InputProblem::BlobUnavailable {
+            message: e.to_string(),
+        })?;
+    verify_blob(&reference, &blob)?;
+    println!(
+        "Fetched blob {} ({} bytes)",
+        reference.hash_hex(),
+        blob.len()
+    );
+    Ok(decode_payload(&blob)?)
+}
+
+/// Reads a blob from `BLOB_SOURCE_URL` (the server's `/blobs` route) when it is set, and
+/// otherwise asks the rollup server for the preimage of its hash.
+async fn fetch_blob(
+    client: &hyper::Client<hyper::client::HttpConnector>,
+    server_addr: &str,
+    reference: &BlobRef,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    if let Ok(source) = env::var("BLOB_SOURCE_URL") {
+        let uri = format!("{}/{}", source.trim_end_matches('/'), reference.hash_hex());
+        let response = client.get(uri.parse()?).await?;
+        if !response.status().is_success() {
+            return Err(format!("{} answered {}", uri, response.status()).into());
+        }
+        return Ok(hyper::body::to_bytes(response).await?.to_vec());
+    }
+
+    let gio = object! {
+        "domain" => KECCAK256_PREIMAGE_DOMAIN,
+        "id" => format!("0x{}", reference.hash_hex()),
+    };
+    let request = hyper::Request::builder()
+        .method(hyper::Method::POST)
+        .uri(format!("{}/gio", server_addr))
+        .header("Content-Type", "application/json")
+        .body(hyper::Body::from(gio.dump()))?;
+    let response = client.request(request).await?;
+    if !response.status().is_success() {
+        return Err(format!("gio request answered {}", response.status()).into());
+    }
+    let body = hyper::body::to_bytes(response).await?;
+    let reply = json::parse(std::str::from_utf8(&body)?)?;
+    let data = reply["data"].as_str().ok_or("gio reply has no data")?;
+    Ok(hex::decode(data.trim_start_matches("0x"))?)
+}
+
+/// Reports the status of the previous request to the rollup server at `server_addr` and
+/// handles the next one, forever.
+pub async fn run(server_addr: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let client = hyper::Client::new();
+
+    let mut status = "accept";
+    loop {
+        println!("Sending finish");
+        let response = object! {"status" => status};
+        let request = hyper::Request::builder()
+            .method(hyper::Method::POST)
+            .header(hyper::header::CONTENT_TYPE, "application/json")
+            .uri(format!("{}/finish", &server_addr))
+            .body(hyper::Body::from(response.dump()))?;
+        let response = client.request(request).await?;
+        println!("Received finish status {}", response.status());
+
+        if response.status() == hyper::StatusCode::ACCEPTED {
+            println!("No pending rollup request, trying again");
+        } else {
+            let body = hyper::body::to_bytes(response).await?;
+            let req = match std::str::from_utf8(&body).map(json::parse) {
+                Ok(Ok(req)) => req,
+                _ => {
+                    eprintln!("Rollup request is not JSON");
+                    status = "reject";
+                    continue;
+                }
+            };
+
+            let handled = match req["request_type"].as_str() {
+                Some("advance_state") => handle_advance(&client, server_addr, req).await,
+                Some("inspect_state") => handle_inspect(&client, server_addr, req).await,
+                _ => {
+                    eprintln!("Unknown request type");
+                    Ok("reject")
+                }
+            };
+            // A request that could not be handled is rejected rather than stopping the loop.
+            status = handled.unwrap_or_else(|err| {
+                eprintln!("Failed to handle request: {}", err);
+                "reject"
+            });
+        }
+    }
+}
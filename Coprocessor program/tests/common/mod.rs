@@ -0,0 +1,197 @@
+//! A stand-in for the rollup HTTP server that hands recorded requests to the `dapp`
+//! binary through `/finish` and keeps whatever it posts back.
+//!
+//! Run from the repository root, where the riscv64 target in `.cargo/config.toml` does
+//! not apply, so the binary can run on the host.
+
+// Each test binary only uses part of this module.
+#![allow(dead_code)]
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server, StatusCode};
+use openquest_types::{encode_payload, GradingPayload, QuizOffchainData};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+
+/// What the binary sent back while handling a run of requests.
+#[derive(Debug, Default)]
+pub struct Captured {
+    /// The status `/finish` reported for each request, in order.
+    pub statuses: Vec<String>,
+    pub notices: Vec<Vec<u8>>,
+    pub reports: Vec<Vec<u8>>,
+}
+
+struct StandIn {
+    pending: VecDeque<Value>,
+    /// Whether a request was handed out, so the next `/finish` carries its status.
+    handling: bool,
+    captured: Captured,
+    drained: Option<oneshot::Sender<()>>,
+}
+
+pub fn fixture_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join(name)
+}
+
+pub fn fixture(name: &str) -> Value {
+    let path = fixture_path(&format!("fixtures/{}", name));
+    let text = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("reading {}: {}", path.display(), e));
+    serde_json::from_str(&text).unwrap()
+}
+
+pub fn quiz_fixture() -> QuizOffchainData {
+    serde_json::from_value(fixture("quiz.json")).unwrap()
+}
+
+/// The recorded `fixture` request with its payload replaced by `payload_hex`.
+pub fn request_with(fixture_name: &str, payload_hex: String) -> Value {
+    let mut request = fixture(fixture_name);
+    request["data"]["payload"] = Value::String(payload_hex);
+    request
+}
+
+fn encoded(payload: &GradingPayload) -> String {
+    format!("0x{}", hex::encode(encode_payload(payload).unwrap()))
+}
+
+pub fn advance_with(payload_hex: String) -> Value {
+    request_with("advance.json", payload_hex)
+}
+
+/// The recorded advance request carrying `payload` the way the server encodes it.
+pub fn advance(payload: &GradingPayload) -> Value {
+    advance_with(encoded(payload))
+}
+
+/// The recorded inspect request carrying `payload` the way the server encodes it.
+pub fn inspect(payload: &GradingPayload) -> Value {
+    request_with("inspect.json", encoded(payload))
+}
+
+async fn serve(
+    state: Arc<Mutex<StandIn>>,
+    request: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    let path = request.uri().path().to_string();
+    let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    let mut state = state.lock().unwrap();
+    let response = match path.as_str() {
+        "/finish" => {
+            if state.handling {
+                let status = body["status"].as_str().unwrap().to_string();
+                state.captured.statuses.push(status);
+            }
+            let next = state.pending.pop_front();
+            state.handling = next.is_some();
+            match next {
+                Some(next) => Response::new(Body::from(next.to_string())),
+                None => {
+                    if let Some(drained) = state.drained.take() {
+                        drained.send(()).ok();
+                    }
+                    Response::builder()
+                        .status(StatusCode::ACCEPTED)
+                        .body(Body::empty())
+                        .unwrap()
+                }
+            }
+        }
+        "/notice" | "/report" => {
+            let payload = body["payload"].as_str().unwrap().trim_start_matches("0x");
+            let payload = hex::decode(payload).unwrap();
+            let outputs = if path == "/notice" {
+                &mut state.captured.notices
+            } else {
+                &mut state.captured.reports
+            };
+            outputs.push(payload);
+            let index = outputs.len() - 1;
+            Response::new(Body::from(json!({ "index": index }).to_string()))
+        }
+        _ => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty())
+            .unwrap(),
+    };
+    Ok(response)
+}
+
+/// Runs the `dapp` binary against a stand-in rollup server until it has finished every
+/// request in `requests`, then stops it.
+pub async fn run_dapp(requests: Vec<Value>) -> Captured {
+    let (drained, mut on_drained) = oneshot::channel();
+    let state = Arc::new(Mutex::new(StandIn {
+        pending: requests.into(),
+        handling: false,
+        captured: Captured::default(),
+        drained: Some(drained),
+    }));
+
+    let service_state = state.clone();
+    let make_service = make_service_fn(move |_| {
+        let state = service_state.clone();
+        async move { Ok::<_, Infallible>(service_fn(move |request| serve(state.clone(), request))) }
+    });
+    let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+    let url = format!("http://{}", server.local_addr());
+    tokio::spawn(server);
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_dapp"))
+        .env("ROLLUP_HTTP_SERVER_URL", &url)
+        .env_remove("BLOB_SOURCE_URL")
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+    let started = Instant::now();
+    loop {
+        if on_drained.try_recv().is_ok() {
+            break;
+        }
+        if let Some(status) = child.try_wait().unwrap() {
+            panic!("dapp exited before finishing its requests: {}", status);
+        }
+        assert!(
+            started.elapsed() < Duration::from_secs(30),
+            "dapp did not finish its requests"
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    child.kill().ok();
+    child.wait().ok();
+
+    let mut state = state.lock().unwrap();
+    std::mem::take(&mut state.captured)
+}
+
+/// Compares `actual` with the golden file `name`, or rewrites the file when
+/// `UPDATE_GOLDEN` is set.
+pub fn assert_golden(name: &str, actual: &[u8]) {
+    let path = fixture_path(&format!("golden/{}", name));
+    if std::env::var("UPDATE_GOLDEN").is_ok() {
+        std::fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read(&path).unwrap_or_else(|_| {
+        panic!(
+            "{} is missing; run with UPDATE_GOLDEN=1 to record it",
+            path.display()
+        )
+    });
+    assert!(
+        expected == actual,
+        "output differs from {}:\n0x{}",
+        path.display(),
+        hex::encode(actual)
+    );
+}
@@ -0,0 +1,100 @@
+//! The grading functions called directly, without the rollup server.
+
+mod common;
+
+use common::quiz_fixture;
+use dapp::grading::{
+    calculate_reward_distribution, decode_quiz_response, encode_quiz_response, grade_participants,
+    grade_payload,
+};
+use openquest_types::{GradingPayload, RewardType, TokenAmount};
+
+#[test]
+fn late_submissions_are_not_graded() {
+    let quiz = quiz_fixture();
+    let graded = grade_participants(
+        &quiz.questions,
+        quiz.participants.clone(),
+        &quiz.attempt_window,
+    );
+    assert_eq!(graded.len(), quiz.participants.len() - 1);
+    assert!(graded.iter().all(|p| p.user_uuid != "participant-7"));
+    let scores: Vec<i64> = graded.iter().map(|p| p.score).collect();
+    assert_eq!(scores, [3, 2, 2, 1, 3, 0, 1]);
+}
+
+#[test]
+fn participants_who_never_submitted_are_not_graded() {
+    let mut quiz = quiz_fixture();
+    quiz.participants[0].answered_questions.clear();
+    quiz.participants[0].submission_time = 0;
+    let graded = grade_participants(
+        &quiz.questions,
+        quiz.participants.clone(),
+        &quiz.attempt_window,
+    );
+    assert_eq!(graded.len(), quiz.participants.len() - 2);
+    assert!(graded.iter().all(|p| p.user_uuid != "participant-1"));
+}
+
+#[test]
+fn rewards_add_up_to_the_pool_for_every_reward_type() {
+    let quiz = quiz_fixture();
+    let graded = grade_participants(
+        &quiz.questions,
+        quiz.participants.clone(),
+        &quiz.attempt_window,
+    );
+    for reward_type in [
+        RewardType::DistributedByRankToTopFive,
+        RewardType::DistributedEqullyToTopFive,
+        RewardType::DistributedByLottery,
+    ] {
+        let winners =
+            calculate_reward_distribution(graded.clone(), quiz.total_reward, reward_type.clone());
+        assert_eq!(winners.len(), 5, "{}", reward_type.to_string());
+        let paid: TokenAmount = winners.iter().map(|winner| winner.reward).sum();
+        assert_eq!(paid, quiz.total_reward, "{}", reward_type.to_string());
+    }
+
+    // Ranked payouts never favour a lower score.
+    let ranked = calculate_reward_distribution(
+        graded,
+        quiz.total_reward,
+        RewardType::DistributedByRankToTopFive,
+    );
+    for pair in ranked.windows(2) {
+        assert!(pair[0].score >= pair[1].score);
+        assert!(pair[0].reward >= pair[1].reward);
+    }
+}
+
+#[test]
+fn notices_are_abi_encoded_with_fixed_point_scores() {
+    let response = grade_payload(GradingPayload::Quiz(quiz_fixture()));
+    let notice = encode_quiz_response(response.clone()).unwrap();
+    // Dynamic parameters start with their offsets: uuid at 0x80, after the four heads.
+    assert_eq!(notice[31], 0x80);
+
+    let decoded = decode_quiz_response(&notice).unwrap();
+    assert_eq!(decoded.uuid, response.uuid);
+    assert_eq!(decoded.protocol, response.protocol);
+    assert_eq!(decoded.results_root, response.results_root);
+    assert_eq!(decoded.results.len(), response.results.len());
+    for (decoded, graded) in decoded.results.iter().zip(response.results.iter()) {
+        assert!(decoded
+            .user_address
+            .eq_ignore_ascii_case(&graded.user_address));
+        assert_eq!(decoded.reward_amount, graded.reward_amount);
+        assert_eq!(decoded.quiz_score, graded.quiz_score);
+        // Points are published to two decimals, so 109.99999999999999 reads back as 110.
+        assert_eq!(
+            decoded.leader_boar_addition,
+            (graded.leader_boar_addition * 100.0).round() / 100.0
+        );
+    }
+
+    let mut invalid = response;
+    invalid.results[0].user_address = "participant-1".to_string();
+    assert!(encode_quiz_response(invalid).is_err());
+}
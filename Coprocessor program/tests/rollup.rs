@@ -0,0 +1,204 @@
+//! The `dapp` binary driven end to end by a stand-in rollup server, with its notices
+//! checked byte for byte against `tests/golden` and its inspect reports against them.
+
+mod common;
+
+use common::{advance, advance_with, assert_golden, fixture, inspect, quiz_fixture, run_dapp};
+use dapp::grading::decode_quiz_response;
+use openquest_types::{
+    encode_payload, merkle::hash_to_hex, merkle::result_leaf, verify_proof, GradingPayload,
+    InputProblem, MerkleTree, ParticipantAudit, RejectionReport, RewardType, WireError,
+};
+use serde_json::json;
+
+#[tokio::test]
+async fn notices_match_the_golden_output_for_each_reward_type() {
+    let reward_types = [
+        RewardType::DistributedByRankToTopFive,
+        RewardType::DistributedEqullyToTopFive,
+        RewardType::DistributedByLottery,
+    ];
+    let mut requests = Vec::new();
+    for reward_type in reward_types.iter() {
+        let mut quiz = quiz_fixture();
+        quiz.reward_type = reward_type.clone();
+        requests.push(advance(&GradingPayload::Quiz(quiz)));
+    }
+
+    let captured = run_dapp(requests).await;
+    assert_eq!(captured.statuses, ["accept"; 3]);
+    assert_eq!(captured.notices.len(), reward_types.len());
+    for (reward_type, notice) in reward_types.iter().zip(captured.notices.iter()) {
+        assert_golden(&format!("{}.notice", reward_type.to_string()), notice);
+    }
+}
+
+#[tokio::test]
+async fn rejects_payloads_it_cannot_decode() {
+    let captured = run_dapp(vec![
+        advance_with("0xdeadbeef".to_string()),
+        advance(&GradingPayload::Quiz(quiz_fixture())),
+    ])
+    .await;
+    assert_eq!(captured.statuses, ["reject", "accept"]);
+    assert_eq!(captured.notices.len(), 1);
+    assert_eq!(
+        RejectionReport::decode(&captured.reports[0]).unwrap(),
+        RejectionReport::from(InputProblem::from(WireError::Truncated))
+    );
+}
+
+#[tokio::test]
+async fn reports_every_problem_with_an_input_and_keeps_going() {
+    let quiz = quiz_fixture();
+    let mut old_version = encode_payload(&GradingPayload::Quiz(quiz.clone())).unwrap();
+    old_version[3] -= 1;
+    let mut invalid = quiz.clone();
+    invalid.participants[1] = invalid.participants[0].clone();
+    invalid.participants[2].answered_questions[1].question_id = 9;
+    invalid.participants[3].answered_questions[2].question_id = 0;
+    let mut empty = quiz.clone();
+    empty.questions.clear();
+    empty.participants.clear();
+    let mut no_payload = fixture("advance.json");
+    no_payload["data"]
+        .as_object_mut()
+        .unwrap()
+        .remove("payload");
+
+    let captured = run_dapp(vec![
+        advance_with("0xzz".to_string()),
+        advance_with(format!("0x{}", hex::encode(old_version))),
+        advance(&GradingPayload::Quiz(invalid)),
+        inspect(&GradingPayload::Quiz(empty)),
+        no_payload,
+        json!({ "request_type": "unknown" }),
+        advance(&GradingPayload::Quiz(quiz.clone())),
+    ])
+    .await;
+    assert_eq!(
+        captured.statuses,
+        ["reject", "reject", "reject", "reject", "reject", "reject", "accept"]
+    );
+    assert_eq!(captured.notices.len(), 1);
+    // Unknown request types are rejected without a report, and the accepted quiz follows
+    // its notice with one audit per participant.
+    assert_eq!(captured.reports.len(), 5 + quiz.participants.len());
+    let reports: Vec<RejectionReport> = captured.reports[..5]
+        .iter()
+        .map(|report| RejectionReport::decode(report).unwrap())
+        .collect();
+    assert!(matches!(
+        reports[0].problems[..],
+        [InputProblem::InvalidHex { .. }]
+    ));
+    assert_eq!(
+        reports[1].problems,
+        [InputProblem::UnsupportedVersion {
+            found: openquest_types::WIRE_FORMAT_VERSION - 1,
+            expected: openquest_types::WIRE_FORMAT_VERSION,
+        }]
+    );
+    assert_eq!(reports[2].uuid.as_deref(), Some(quiz.uuid.as_str()));
+    assert_eq!(
+        reports[2].problems,
+        [
+            InputProblem::DuplicateParticipant {
+                participant: "participant-1".to_string(),
+            },
+            InputProblem::UnknownQuestion {
+                participant: "participant-3".to_string(),
+                question_id: 9,
+            },
+            InputProblem::DuplicateAnswer {
+                participant: "participant-4".to_string(),
+                question_id: 0,
+            },
+        ]
+    );
+    assert_eq!(
+        reports[3].problems,
+        [InputProblem::NoQuestions, InputProblem::NoParticipants]
+    );
+    assert_eq!(reports[4].problems, [InputProblem::MissingPayload]);
+}
+
+#[tokio::test]
+async fn inspect_reports_the_notice_an_advance_would_publish() {
+    let mut requests = Vec::new();
+    for reward_type in [
+        RewardType::DistributedByRankToTopFive,
+        RewardType::DistributedByLottery,
+    ] {
+        let mut quiz = quiz_fixture();
+        quiz.reward_type = reward_type;
+        let payload = GradingPayload::Quiz(quiz);
+        requests.push(inspect(&payload));
+        requests.push(advance(&payload));
+    }
+    // A recorded inspect request whose payload is not a grading payload.
+    requests.push(fixture("inspect.json"));
+
+    let captured = run_dapp(requests).await;
+    assert_eq!(
+        captured.statuses,
+        ["accept", "accept", "accept", "accept", "reject"]
+    );
+    assert_eq!(captured.notices.len(), 2);
+    // Inspects report no audits; the advances' audits are left out here.
+    let previews: Vec<&Vec<u8>> = captured
+        .reports
+        .iter()
+        .filter(|report| serde_json::from_slice::<ParticipantAudit>(report).is_err())
+        .collect();
+    assert_eq!(previews.len(), 3);
+    assert_eq!(previews[0], &captured.notices[0]);
+    assert_eq!(previews[1], &captured.notices[1]);
+    assert_eq!(
+        RejectionReport::decode(previews[2]).unwrap(),
+        RejectionReport::from(InputProblem::from(WireError::Truncated))
+    );
+}
+
+#[tokio::test]
+async fn advances_audit_every_answer_and_commit_to_their_results() {
+    let quiz = quiz_fixture();
+    let captured = run_dapp(vec![advance(&GradingPayload::Quiz(quiz.clone()))]).await;
+    assert_eq!(captured.statuses, ["accept"]);
+    let notice = decode_quiz_response(&captured.notices[0]).unwrap();
+    let audits: Vec<ParticipantAudit> = captured
+        .reports
+        .iter()
+        .map(|report| serde_json::from_slice(report).unwrap())
+        .collect();
+    assert_eq!(audits.len(), quiz.participants.len());
+
+    for (participant, audit) in quiz.participants.iter().zip(audits.iter()) {
+        assert_eq!(audit.quiz_uuid, quiz.uuid);
+        assert_eq!(audit.user_address, participant.wallet_address);
+        assert_eq!(audit.answers.len(), participant.answered_questions.len());
+        match notice.results.iter().find(|result| {
+            result
+                .user_address
+                .eq_ignore_ascii_case(&audit.user_address)
+        }) {
+            Some(result) => {
+                assert!(!audit.late);
+                assert_eq!(audit.score as f64, result.quiz_score);
+            }
+            // Only the late submission is left out of the notice.
+            None => {
+                assert_eq!(participant.user_uuid, "participant-7");
+                assert!(audit.late);
+                assert_eq!(audit.score, 0);
+            }
+        }
+    }
+
+    let tree = MerkleTree::from_results(&notice.results);
+    assert_eq!(notice.results_root, Some(hash_to_hex(&tree.root())));
+    for (index, result) in notice.results.iter().enumerate() {
+        let proof = tree.proof(index).unwrap();
+        assert!(verify_proof(&tree.root(), &result_leaf(result), &proof));
+    }
+}
@@ -38,12 +38,22 @@ contract Protocol is Ownable, CoprocessorAdapter, Script {
     /// @notice maps a compressed result bytes to its quizId;
     mapping(bytes => string) public quizResultToQuizId;
 
+    /// @notice maps the hash of a graded payload to the quizId it was sent for;
+    mapping(bytes32 => string) public payloadQuizId;
+
         /// @notice maps a 
     mapping(bytes32 => bytes) public quizResponse;
 
     /// @notice maps a protocol id to user address and finally to his leaderboard score;
+    /// @dev points and scores from notices are fixed point with 2 decimals.
     mapping(string => mapping(address => uint256)) public leaderboardPoint;
 
+    /// @notice maps a quizId to whether its results have been paid out;
+    mapping(string => bool) public quizSettled;
+
+    /// @notice maps a quizId to the Merkle root of its results;
+    mapping(string => bytes32) public quizResultsRoot;
+
     /// @notice maps a protocol id to user address and finally to a bool indicating if he is a member of the protocol
     mapping(string => mapping(address => bool)) public isProtocolMember;
 
@@ -97,7 +107,7 @@ contract Protocol is Ownable, CoprocessorAdapter, Script {
     }
 
     struct RewardData {
-        string userAddress;
+        address userAddress;
         uint256 rewardAmount;
         uint256 leaderboardAddition;
         uint256 quizScore;
@@ -107,6 +117,7 @@ contract Protocol is Ownable, CoprocessorAdapter, Script {
     string uuid;
     string protocol;
     RewardData[] results;
+    bytes32 resultsRoot;
 }
 
         /// EVENTS  ///
@@ -130,6 +141,8 @@ contract Protocol is Ownable, CoprocessorAdapter, Script {
     error InvalidAddress();
     error NotStaffMember();
     error InvalidQuizId();
+    error NoticeQuizMismatch();
+    error RewardsExceedQuiz();
 
 
 
@@ -194,12 +207,11 @@ contract Protocol is Ownable, CoprocessorAdapter, Script {
         string memory access,
         bytes memory compressed_data,
         uint256 endTime
-     ) external  {
+     ) external onlyOwnerOrStaff {
 
         if (isQuizRegistered[quiz_id]) {
 
             compressedQuizResult[quiz_id] = compressed_data;
-            quizResultToQuizId[compressed_data] = quiz_id;
 
         } else {
             Quiz memory quiz = Quiz({
@@ -219,6 +231,8 @@ contract Protocol is Ownable, CoprocessorAdapter, Script {
             compressedQuizResult[quiz_id] = compressed_data;
             isQuizRegistered[quiz_id] = true;
         }
+        quizResultToQuizId[compressed_data] = quiz_id;
+        payloadQuizId[keccak256(compressed_data)] = quiz_id;
 
 
         // Call Coprocessor with the compressed_data
@@ -295,55 +309,60 @@ contract Protocol is Ownable, CoprocessorAdapter, Script {
 
     function handleNotice(bytes32 payloadHash, bytes memory notice) internal override {
         quizResponse[payloadHash] = notice;
-        // string memory quizId = quizResultToQuizId[abi.encodePacked(payloadHash)];
 
-        // (string memory uuid, string memory _protocol, RewardData[] memory results) = abi.decode(
-        //     notice,
-        //     (string, string, RewardData[])
-        // );
+        (string memory uuid, , RewardData[] memory results, bytes32 resultsRoot) = abi.decode(
+            notice,
+            (string, string, RewardData[], bytes32)
+        );
+        // A notice can only settle the quiz whose payload was graded, within its reward.
+        require(
+            keccak256(bytes(uuid)) == keccak256(bytes(payloadQuizId[payloadHash])),
+            NoticeQuizMismatch()
+        );
+        uint256 totalRewards;
+        for (uint256 i = 0; i < results.length; i++) {
+            totalRewards += results[i].rewardAmount;
+        }
+        require(totalRewards <= quizDetails[uuid].reward, RewardsExceedQuiz());
 
-        // for (uint256 i = 0; i < results.length; i++) {
-        //     RewardData memory r = results[i];
+        // A quiz graded more than once is only paid out for its first notice.
+        if (!quizSettled[uuid]) {
+            quizSettled[uuid] = true;
+            quizResultsRoot[uuid] = resultsRoot;
+            quizDetails[uuid].completed = true;
 
-        //     // if (r.rewardAmount != 0) {
-        //     //     rewardToken.transfer(r.userAddress, r.rewardAmount);
-        //     // }
+            for (uint256 i = 0; i < results.length; i++) {
+                RewardData memory r = results[i];
 
-        //     leaderboard[r.userAddress] += r.leaderboardAddition;
+                if (r.rewardAmount != 0) {
+                    _payReward(r.userAddress, r.rewardAmount);
+                }
 
-        //     quizparticipants[uuid].push(r.userAddress);
-        //     usersQuizScore[uuid][r.userAddress] = r.quizScore;
+                leaderboard[r.userAddress] += r.leaderboardAddition;
+                leaderboardPoint[protocol.protocol_id][r.userAddress] += r.leaderboardAddition;
 
-        // }
+                quizparticipants[uuid].push(r.userAddress);
+                usersQuizScore[uuid][r.userAddress] = r.quizScore;
+            }
+        }
 
         emit ResultReceived(notice);
-        
-
     }
 
-        function demoHandleNotice(bytes32 payloadHash, bytes calldata notice) public {
-        // Decode the top-level tuple (uuid, protocol, results)
-            quizResponse[payloadHash] = notice;
-            // console.log("test location");
-        // RewardData[] memory results = new RewardData[](encodedResults.length);
-
-        // Decode each RewardData tuple inside the results array
-        // for (uint i = 0; i < encodedResults.length; i++) {
-        //     (string memory user_address, bytes memory reward_amount_bytes, bytes memory leader_boar_addition_bytes, bytes memory quiz_score_bytes) = abi.decode(encodedResults[i], (string, bytes, bytes, bytes));
-            
-        //     // Convert bytes to uint256 for amounts and scores
-        //     uint256 reward_amount = toUint256(reward_amount_bytes);
-        //     uint256 leader_boar_addition = toUint256(leader_boar_addition_bytes);
-        //     uint256 quiz_score = toUint256(quiz_score_bytes);
-
-        //     results[i] = RewardData((user_address), reward_amount, leader_boar_addition, quiz_score);
-        // }
-
-        // return (uuid, protocol);
-
+    /// @dev a failed transfer emits TransferFailed instead of reverting, so it cannot hold back
+    /// the rest of the notice.
+    function _payReward(address to, uint256 amount) internal {
+        (bool success, bytes memory data) = address(rewardToken).call(
+            abi.encodeCall(IERC20.transfer, (to, amount))
+        );
+        bool transferred = success
+            && address(rewardToken).code.length != 0
+            && (data.length == 0 || (data.length >= 32 && abi.decode(data, (bool))));
+        if (!transferred) {
+            emit TransferFailed(to, amount, data);
+        }
     }
 
-    
     function toUint256(bytes memory b) internal pure returns (uint256) {
         require(b.length <= 32, "Invalid bytes length");
         uint256 number;
@@ -8,6 +8,7 @@ import "../src/protocol.sol";
 import "../src/mockToken.sol";
 import "@openzeppelin/contracts/token/ERC20/IERC20.sol";
 import {IProtocol} from "../src/IProtocol.sol";
+import {ICoprocessorOutputs} from "../lib/coprocessor-base-contract/src/ICoprocessorOutputs.sol";
 
 contract FactoryQuestTest is Test {
     Factory factory;
@@ -63,16 +64,66 @@ contract FactoryQuestTest is Test {
 
     function testCoprocessorResponse() public {
         testGradeQuiz();
-        vm.startPrank(taskIssuerAddress);
-       bytes memory outPut = hex"7b2275756964223a2230333164656466302d633565322d343662612d616132342d396534373337663865363934222c2270726f746f636f6c223a2243617274657369222c22726573756c7473223a5b7b22757365725f61646472657373223a22307866333946643665353161616438384636463463653661423838323732373963666646623932323636222c227265776172645f616d6f756e74223a3130302e302c226c65616465725f626f61725f6164646974696f6e223a3131302e302c227175697a5f73636f7265223a322e307d5d7d";
-       console.logBytes(outPut);
-        IProtocol(tokenAddress).demoHandleNotice{gas: 10000000000}(data, outPut);
-        bytes memory contract_output = IProtocol(tokenAddress).checkQuizResponse(keccak256(compressed_data));
-        console.logBytes(contract_output);
-        vm.stopPrank();
+        bytes memory notice = encodedNotice();
+        bytes[] memory outputs = new bytes[](1);
+        outputs[0] = abi.encodeCall(ICoprocessorOutputs.Notice, (notice));
+
+        vm.prank(taskIssuerAddress);
+        IProtocol(tokenAddress).coprocessorCallbackOutputsOnly(machineHash, data, outputs);
+        bytes memory contract_output = IProtocol(tokenAddress).checkQuizResponse(data);
+
+        assert(keccak256(notice) == keccak256(contract_output));
+    }
+
+    function testForgedNoticeIsRejected() public {
+        testGradeQuiz();
+        bytes[] memory outputs = new bytes[](1);
+        outputs[0] = abi.encodeCall(ICoprocessorOutputs.Notice, (encodedNotice()));
+
+        vm.prank(user1);
+        vm.expectRevert(abi.encodeWithSelector(CoprocessorAdapter.UnauthorizedCaller.selector, user1));
+        IProtocol(tokenAddress).coprocessorCallbackOutputsOnly(machineHash, data, outputs);
+
+        assert(IProtocol(tokenAddress).checkQuizResponse(data).length == 0);
+    }
+
+    function testGradeQuizFromNonStaffReverts() public {
+        testProtocol();
+        vm.prank(user1);
+        vm.expectRevert(Protocol.NewUnauthorizedCaller.selector);
+        IProtocol(tokenAddress).gradeQuiz("001a", "test", 100, 20, user1, "Cartesi", "public", compressed_data, 1739547363);
 
-        assert(keccak256(outPut) == keccak256(contract_output));
+        assert(!IProtocol(tokenAddress).checkQuizIsRegistered("001a"));
+    }
+
+    function testNoticeForAnotherQuizIsRejected() public {
+        testGradeQuiz();
+        bytes[] memory outputs = new bytes[](1);
+        outputs[0] = abi.encodeCall(ICoprocessorOutputs.Notice, (encodedNotice("002b", 0)));
+
+        vm.prank(taskIssuerAddress);
+        vm.expectRevert(Protocol.NoticeQuizMismatch.selector);
+        IProtocol(tokenAddress).coprocessorCallbackOutputsOnly(machineHash, data, outputs);
+    }
+
+    function testNoticeOverpayingTheQuizIsRejected() public {
+        testGradeQuiz();
+        bytes[] memory outputs = new bytes[](1);
+        outputs[0] = abi.encodeCall(ICoprocessorOutputs.Notice, (encodedNotice("001a", 101)));
+
+        vm.prank(taskIssuerAddress);
+        vm.expectRevert(Protocol.RewardsExceedQuiz.selector);
+        IProtocol(tokenAddress).coprocessorCallbackOutputsOnly(machineHash, data, outputs);
+    }
+
+    function encodedNotice() internal view returns (bytes memory) {
+        return encodedNotice("001a", 0);
+    }
 
+    function encodedNotice(string memory uuid, uint256 reward) internal view returns (bytes memory) {
+        Protocol.RewardData[] memory results = new Protocol.RewardData[](1);
+        results[0] = Protocol.RewardData(user1, reward, 110, 2);
+        return abi.encode(uuid, string("Cartesi"), results, bytes32(0));
     }
 
 //     function testPublishQuiz() public {
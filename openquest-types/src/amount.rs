@@ -0,0 +1,407 @@
+use ruint::aliases::U256;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// Largest number of token decimals whose scale still fits in a `U256`.
+pub const MAX_TOKEN_DECIMALS: u8 = 77;
+
+/// An exact token amount, counted in the token's smallest unit.
+///
+/// Serialized as a base-10 string of base units so JSON, BSON and bincode all carry it
+/// without rounding. Plain numbers are still accepted and truncated to whole base units,
+/// which is how the contracts already read the `f64` amounts stored by older records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct TokenAmount(U256);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AmountError {
+    Empty,
+    Invalid(String),
+    TooManyDecimals { found: usize, allowed: u8 },
+    UnsupportedDecimals(u8),
+    Overflow,
+}
+
+impl fmt::Display for AmountError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AmountError::Empty => write!(f, "amount is empty"),
+            AmountError::Invalid(value) => write!(f, "{:?} is not a decimal amount", value),
+            AmountError::TooManyDecimals { found, allowed } => write!(
+                f,
+                "amount has {} decimal places but the token only has {}",
+                found, allowed
+            ),
+            AmountError::UnsupportedDecimals(decimals) => write!(
+                f,
+                "tokens with {} decimals are not supported (at most {})",
+                decimals, MAX_TOKEN_DECIMALS
+            ),
+            AmountError::Overflow => write!(f, "amount does not fit in 256 bits"),
+        }
+    }
+}
+
+impl std::error::Error for AmountError {}
+
+impl TokenAmount {
+    pub const ZERO: TokenAmount = TokenAmount(U256::ZERO);
+
+    pub fn from_base_units(units: U256) -> Self {
+        TokenAmount(units)
+    }
+
+    pub fn base_units(&self) -> U256 {
+        self.0
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+
+    /// Parses an amount written in whole tokens, such as `"12.5"`, for a token with
+    /// `decimals` decimals.
+    pub fn parse_units(value: &str, decimals: u8) -> Result<Self, AmountError> {
+        let scale = scale(decimals)?;
+        let value = value.trim();
+        if value.is_empty() {
+            return Err(AmountError::Empty);
+        }
+        let (whole, fraction) = value.split_once('.').unwrap_or((value, ""));
+        let is_digits = |part: &str| part.bytes().all(|byte| byte.is_ascii_digit());
+        if (whole.is_empty() && fraction.is_empty()) || !is_digits(whole) || !is_digits(fraction) {
+            return Err(AmountError::Invalid(value.to_string()));
+        }
+        // Trailing zeros past the token's precision are harmless.
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.len() > decimals as usize {
+            return Err(AmountError::TooManyDecimals {
+                found: fraction.len(),
+                allowed: decimals,
+            });
+        }
+
+        let whole = parse_digits(whole)?;
+        let fraction = parse_digits(fraction)?
+            .checked_mul(U256::from(10u8).pow(U256::from(decimals as usize - fraction.len())))
+            .ok_or(AmountError::Overflow)?;
+        whole
+            .checked_mul(scale)
+            .and_then(|whole| whole.checked_add(fraction))
+            .map(TokenAmount)
+            .ok_or(AmountError::Overflow)
+    }
+
+    /// The amount in whole tokens without trailing zeros, such as `"12.5"`.
+    pub fn format_units(&self, decimals: u8) -> String {
+        let Ok(scale) = scale(decimals) else {
+            return self.0.to_string();
+        };
+        let whole = self.0 / scale;
+        let fraction = self.0 % scale;
+        if fraction.is_zero() {
+            return whole.to_string();
+        }
+        let fraction = format!(
+            "{:0>width$}",
+            fraction.to_string(),
+            width = decimals as usize
+        );
+        format!("{}.{}", whole, fraction.trim_end_matches('0'))
+    }
+
+    pub fn checked_add(self, other: TokenAmount) -> Option<TokenAmount> {
+        self.0.checked_add(other.0).map(TokenAmount)
+    }
+
+    pub fn saturating_add(self, other: TokenAmount) -> TokenAmount {
+        TokenAmount(self.0.saturating_add(other.0))
+    }
+
+    /// Splits the amount in proportion to `weights`. Each share is rounded down and the
+    /// base units left over go one each to the earliest entries with a non-zero weight,
+    /// so the shares always add up to the whole amount. All-zero weights get nothing.
+    pub fn split(&self, weights: &[u64]) -> Vec<TokenAmount> {
+        let total_weight: u128 = weights.iter().map(|weight| *weight as u128).sum();
+        if total_weight == 0 {
+            return vec![TokenAmount::ZERO; weights.len()];
+        }
+        let total_weight = U256::from(total_weight);
+        // amount * w / total, computed as q * w + r * w / total so it cannot overflow.
+        let quotient = self.0 / total_weight;
+        let remainder = self.0 % total_weight;
+        let mut shares: Vec<TokenAmount> = weights
+            .iter()
+            .map(|weight| {
+                let weight = U256::from(*weight);
+                TokenAmount(quotient * weight + remainder * weight / total_weight)
+            })
+            .collect();
+
+        let distributed = shares.iter().fold(U256::ZERO, |sum, share| sum + share.0);
+        let mut leftover = self.0 - distributed;
+        for (share, weight) in shares.iter_mut().zip(weights) {
+            if leftover.is_zero() {
+                break;
+            }
+            if *weight > 0 {
+                share.0 += U256::from(1u8);
+                leftover -= U256::from(1u8);
+            }
+        }
+        shares
+    }
+}
+
+fn scale(decimals: u8) -> Result<U256, AmountError> {
+    if decimals > MAX_TOKEN_DECIMALS {
+        return Err(AmountError::UnsupportedDecimals(decimals));
+    }
+    Ok(U256::from(10u8).pow(U256::from(decimals)))
+}
+
+fn parse_digits(digits: &str) -> Result<U256, AmountError> {
+    if digits.is_empty() {
+        return Ok(U256::ZERO);
+    }
+    U256::from_str_radix(digits, 10).map_err(|_| AmountError::Overflow)
+}
+
+impl fmt::Display for TokenAmount {
+    /// Base units, in decimal.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for TokenAmount {
+    type Err = AmountError;
+
+    /// Parses a whole number of base units.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        TokenAmount::parse_units(value, 0)
+    }
+}
+
+impl From<u64> for TokenAmount {
+    fn from(units: u64) -> Self {
+        TokenAmount(U256::from(units))
+    }
+}
+
+impl std::iter::Sum for TokenAmount {
+    /// Saturates instead of overflowing.
+    fn sum<I: Iterator<Item = TokenAmount>>(iter: I) -> Self {
+        iter.fold(TokenAmount::ZERO, TokenAmount::saturating_add)
+    }
+}
+
+impl Serialize for TokenAmount {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for TokenAmount {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // bincode cannot describe its own data, so only self-describing formats are asked
+        // for whatever value is there.
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(TokenAmountVisitor)
+        } else {
+            deserializer.deserialize_str(TokenAmountVisitor)
+        }
+    }
+}
+
+struct TokenAmountVisitor;
+
+impl<'de> de::Visitor<'de> for TokenAmountVisitor {
+    type Value = TokenAmount;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a decimal string of token base units")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<TokenAmount, E> {
+        TokenAmount::from_str(value).map_err(E::custom)
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<TokenAmount, E> {
+        Ok(TokenAmount::from(value))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<TokenAmount, E> {
+        u64::try_from(value)
+            .map(TokenAmount::from)
+            .map_err(|_| E::custom("token amounts cannot be negative"))
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<TokenAmount, E> {
+        if !value.is_finite() || value < 0.0 || value >= u128::MAX as f64 {
+            return Err(E::custom(format!("{} is not a token amount", value)));
+        }
+        Ok(TokenAmount(U256::from(value as u128)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const U256_MAX: &str =
+        "115792089237316195423570985008687907853269984665640564039457584007913129639935";
+
+    fn units(value: u128) -> TokenAmount {
+        TokenAmount(U256::from(value))
+    }
+
+    #[test]
+    fn parses_whole_token_amounts() {
+        assert_eq!(TokenAmount::parse_units("12.5", 6), Ok(units(12_500_000)));
+        assert_eq!(
+            TokenAmount::parse_units("12.5", 18),
+            Ok(units(12_500_000_000_000_000_000))
+        );
+        assert_eq!(TokenAmount::parse_units(" 7 ", 0), Ok(units(7)));
+        assert_eq!(TokenAmount::parse_units("0.000001", 6), Ok(units(1)));
+    }
+
+    #[test]
+    fn parses_a_missing_whole_or_fraction_part() {
+        assert_eq!(TokenAmount::parse_units(".5", 6), Ok(units(500_000)));
+        assert_eq!(TokenAmount::parse_units("1.", 6), Ok(units(1_000_000)));
+        assert_eq!(
+            TokenAmount::parse_units(".", 6),
+            Err(AmountError::Invalid(".".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_more_decimals_than_the_token_has() {
+        assert_eq!(
+            TokenAmount::parse_units("1.1234567", 6),
+            Err(AmountError::TooManyDecimals {
+                found: 7,
+                allowed: 6
+            })
+        );
+        assert_eq!(
+            TokenAmount::parse_units("0.5", 0),
+            Err(AmountError::TooManyDecimals {
+                found: 1,
+                allowed: 0
+            })
+        );
+        // Zeros past the token's precision change nothing.
+        assert_eq!(
+            TokenAmount::parse_units("1.5000000", 6),
+            Ok(units(1_500_000))
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_amounts() {
+        assert_eq!(TokenAmount::parse_units("  ", 6), Err(AmountError::Empty));
+        for value in ["-1", "1.2.3", "1e6", "12,5", "0x10"] {
+            assert_eq!(
+                TokenAmount::parse_units(value, 6),
+                Err(AmountError::Invalid(value.to_string()))
+            );
+        }
+        assert_eq!(
+            TokenAmount::parse_units("1", MAX_TOKEN_DECIMALS + 1),
+            Err(AmountError::UnsupportedDecimals(MAX_TOKEN_DECIMALS + 1))
+        );
+    }
+
+    #[test]
+    fn rejects_amounts_that_overflow() {
+        assert_eq!(
+            TokenAmount::parse_units(U256_MAX, 0),
+            Ok(TokenAmount(U256::MAX))
+        );
+        assert_eq!(
+            TokenAmount::parse_units(&format!("{}0", U256_MAX), 0),
+            Err(AmountError::Overflow)
+        );
+        // Fits as a whole number, but not once scaled to base units.
+        assert_eq!(
+            TokenAmount::parse_units(U256_MAX, 18),
+            Err(AmountError::Overflow)
+        );
+    }
+
+    #[test]
+    fn formats_back_to_what_was_parsed() {
+        for decimals in [0, 6, 18] {
+            for value in ["0", "1", "12", "1000000"] {
+                let amount = TokenAmount::parse_units(value, decimals).unwrap();
+                assert_eq!(amount.format_units(decimals), value);
+            }
+        }
+        for decimals in [6, 18] {
+            for value in ["12.5", "0.000001", "123456.789"] {
+                let amount = TokenAmount::parse_units(value, decimals).unwrap();
+                assert_eq!(amount.format_units(decimals), value);
+            }
+        }
+        assert_eq!(units(1).format_units(18), "0.000000000000000001");
+        assert_eq!(
+            TokenAmount::parse_units("12.50", 6)
+                .unwrap()
+                .format_units(6),
+            "12.5"
+        );
+    }
+
+    #[test]
+    fn splits_remainders_to_the_earliest_shares() {
+        assert_eq!(
+            units(10).split(&[1, 1, 1]),
+            vec![units(4), units(3), units(3)]
+        );
+        assert_eq!(
+            units(100).split(&[3, 2, 1]),
+            vec![units(51), units(33), units(16)]
+        );
+        assert_eq!(
+            units(2).split(&[1, 1, 1]),
+            vec![units(1), units(1), units(0)]
+        );
+    }
+
+    #[test]
+    fn gives_nothing_to_zero_weights() {
+        assert_eq!(
+            units(10).split(&[0, 2, 0, 1]),
+            vec![units(0), units(7), units(0), units(3)]
+        );
+        assert_eq!(units(10).split(&[0, 0]), vec![TokenAmount::ZERO; 2]);
+        assert!(units(10).split(&[]).is_empty());
+    }
+
+    #[test]
+    fn splits_add_up_to_the_pool_every_time() {
+        let pools = [
+            units(0),
+            units(1),
+            units(999_999_999_999_999_999),
+            TokenAmount::parse_units("12.5", 18).unwrap(),
+            TokenAmount(U256::MAX),
+        ];
+        let weights: [&[u64]; 4] = [&[5, 4, 3, 2, 1], &[1, 1, 1, 1, 1], &[7], &[u64::MAX, 1, 0]];
+        for pool in pools {
+            for weights in weights {
+                let shares = pool.split(weights);
+                assert_eq!(shares.len(), weights.len());
+                let total = shares
+                    .iter()
+                    .try_fold(TokenAmount::ZERO, |sum, share| sum.checked_add(*share));
+                assert_eq!(total, Some(pool));
+                assert_eq!(pool.split(weights), shares);
+            }
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use crate::amount::TokenAmount;
+use serde::{Deserialize, Serialize};
+
+/// One line of a hackathon's judging rubric.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RubricCriterion {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    /// Relative weight of this criterion in a team's final score.
+    pub weight: f64,
+    pub max_score: u32,
+}
+
+/// A prize pool that teams opt into when they submit; `rewards[0]` goes to first place.
+/// Rewards are in base units of the reward token.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PrizeTrack {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub rewards: Vec<TokenAmount>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CriterionScore {
+    pub criterion_id: String,
+    pub score: u32,
+}
+
+/// A single judge's marks for a team's submission.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JudgeScore {
+    pub judge_uuid: String,
+    pub scores: Vec<CriterionScore>,
+    pub comment: String,
+    pub scored_at: i64,
+}
+
+/// A submitted team as seen by the coprocessor.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TeamOffchainData {
+    pub uuid: String,
+    pub member_wallets: Vec<String>,
+    pub track_ids: Vec<String>,
+    pub scores: Vec<JudgeScore>,
+}
+
+/// The slice of a hackathon that is shipped to the coprocessor for settlement.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HackathonOffchainData {
+    pub uuid: String,
+    pub protocol: String,
+    pub rubric: Vec<RubricCriterion>,
+    pub prize_tracks: Vec<PrizeTrack>,
+    pub judges: Vec<String>,
+    pub teams: Vec<TeamOffchainData>,
+}
+
+impl PrizeTrack {
+    pub fn total_reward(&self) -> TokenAmount {
+        self.rewards.iter().copied().sum()
+    }
+}
+
+impl JudgeScore {
+    /// This judge's mark for the team as a percentage, weighting each criterion by the
+    /// rubric. Scores above a criterion's maximum are clamped and unknown criteria ignored.
+    pub fn weighted_percentage(&self, rubric: &[RubricCriterion]) -> f64 {
+        let total_weight: f64 = rubric.iter().map(|criterion| criterion.weight).sum();
+        if total_weight <= 0.0 {
+            return 0.0;
+        }
+        let earned: f64 = rubric
+            .iter()
+            .filter(|criterion| criterion.max_score > 0)
+            .map(|criterion| {
+                let score = self
+                    .scores
+                    .iter()
+                    .find(|score| score.criterion_id == criterion.id)
+                    .map_or(0, |score| score.score.min(criterion.max_score));
+                criterion.weight * score as f64 / criterion.max_score as f64
+            })
+            .sum();
+        earned / total_weight * 100.0
+    }
+}
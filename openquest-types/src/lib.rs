@@ -0,0 +1,35 @@
+//! Types shared by the OpenQuest server and the Coprocessor program.
+//!
+//! Both sides only meet through the bytes produced by [`wire::encode_payload`] (or a
+//! reference to them from [`wire::encode_blob_ref`]), so anything that crosses that
+//! boundary lives here instead of being copied by hand.
+
+pub mod amount;
+pub mod hackathon;
+pub mod merkle;
+pub mod payload;
+pub mod quiz;
+pub mod response;
+pub mod validation;
+pub mod wire;
+
+pub use amount::{AmountError, TokenAmount, MAX_TOKEN_DECIMALS};
+pub use hackathon::{
+    CriterionScore, HackathonOffchainData, JudgeScore, PrizeTrack, RubricCriterion,
+    TeamOffchainData,
+};
+pub use merkle::{verify_proof, MerkleTree};
+pub use payload::GradingPayload;
+pub use quiz::{
+    AttemptWindow, DifficultyLevel, OptionIndex, Participant, Question, QuizAnswer,
+    QuizOffchainData, QuizOption, RewardType,
+};
+pub use response::{
+    from_fixed, parse_address, to_fixed, AnswerAudit, ParticipantAudit, QuizResponse, RewardData,
+    SCORE_DECIMALS,
+};
+pub use validation::{validate_payload, InputProblem, RejectionReport};
+pub use wire::{
+    blob_ref, decode_payload, encode_blob_ref, encode_payload, keccak256, verify_blob, BlobRef,
+    PayloadEncoding, WireError, WIRE_FORMAT_VERSION,
+};
@@ -0,0 +1,109 @@
+use crate::response::{parse_address, RewardData};
+use crate::wire::keccak256;
+
+pub type Hash = [u8; 32];
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+/// The leaf committing to one result: keccak256 of `0x00 || address || reward || points ||
+/// score`, the Solidity `abi.encodePacked(uint8(0), user, reward, points, score)` of the
+/// values published in the notice. An address that does not parse, which no notice can
+/// carry, is committed as the zero address.
+pub fn result_leaf(result: &RewardData) -> Hash {
+    let address = parse_address(&result.user_address).unwrap_or([0; 20]);
+    let mut preimage = Vec::with_capacity(1 + 20 + 3 * 32);
+    preimage.push(LEAF_PREFIX);
+    preimage.extend_from_slice(&address);
+    preimage.extend_from_slice(&result.reward_amount.base_units().to_be_bytes::<32>());
+    preimage.extend_from_slice(&result.fixed_points().to_be_bytes::<32>());
+    preimage.extend_from_slice(&result.fixed_score().to_be_bytes::<32>());
+    keccak256(&preimage)
+}
+
+/// keccak256 of `0x01 || lower || higher`. Pairs are sorted, so a proof needs no path bits.
+pub fn hash_pair(a: &Hash, b: &Hash) -> Hash {
+    let (lower, higher) = if a <= b { (a, b) } else { (b, a) };
+    let mut preimage = [0u8; 65];
+    preimage[0] = NODE_PREFIX;
+    preimage[1..33].copy_from_slice(lower);
+    preimage[33..].copy_from_slice(higher);
+    keccak256(&preimage)
+}
+
+/// A binary Merkle tree over result leaves. A node without a sibling is carried up to the
+/// next level unchanged; a tree without leaves has an all-zero root.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MerkleTree {
+    levels: Vec<Vec<Hash>>,
+}
+
+impl MerkleTree {
+    pub fn new(leaves: Vec<Hash>) -> Self {
+        let mut levels = vec![leaves];
+        while levels[levels.len() - 1].len() > 1 {
+            let next = levels[levels.len() - 1]
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => hash_pair(a, b),
+                    [a] => *a,
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(next);
+        }
+        MerkleTree { levels }
+    }
+
+    /// The tree over `results`, in the order they are published in the notice.
+    pub fn from_results(results: &[RewardData]) -> Self {
+        MerkleTree::new(results.iter().map(result_leaf).collect())
+    }
+
+    pub fn leaves(&self) -> &[Hash] {
+        &self.levels[0]
+    }
+
+    pub fn root(&self) -> Hash {
+        self.levels[self.levels.len() - 1]
+            .first()
+            .copied()
+            .unwrap_or([0; 32])
+    }
+
+    /// The siblings from the leaf at `index` up to the root, or `None` past the last leaf.
+    pub fn proof(&self, index: usize) -> Option<Vec<Hash>> {
+        if index >= self.leaves().len() {
+            return None;
+        }
+        let mut proof = Vec::new();
+        let mut index = index;
+        for level in self.levels[..self.levels.len() - 1].iter() {
+            if let Some(sibling) = level.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            index /= 2;
+        }
+        Some(proof)
+    }
+}
+
+/// Whether `proof` leads from `leaf` to `root`.
+pub fn verify_proof(root: &Hash, leaf: &Hash, proof: &[Hash]) -> bool {
+    let computed = proof
+        .iter()
+        .fold(*leaf, |node, sibling| hash_pair(&node, sibling));
+    computed == *root
+}
+
+/// `0x`-prefixed lowercase hex of `hash`, as it appears in notices and API responses.
+pub fn hash_to_hex(hash: &Hash) -> String {
+    format!("0x{}", hex::encode(hash))
+}
+
+pub fn hash_from_hex(value: &str) -> Result<Hash, String> {
+    let bytes = hex::decode(value.trim_start_matches("0x")).map_err(|e| e.to_string())?;
+    bytes
+        .try_into()
+        .map_err(|bytes: Vec<u8>| format!("expected 32 bytes, got {}", bytes.len()))
+}
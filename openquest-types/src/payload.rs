@@ -0,0 +1,19 @@
+use crate::hackathon::HackathonOffchainData;
+use crate::quiz::QuizOffchainData;
+use serde::{Deserialize, Serialize};
+
+/// Everything the server can ask the coprocessor to settle through `gradeQuiz`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum GradingPayload {
+    Quiz(QuizOffchainData),
+    Hackathon(HackathonOffchainData),
+}
+
+impl GradingPayload {
+    pub fn uuid(&self) -> &str {
+        match self {
+            GradingPayload::Quiz(quiz) => &quiz.uuid,
+            GradingPayload::Hackathon(hackathon) => &hackathon.uuid,
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use crate::amount::TokenAmount;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum RewardType {
+    DistributedByRankToTopFive,
+    DistributedEqullyToTopFive,
+    DistributedByLottery,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum DifficultyLevel {
+    Easy,
+    Medium,
+    Hard,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum OptionIndex {
+    A,
+    B,
+    C,
+    D,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Question {
+    pub id: usize, // Question index
+    pub question_text: String,
+    pub options: [QuizOption; 4],    // Options A-D
+    pub correct_answer: OptionIndex, // 'A', 'B', 'C', or 'D'
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QuizOption {
+    pub text: String,
+    pub option_index: OptionIndex,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QuizAnswer {
+    pub question_id: usize,
+    pub answer: OptionIndex,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Participant {
+    pub user_uuid: String,
+    pub wallet_address: String,
+    pub score: i64,
+    pub answered_questions: Vec<QuizAnswer>, // Question index and answer index
+    pub submission_time: i64,
+    pub start_time: i64,
+    pub reward: TokenAmount,
+}
+
+/// When a participant's attempt closes.
+///
+/// Both the server (when accepting a submission) and the coprocessor (when grading)
+/// go through this, so they agree on which submissions are late.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct AttemptWindow {
+    /// End of the overall quiz window.
+    pub end_time: i64,
+    /// How long each participant has from joining, if the quiz limits attempts.
+    pub attempt_duration_in_sec: Option<i64>,
+    /// Slack allowed past the deadline for network and clock drift.
+    pub grace_in_sec: i64,
+}
+
+/// The slice of a quiz that is shipped to the coprocessor for grading.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QuizOffchainData {
+    pub uuid: String,
+    pub protocol: String,
+    pub num_questions: usize,
+    pub questions: Vec<Question>,
+    /// Amounts are in base units of the reward token.
+    pub total_reward: TokenAmount,
+    pub max_reward_per_user: TokenAmount,
+    pub participants: Vec<Participant>,
+    pub reward_type: RewardType,
+    pub difficulty: DifficultyLevel,
+    pub attempt_window: AttemptWindow,
+}
+
+impl RewardType {
+    pub fn to_string(&self) -> &'static str {
+        match self {
+            RewardType::DistributedByRankToTopFive => "DistributedByRankToTopFive",
+            RewardType::DistributedEqullyToTopFive => "DistributedEqullyToTopFive",
+            RewardType::DistributedByLottery => "DistributedByLottery",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<RewardType> {
+        match value.to_lowercase().as_str() {
+            "distributed_by_rank" => Some(RewardType::DistributedByRankToTopFive),
+            "distributed_equally" => Some(RewardType::DistributedEqullyToTopFive),
+            "distributed_by_lottery" => Some(RewardType::DistributedByLottery),
+            _ => None,
+        }
+    }
+}
+
+impl DifficultyLevel {
+    pub fn to_string(&self) -> &'static str {
+        match self {
+            DifficultyLevel::Easy => "Easy",
+            DifficultyLevel::Medium => "Medium",
+            DifficultyLevel::Hard => "Hard",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<DifficultyLevel> {
+        match value.to_lowercase().as_str() {
+            "easy" => Some(DifficultyLevel::Easy),
+            "medium" => Some(DifficultyLevel::Medium),
+            "hard" => Some(DifficultyLevel::Hard),
+            _ => None,
+        }
+    }
+
+    /// Weight applied to the score percentage when awarding leaderboard points.
+    pub fn multiplier(&self) -> f64 {
+        match self {
+            DifficultyLevel::Easy => 1.0,
+            DifficultyLevel::Medium => 1.5,
+            DifficultyLevel::Hard => 2.0,
+        }
+    }
+}
+
+impl OptionIndex {
+    pub fn to_string(&self) -> &'static str {
+        match self {
+            OptionIndex::A => "A",
+            OptionIndex::B => "B",
+            OptionIndex::C => "C",
+            OptionIndex::D => "D",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<OptionIndex> {
+        match value.to_lowercase().as_str() {
+            "a" => Some(OptionIndex::A),
+            "b" => Some(OptionIndex::B),
+            "c" => Some(OptionIndex::C),
+            "d" => Some(OptionIndex::D),
+            _ => None,
+        }
+    }
+}
+
+impl AttemptWindow {
+    /// Latest submission time accepted for an attempt started at `attempt_start`.
+    pub fn deadline(&self, attempt_start: i64) -> i64 {
+        let closes_at = match self.attempt_duration_in_sec {
+            Some(duration) => (attempt_start + duration).min(self.end_time),
+            None => self.end_time,
+        };
+        closes_at + self.grace_in_sec
+    }
+
+    pub fn is_late(&self, participant: &Participant) -> bool {
+        participant.submission_time > self.deadline(participant.start_time)
+    }
+}
+
+impl Participant {
+    /// Participants who joined but never submitted keep a zero submission time.
+    pub fn has_submitted(&self) -> bool {
+        self.submission_time > 0
+    }
+
+    pub fn submit_quiz(
+        &mut self,
+        answered_questions: Vec<QuizAnswer>,
+        submission_time: i64,
+    ) -> bool {
+        self.answered_questions = answered_questions;
+        self.submission_time = submission_time;
+        true
+    }
+}
@@ -0,0 +1,92 @@
+use crate::amount::TokenAmount;
+use crate::merkle::{hash_to_hex, MerkleTree};
+use crate::quiz::OptionIndex;
+use ruint::aliases::U256;
+use serde::{Deserialize, Serialize};
+
+/// Decimals of the fixed-point integers scores and leaderboard points are published as in
+/// the ABI-encoded notice, where Solidity has no floating point.
+pub const SCORE_DECIMALS: u32 = 2;
+
+/// Grading outcome for a single participant, as published in the coprocessor notice.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RewardData {
+    pub user_address: String,
+    pub reward_amount: TokenAmount,
+    pub leader_boar_addition: f64,
+    pub quiz_score: f64,
+}
+
+impl RewardData {
+    /// `quiz_score` as published in the notice.
+    pub fn fixed_score(&self) -> U256 {
+        to_fixed(self.quiz_score)
+    }
+
+    /// `leader_boar_addition` as published in the notice.
+    pub fn fixed_points(&self) -> U256 {
+        to_fixed(self.leader_boar_addition)
+    }
+}
+
+/// `value` as an integer with `SCORE_DECIMALS` decimals, rounded to the nearest unit.
+/// Negative values are published as zero.
+pub fn to_fixed(value: f64) -> U256 {
+    let scaled = (value * 10f64.powi(SCORE_DECIMALS as i32)).round();
+    U256::from(scaled.max(0.0) as u128)
+}
+
+pub fn from_fixed(value: U256) -> f64 {
+    let units = u128::try_from(value).unwrap_or(u128::MAX);
+    units as f64 / 10f64.powi(SCORE_DECIMALS as i32)
+}
+
+/// The 20 bytes of a `0x` hex wallet address, in any case.
+pub fn parse_address(value: &str) -> Result<[u8; 20], String> {
+    let digits = value
+        .strip_prefix("0x")
+        .ok_or_else(|| format!("{} is not 0x-prefixed", value))?;
+    let bytes = hex::decode(digits).map_err(|e| format!("{} is not hex: {}", value, e))?;
+    bytes
+        .try_into()
+        .map_err(|_| format!("{} is not a 20-byte address", value))
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QuizResponse {
+    pub uuid: String,
+    pub protocol: String,
+    pub results: Vec<RewardData>,
+    /// `0x` hex root of the [`MerkleTree`] over `results`, so one result can be proven
+    /// against the notice without the others.
+    #[serde(default)]
+    pub results_root: Option<String>,
+}
+
+impl QuizResponse {
+    /// Sets `results_root` to the root of the tree over the current results.
+    pub fn commit_results(mut self) -> Self {
+        self.results_root = Some(hash_to_hex(&MerkleTree::from_results(&self.results).root()));
+        self
+    }
+}
+
+/// How one answer was graded.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AnswerAudit {
+    pub question_id: usize,
+    pub answer: OptionIndex,
+    pub correct: bool,
+}
+
+/// How one participant's score was computed, published by the coprocessor as a report
+/// next to the notice.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ParticipantAudit {
+    pub quiz_uuid: String,
+    pub user_address: String,
+    /// Late submissions are listed but not graded, and score nothing.
+    pub late: bool,
+    pub answers: Vec<AnswerAudit>,
+    pub score: i64,
+}
@@ -0,0 +1,243 @@
+use crate::hackathon::HackathonOffchainData;
+use crate::payload::GradingPayload;
+use crate::quiz::QuizOffchainData;
+use crate::response::parse_address;
+use crate::wire::WireError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+
+/// One thing wrong with an input the coprocessor was asked to grade.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum InputProblem {
+    MissingPayload,
+    InvalidHex {
+        message: String,
+    },
+    UnsupportedVersion {
+        found: u16,
+        expected: u16,
+    },
+    /// Not a grading payload: bad magic, an unknown encoding or an undecodable body.
+    Undecodable {
+        message: String,
+    },
+    /// A blob reference that could not be fetched or did not match its hash.
+    BlobUnavailable {
+        message: String,
+    },
+    NoQuestions,
+    NoParticipants,
+    /// A participant (or hackathon team or member) listed more than once.
+    DuplicateParticipant {
+        participant: String,
+    },
+    /// A wallet that cannot be published as an `address` in the notice.
+    InvalidWallet {
+        participant: String,
+        wallet: String,
+    },
+    /// An answer to a question the quiz does not have.
+    UnknownQuestion {
+        participant: String,
+        question_id: usize,
+    },
+    /// A question answered more than once by the same participant.
+    DuplicateAnswer {
+        participant: String,
+        question_id: usize,
+    },
+}
+
+/// Why an input was rejected, published by the coprocessor as a report.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RejectionReport {
+    /// The quiz or hackathon, when the payload decoded far enough to tell.
+    pub uuid: Option<String>,
+    pub problems: Vec<InputProblem>,
+}
+
+impl fmt::Display for InputProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputProblem::MissingPayload => write!(f, "request has no payload"),
+            InputProblem::InvalidHex { message } => {
+                write!(f, "payload is not valid hex: {}", message)
+            }
+            InputProblem::UnsupportedVersion { found, expected } => write!(
+                f,
+                "unsupported wire format version {} (expected {})",
+                found, expected
+            ),
+            InputProblem::Undecodable { message } => write!(f, "{}", message),
+            InputProblem::BlobUnavailable { message } => {
+                write!(f, "payload blob is unavailable: {}", message)
+            }
+            InputProblem::NoQuestions => write!(f, "quiz has no questions"),
+            InputProblem::NoParticipants => write!(f, "nobody took part"),
+            InputProblem::DuplicateParticipant { participant } => {
+                write!(f, "{} is listed more than once", participant)
+            }
+            InputProblem::InvalidWallet {
+                participant,
+                wallet,
+            } => write!(
+                f,
+                "{} has an invalid wallet address {}",
+                participant, wallet
+            ),
+            InputProblem::UnknownQuestion {
+                participant,
+                question_id,
+            } => write!(
+                f,
+                "{} answered question {}, which the quiz does not have",
+                participant, question_id
+            ),
+            InputProblem::DuplicateAnswer {
+                participant,
+                question_id,
+            } => write!(
+                f,
+                "{} answered question {} more than once",
+                participant, question_id
+            ),
+        }
+    }
+}
+
+impl From<WireError> for InputProblem {
+    fn from(error: WireError) -> Self {
+        match error {
+            WireError::UnsupportedVersion { found, expected } => {
+                InputProblem::UnsupportedVersion { found, expected }
+            }
+            WireError::UnresolvedBlob | WireError::BlobMismatch => InputProblem::BlobUnavailable {
+                message: error.to_string(),
+            },
+            other => InputProblem::Undecodable {
+                message: other.to_string(),
+            },
+        }
+    }
+}
+
+impl RejectionReport {
+    pub fn new(uuid: Option<String>, problems: Vec<InputProblem>) -> Self {
+        RejectionReport { uuid, problems }
+    }
+
+    /// The JSON bytes published as the report.
+    pub fn encode(&self) -> Vec<u8> {
+        serde_json::to_vec(self).unwrap_or_default()
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, String> {
+        serde_json::from_slice(bytes).map_err(|e| e.to_string())
+    }
+}
+
+impl From<InputProblem> for RejectionReport {
+    fn from(problem: InputProblem) -> Self {
+        RejectionReport::new(None, vec![problem])
+    }
+}
+
+impl fmt::Display for RejectionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let problems: Vec<String> = self.problems.iter().map(|p| p.to_string()).collect();
+        write!(f, "{}", problems.join("; "))
+    }
+}
+
+/// Checks a decoded payload before it is graded, listing every problem found. The server
+/// runs the same checks before sending a payload on-chain.
+pub fn validate_payload(payload: &GradingPayload) -> Result<(), RejectionReport> {
+    let problems = match payload {
+        GradingPayload::Quiz(quiz) => quiz_problems(quiz),
+        GradingPayload::Hackathon(hackathon) => hackathon_problems(hackathon),
+    };
+    if problems.is_empty() {
+        return Ok(());
+    }
+    Err(RejectionReport::new(
+        Some(payload.uuid().to_string()),
+        problems,
+    ))
+}
+
+fn quiz_problems(quiz: &QuizOffchainData) -> Vec<InputProblem> {
+    let mut problems = Vec::new();
+    if quiz.questions.is_empty() {
+        problems.push(InputProblem::NoQuestions);
+    }
+    if quiz.participants.is_empty() {
+        problems.push(InputProblem::NoParticipants);
+    }
+
+    let question_ids: HashSet<usize> = quiz.questions.iter().map(|q| q.id).collect();
+    let mut users = HashSet::new();
+    let mut wallets = HashSet::new();
+    for participant in quiz.participants.iter() {
+        if !users.insert(participant.user_uuid.as_str())
+            || !wallets.insert(participant.wallet_address.to_lowercase())
+        {
+            problems.push(InputProblem::DuplicateParticipant {
+                participant: participant.user_uuid.clone(),
+            });
+        }
+        if parse_address(&participant.wallet_address).is_err() {
+            problems.push(InputProblem::InvalidWallet {
+                participant: participant.user_uuid.clone(),
+                wallet: participant.wallet_address.clone(),
+            });
+        }
+        let mut answered = HashSet::new();
+        for answer in participant.answered_questions.iter() {
+            if !question_ids.contains(&answer.question_id) {
+                problems.push(InputProblem::UnknownQuestion {
+                    participant: participant.user_uuid.clone(),
+                    question_id: answer.question_id,
+                });
+            } else if !answered.insert(answer.question_id) {
+                problems.push(InputProblem::DuplicateAnswer {
+                    participant: participant.user_uuid.clone(),
+                    question_id: answer.question_id,
+                });
+            }
+        }
+    }
+    problems
+}
+
+fn hackathon_problems(hackathon: &HackathonOffchainData) -> Vec<InputProblem> {
+    let mut problems = Vec::new();
+    if hackathon.teams.is_empty() {
+        problems.push(InputProblem::NoParticipants);
+    }
+
+    let mut teams = HashSet::new();
+    let mut wallets = HashSet::new();
+    for team in hackathon.teams.iter() {
+        if !teams.insert(team.uuid.as_str()) {
+            problems.push(InputProblem::DuplicateParticipant {
+                participant: team.uuid.clone(),
+            });
+        }
+        for wallet in team.member_wallets.iter() {
+            if !wallets.insert(wallet.to_lowercase()) {
+                problems.push(InputProblem::DuplicateParticipant {
+                    participant: wallet.clone(),
+                });
+            }
+            if parse_address(wallet).is_err() {
+                problems.push(InputProblem::InvalidWallet {
+                    participant: team.uuid.clone(),
+                    wallet: wallet.clone(),
+                });
+            }
+        }
+    }
+    problems
+}
@@ -0,0 +1,230 @@
+use serde::{de::DeserializeOwned, Serialize};
+use sha3::{Digest, Keccak256};
+use std::fmt;
+use std::io::Read;
+
+/// Version of the payload layout exchanged between the server and the coprocessor.
+///
+/// Bump this whenever a type that crosses the wire changes shape, so that a server
+/// and a coprocessor image built from different commits refuse each other's payloads
+/// instead of misdecoding them.
+pub const WIRE_FORMAT_VERSION: u16 = 5;
+
+/// Largest body a compressed payload may inflate to, so a small payload cannot exhaust
+/// the coprocessor's memory.
+pub const MAX_DECOMPRESSED_LEN: u64 = 64 * 1024 * 1024;
+
+const MAGIC: [u8; 2] = *b"OQ";
+const HEADER_LEN: usize = MAGIC.len() + 3;
+const ZSTD_LEVEL: i32 = 19;
+const BLOB_REF_LEN: usize = 32 + 8;
+
+/// How the bytes after the `magic || version || encoding` header are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadEncoding {
+    /// Plain bincode.
+    Raw,
+    /// zstd-compressed bincode.
+    Zstd,
+    /// A [`BlobRef`] to a payload published off-chain.
+    BlobRef,
+}
+
+/// Points at a payload kept off-chain by the keccak256 hash of its bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlobRef {
+    pub hash: [u8; 32],
+    pub len: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WireError {
+    Truncated,
+    BadMagic,
+    UnsupportedVersion {
+        found: u16,
+        expected: u16,
+    },
+    UnknownEncoding(u8),
+    /// The payload is a [`BlobRef`] that has to be resolved with [`verify_blob`] first.
+    UnresolvedBlob,
+    BlobMismatch,
+    TooLarge,
+    Encode(String),
+    Decode(String),
+}
+
+impl fmt::Display for WireError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WireError::Truncated => write!(f, "payload is shorter than the wire header"),
+            WireError::BadMagic => {
+                write!(f, "payload does not start with the OpenQuest magic bytes")
+            }
+            WireError::UnsupportedVersion { found, expected } => write!(
+                f,
+                "unsupported wire format version {} (expected {})",
+                found, expected
+            ),
+            WireError::UnknownEncoding(encoding) => {
+                write!(f, "unknown payload encoding {}", encoding)
+            }
+            WireError::UnresolvedBlob => {
+                write!(
+                    f,
+                    "payload refers to an off-chain blob that was not fetched"
+                )
+            }
+            WireError::BlobMismatch => {
+                write!(
+                    f,
+                    "blob does not match the hash and length it was referenced by"
+                )
+            }
+            WireError::TooLarge => {
+                write!(f, "payload inflates past {} bytes", MAX_DECOMPRESSED_LEN)
+            }
+            WireError::Encode(e) => write!(f, "failed to encode payload: {}", e),
+            WireError::Decode(e) => write!(f, "failed to decode payload: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for WireError {}
+
+impl PayloadEncoding {
+    fn to_byte(self) -> u8 {
+        match self {
+            PayloadEncoding::Raw => 0,
+            PayloadEncoding::Zstd => 1,
+            PayloadEncoding::BlobRef => 2,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Result<Self, WireError> {
+        match byte {
+            0 => Ok(PayloadEncoding::Raw),
+            1 => Ok(PayloadEncoding::Zstd),
+            2 => Ok(PayloadEncoding::BlobRef),
+            other => Err(WireError::UnknownEncoding(other)),
+        }
+    }
+}
+
+impl BlobRef {
+    /// The reference to `blob`.
+    pub fn of(blob: &[u8]) -> Self {
+        BlobRef {
+            hash: keccak256(blob),
+            len: blob.len() as u64,
+        }
+    }
+
+    /// Lowercase hex of the hash without a `0x` prefix, which names the blob in a store.
+    pub fn hash_hex(&self) -> String {
+        self.hash
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+}
+
+pub fn keccak256(bytes: &[u8]) -> [u8; 32] {
+    Keccak256::digest(bytes).into()
+}
+
+/// Serializes `data` with bincode and compresses it with zstd behind a
+/// `magic || version || encoding` header.
+pub fn encode_payload<T: Serialize>(data: &T) -> Result<Vec<u8>, WireError> {
+    let body = bincode::serialize(data).map_err(|e| WireError::Encode(e.to_string()))?;
+    let compressed = zstd::stream::encode_all(body.as_slice(), ZSTD_LEVEL)
+        .map_err(|e| WireError::Encode(e.to_string()))?;
+    Ok(with_header(PayloadEncoding::Zstd, &compressed))
+}
+
+/// A payload that only carries the reference to `blob`, itself produced by
+/// [`encode_payload`] and published off-chain.
+pub fn encode_blob_ref(blob: &[u8]) -> Vec<u8> {
+    let reference = BlobRef::of(blob);
+    let mut body = Vec::with_capacity(BLOB_REF_LEN);
+    body.extend_from_slice(&reference.hash);
+    body.extend_from_slice(&reference.len.to_be_bytes());
+    with_header(PayloadEncoding::BlobRef, &body)
+}
+
+/// The blob `bytes` refers to, if it is a reference rather than the payload itself.
+pub fn blob_ref(bytes: &[u8]) -> Result<Option<BlobRef>, WireError> {
+    let (encoding, body) = split_header(bytes)?;
+    if encoding != PayloadEncoding::BlobRef {
+        return Ok(None);
+    }
+    if body.len() != BLOB_REF_LEN {
+        return Err(WireError::Truncated);
+    }
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&body[..32]);
+    let mut len = [0u8; 8];
+    len.copy_from_slice(&body[32..]);
+    Ok(Some(BlobRef {
+        hash,
+        len: u64::from_be_bytes(len),
+    }))
+}
+
+/// Checks that a fetched `blob` is the one `reference` points at.
+pub fn verify_blob(reference: &BlobRef, blob: &[u8]) -> Result<(), WireError> {
+    if BlobRef::of(blob) != *reference {
+        return Err(WireError::BlobMismatch);
+    }
+    Ok(())
+}
+
+/// Checks the header written by [`encode_payload`] and deserializes the body.
+pub fn decode_payload<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, WireError> {
+    let (encoding, body) = split_header(bytes)?;
+    match encoding {
+        PayloadEncoding::Raw => {
+            bincode::deserialize(body).map_err(|e| WireError::Decode(e.to_string()))
+        }
+        PayloadEncoding::Zstd => {
+            let decoder =
+                zstd::stream::Decoder::new(body).map_err(|e| WireError::Decode(e.to_string()))?;
+            let mut inflated = Vec::new();
+            decoder
+                .take(MAX_DECOMPRESSED_LEN + 1)
+                .read_to_end(&mut inflated)
+                .map_err(|e| WireError::Decode(e.to_string()))?;
+            if inflated.len() as u64 > MAX_DECOMPRESSED_LEN {
+                return Err(WireError::TooLarge);
+            }
+            bincode::deserialize(&inflated).map_err(|e| WireError::Decode(e.to_string()))
+        }
+        PayloadEncoding::BlobRef => Err(WireError::UnresolvedBlob),
+    }
+}
+
+fn with_header(encoding: PayloadEncoding, body: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(HEADER_LEN + body.len());
+    bytes.extend_from_slice(&MAGIC);
+    bytes.extend_from_slice(&WIRE_FORMAT_VERSION.to_be_bytes());
+    bytes.push(encoding.to_byte());
+    bytes.extend_from_slice(body);
+    bytes
+}
+
+fn split_header(bytes: &[u8]) -> Result<(PayloadEncoding, &[u8]), WireError> {
+    if bytes.len() < HEADER_LEN {
+        return Err(WireError::Truncated);
+    }
+    if bytes[..MAGIC.len()] != MAGIC {
+        return Err(WireError::BadMagic);
+    }
+    let version = u16::from_be_bytes([bytes[2], bytes[3]]);
+    if version != WIRE_FORMAT_VERSION {
+        return Err(WireError::UnsupportedVersion {
+            found: version,
+            expected: WIRE_FORMAT_VERSION,
+        });
+    }
+    Ok((PayloadEncoding::from_byte(bytes[4])?, &bytes[HEADER_LEN..]))
+}
@@ -0,0 +1,77 @@
+//! Repairs or backfills `Protocol.contract_address` from the factory on each chain.
+//!
+//! Usage: `cargo run --bin reconcile_protocols -- [--dry-run] [--owner <user uuid>]
+//! [--protocol-id <id>]...`
+
+use dotenv::dotenv;
+use server::services::{
+    chain_registry::connect_from_env,
+    protocol_reconciler::{reconcile_protocols, ReconcileAction, ReconcileOptions},
+    storage::storage_from_env,
+};
+use std::process::ExitCode;
+
+fn parse_args() -> Result<ReconcileOptions, String> {
+    let mut options = ReconcileOptions::default();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dry-run" => options.dry_run = true,
+            "--owner" => options.owner_uuid = Some(args.next().ok_or("--owner needs a user uuid")?),
+            "--protocol-id" => options
+                .protocol_ids
+                .push(args.next().ok_or("--protocol-id needs an id")?),
+            _ => return Err(format!("Unknown argument {}", arg)),
+        }
+    }
+    Ok(options)
+}
+
+#[actix_web::main]
+async fn main() -> ExitCode {
+    dotenv().ok();
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!(
+                "Usage: reconcile_protocols [--dry-run] [--owner <user uuid>] [--protocol-id <id>]..."
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let db = match storage_from_env().await {
+        Ok(db) => db,
+        Err(e) => {
+            eprintln!("Unable to initialize the database: {}", e.message);
+            return ExitCode::FAILURE;
+        }
+    };
+    // Reconciliation only reads from the chain, so the transaction managers are not run.
+    let (registry, _tx_managers) = connect_from_env(db.clone());
+
+    let outcomes = match reconcile_protocols(&db, &registry, &options).await {
+        Ok(outcomes) => outcomes,
+        Err(e) => {
+            eprintln!("Reconciliation failed: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut failed = false;
+    for outcome in outcomes.iter() {
+        failed |= matches!(outcome.action, ReconcileAction::Failed { .. });
+        println!(
+            "{}",
+            serde_json::to_string(outcome).unwrap_or_else(|e| e.to_string())
+        );
+    }
+    if options.dry_run {
+        println!("Dry run: nothing was saved");
+    }
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
@@ -0,0 +1,4 @@
+pub mod models;
+pub mod routes;
+pub mod services;
+pub mod utils;
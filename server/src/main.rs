@@ -1,29 +1,49 @@
-mod models;
-mod routes;
-mod services;
-mod utils;
 use actix_cors::Cors;
-use actix_web::{
-    get, http, middleware::Logger, web, web::Data, App, HttpResponse, HttpServer, Responder,
-};
+use actix_web::{http, middleware::Logger, web, web::Data, App, HttpServer};
 use dotenv::dotenv;
-use routes::{
+use server::routes::{
+    blob_routes::get_blob,
+    chain_routes::get_chains,
+    hackathon_routes::{
+        hacker_hackathon_route::{
+            create_hackathon_team, invite_team_member, register_for_hackathon,
+            respond_team_invitation, submit_hackathon_project,
+        },
+        protocol_hackathon_route::{
+            add_hackathon_judge, create_hackathon, get_all_hackathons, get_hackathon_by_id,
+            get_hackathon_transactions, score_hackathon_team,
+        },
+    },
     health_routes::health_check,
     protocol_routes::{
-        add_protocol_staff, get_all_protocols, get_protocol_by_id, get_protocol_via_name,
-        register_protocol,
+        add_protocol_staff, get_all_protocols, get_protocol_by_id, get_protocol_transactions,
+        get_protocol_via_name, register_protocol, remove_protocol_staff,
+        update_protocol_staff_role,
     },
     quizes_routes::{
         hacker_quize_route::{start_quiz, submit_quiz},
-        protocol_quiz_route::{create_quiz, get_all_quiz, get_quiz_by_id},
+        protocol_quiz_route::{
+            create_quiz, get_all_quiz, get_quiz_by_id, get_quiz_transactions, get_result_proof,
+            preview_quiz_grading,
+        },
     },
     user_routes::{
         get_all_users, get_user_by_id, get_user_via_email, link_wallet_address, login_user,
-        register_user,
+        register_user, request_siwe_nonce, request_wallet_challenge, siwe_login,
     },
 };
-use services::{db::Database, quiz_services::check_and_submit_quizzes};
+use server::services::{
+    blob_store::BlobStore,
+    chain_registry::{connect_from_env, ChainRegistry},
+    indexer::run_indexer,
+    inspect::InspectClient,
+    settlement_queue::run_settlement_queue,
+    storage::{storage_from_env, Storage},
+    tx_manager::run_tx_monitor,
+};
+use server::utils::api_response::json_error_handler;
 use std::env;
+use std::sync::Arc;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -32,18 +52,46 @@ async fn main() -> std::io::Result<()> {
     std::env::set_var("RUST_BACKTRACE", "1");
     env_logger::init();
 
-    // Initialize the database
-    let db = Database::init().await;
-    let db_data = Data::new(db.clone());
     dotenv().ok();
-    // let db_url = env::var("DB_URL").expect("DB_URL must be set");
 
-    // Clone the database for the quiz submission task
+    // Initialize the storage backend, MongoDB unless the in-memory store is requested
+    let db: Arc<dyn Storage> = match storage_from_env().await {
+        Ok(db) => db,
+        Err(e) => {
+            return Err(std::io::Error::other(format!(
+                "Unable to initialize the database: {}",
+                e.message
+            )))
+        }
+    };
+    let db_data: Data<dyn Storage> = Data::from(db.clone());
+
+    // Connect to every chain in the registry and follow sent transactions until they are confirmed
+    let (registry, tx_managers) = connect_from_env(db.clone());
+    for tx_manager in tx_managers {
+        tokio::spawn(async move {
+            run_tx_monitor(tx_manager).await;
+        });
+    }
+    let registry = Arc::new(registry);
+    let registry_data: Data<ChainRegistry> = Data::from(registry.clone());
+    let blob_store_data = Data::new(BlobStore::from_env());
+    let inspect_data = Data::new(InspectClient::from_env());
+
+    // Clone the database for the settlement task
     let db_clone = db.clone();
+    let settlement_registry = registry.clone();
+
+    // Spawn a separate task that works through the settlement queue
+    tokio::spawn(async move {
+        run_settlement_queue(db_clone, settlement_registry).await;
+    });
 
-    // Spawn a separate task for quiz submission
+    // Spawn the contract event indexer
+    let indexer_db = db.clone();
+    let indexer_registry = registry.clone();
     tokio::spawn(async move {
-        check_and_submit_quizzes(db_clone).await;
+        run_indexer(indexer_db, indexer_registry).await;
     });
 
     // Set server configurations
@@ -58,6 +106,10 @@ async fn main() -> std::io::Result<()> {
         let logger = Logger::default();
         App::new()
             .app_data(db_data.clone())
+            .app_data(registry_data.clone())
+            .app_data(blob_store_data.clone())
+            .app_data(inspect_data.clone())
+            .app_data(web::JsonConfig::default().error_handler(json_error_handler))
             .wrap(logger)
             .wrap(
                 Cors::default()
@@ -71,22 +123,44 @@ async fn main() -> std::io::Result<()> {
                     .supports_credentials(),
             )
             .service(health_check)
+            .service(get_chains)
+            .service(get_blob)
             .service(get_all_users)
             .service(register_user)
+            .service(request_wallet_challenge)
             .service(link_wallet_address)
             .service(login_user)
+            .service(request_siwe_nonce)
+            .service(siwe_login)
             .service(register_protocol)
             .service(get_all_protocols)
             .service(add_protocol_staff)
+            .service(update_protocol_staff_role)
+            .service(remove_protocol_staff)
             .service(create_quiz)
             .service(start_quiz)
             .service(get_user_via_email)
             .service(get_user_by_id)
             .service(get_protocol_via_name)
             .service(get_protocol_by_id)
+            .service(get_protocol_transactions)
             .service(get_all_quiz)
             .service(get_quiz_by_id)
+            .service(get_quiz_transactions)
+            .service(preview_quiz_grading)
+            .service(get_result_proof)
             .service(submit_quiz)
+            .service(create_hackathon)
+            .service(add_hackathon_judge)
+            .service(score_hackathon_team)
+            .service(get_all_hackathons)
+            .service(get_hackathon_by_id)
+            .service(get_hackathon_transactions)
+            .service(register_for_hackathon)
+            .service(create_hackathon_team)
+            .service(invite_team_member)
+            .service(respond_team_invitation)
+            .service(submit_hackathon_project)
     })
     .bind((server_url, port))?
     .run()
@@ -0,0 +1,70 @@
+use super::quiz_model::reward_token_decimals_from_env;
+use serde::{Deserialize, Serialize};
+
+/// One entry of the chain registry: where a chain is reached and which OpenQuest
+/// contracts live on it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChainConfig {
+    pub chain_id: u64,
+    pub name: String,
+    /// May carry an API key, so it is never sent to clients.
+    #[serde(skip_serializing)]
+    pub rpc_url: String,
+    pub factory_address: Option<String>,
+    /// Address the coprocessor posts notices from.
+    pub task_issuer: Option<String>,
+    /// Block explorer base URL, such as `https://sepolia.etherscan.io`.
+    #[serde(default)]
+    pub explorer_url: Option<String>,
+    /// Decimals of the token quizzes and hackathons on this chain pay out in;
+    /// `REWARD_TOKEN_DECIMALS` when unset.
+    #[serde(default)]
+    pub reward_token_decimals: Option<u8>,
+}
+
+/// Explorer URLs with `{address}` or `{hash}` left for the client to fill in.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ExplorerLinks {
+    pub address: String,
+    pub tx: String,
+}
+
+/// The public description of a chain, attached to API responses.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ChainInfo {
+    pub chain_id: u64,
+    pub name: String,
+    pub factory_address: Option<String>,
+    pub task_issuer: Option<String>,
+    pub explorer: Option<ExplorerLinks>,
+    pub reward_token_decimals: u8,
+    /// False when the server could not connect to the chain at startup.
+    pub enabled: bool,
+}
+
+impl ChainConfig {
+    pub fn explorer_links(&self) -> Option<ExplorerLinks> {
+        let base = self.explorer_url.as_ref()?.trim_end_matches('/');
+        Some(ExplorerLinks {
+            address: format!("{}/address/{{address}}", base),
+            tx: format!("{}/tx/{{hash}}", base),
+        })
+    }
+
+    pub fn reward_token_decimals(&self) -> u8 {
+        self.reward_token_decimals
+            .unwrap_or_else(reward_token_decimals_from_env)
+    }
+
+    pub fn info(&self, enabled: bool) -> ChainInfo {
+        ChainInfo {
+            chain_id: self.chain_id,
+            name: self.name.clone(),
+            factory_address: self.factory_address.clone(),
+            task_issuer: self.task_issuer.clone(),
+            explorer: self.explorer_links(),
+            reward_token_decimals: self.reward_token_decimals(),
+            enabled,
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+
+/// Decoded body of a log emitted by a protocol contract or the protocol factory.
+///
+/// `uint256` values are kept as decimal strings, and `indexed string` arguments
+/// (which only reach the log as their keccak256 hash) as `0x` hex.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "kind")]
+pub enum ContractEventData {
+    ResultReceived {
+        /// The quiz or hackathon the notice settles, if the payload could be decoded.
+        target_uuid: Option<String>,
+        data: String,
+    },
+    QuizCreated {
+        by: String,
+        quiz_id_hash: String,
+    },
+    RewardsDistributed {
+        winners: Vec<String>,
+        reward_per_winner: String,
+        time: String,
+    },
+    TriviaCanceled {
+        admin: String,
+        reason: String,
+        time: String,
+    },
+    StaffAdded {
+        admin: String,
+        staff: String,
+        time: String,
+    },
+    StaffRemoved {
+        admin: String,
+        staff: String,
+        time: String,
+    },
+    ProtocolCreated {
+        admin: String,
+        time: String,
+        protocol_id_hash: String,
+        protocol_contract: String,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ContractEvent {
+    /// `<tx hash>:<log index>`, unique per log so replays overwrite instead of duplicating.
+    pub id: String,
+    /// Events indexed before chains were recorded have none.
+    #[serde(default)]
+    pub chain_id: Option<u64>,
+    pub contract_address: String,
+    pub block_number: i64,
+    pub tx_hash: String,
+    pub log_index: i64,
+    pub data: ContractEventData,
+    pub indexed_at: i64,
+}
+
+/// How far the indexer has read the logs of one contract on one chain.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IndexerCursor {
+    /// Cursors saved before chains were recorded have none and are not resumed from;
+    /// the contract is read again from `INDEXER_START_BLOCK`, which replays safely.
+    #[serde(default)]
+    pub chain_id: Option<u64>,
+    pub contract_address: String,
+    /// First block that has not been indexed yet.
+    pub next_block: i64,
+    pub updated_at: i64,
+}
+
+impl ContractEvent {
+    pub fn event_id(tx_hash: &str, log_index: i64) -> String {
+        format!("{}:{}", tx_hash, log_index)
+    }
+}
@@ -0,0 +1,333 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub use openquest_types::{
+    CriterionScore, HackathonOffchainData, JudgeScore, PrizeTrack, RubricCriterion,
+    TeamOffchainData, TokenAmount,
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum HackathonStatus {
+    Scheduled,
+    Completed,
+    Canceled,
+}
+
+/// Where a hackathon is in its timeline, derived from its deadlines.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum HackathonPhase {
+    NotStarted,
+    Registration,
+    Building,
+    Judging,
+    Closed,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum InvitationStatus {
+    Pending,
+    Accepted,
+    Declined,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HackathonParticipant {
+    pub user_uuid: String,
+    pub wallet_address: String,
+    pub registered_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Invitation {
+    pub invitee_uuid: String,
+    pub invited_by: String,
+    pub status: InvitationStatus,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProjectSubmission {
+    pub repo_url: String,
+    pub demo_url: String,
+    pub description: String,
+    pub track_ids: Vec<String>,
+    pub submitted_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Team {
+    pub uuid: String,
+    pub name: String,
+    pub leader_uuid: String,
+    pub members: Vec<String>,
+    pub invitations: Vec<Invitation>,
+    pub submission: Option<ProjectSubmission>,
+    pub scores: Vec<JudgeScore>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Hackathon {
+    pub uuid: String,
+    pub name: String,
+    pub description: String,
+    pub protocol: String,
+    pub created_by: String,
+    pub created_at: i64,
+    pub registration_start: i64,
+    pub registration_end: i64,
+    pub submission_end: i64,
+    pub judging_end: i64,
+    pub max_team_size: usize,
+    /// Leaderboard points a user must hold on the protocol to register.
+    pub min_leaderboard_score: f64,
+    pub rubric: Vec<RubricCriterion>,
+    pub prize_tracks: Vec<PrizeTrack>,
+    /// Decimals of the reward token the prize tracks are paid in.
+    #[serde(default)]
+    pub token_decimals: u8,
+    pub judges: Vec<String>,
+    pub participants: Vec<HackathonParticipant>,
+    pub teams: Vec<Team>,
+    pub status: HackathonStatus,
+    pub submited: bool,
+}
+
+impl Hackathon {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        name: String,
+        description: String,
+        protocol: String,
+        created_by: String,
+        registration_start: i64,
+        registration_end: i64,
+        submission_end: i64,
+        judging_end: i64,
+        max_team_size: usize,
+        min_leaderboard_score: f64,
+        rubric: Vec<RubricCriterion>,
+        prize_tracks: Vec<PrizeTrack>,
+        token_decimals: u8,
+    ) -> Self {
+        Self {
+            uuid: Uuid::new_v4().to_string(),
+            name,
+            description,
+            protocol,
+            created_by,
+            created_at: Utc::now().timestamp(),
+            registration_start,
+            registration_end,
+            submission_end,
+            judging_end,
+            max_team_size,
+            min_leaderboard_score,
+            rubric,
+            prize_tracks,
+            token_decimals,
+            judges: Vec::new(),
+            participants: Vec::new(),
+            teams: Vec::new(),
+            status: HackathonStatus::Scheduled,
+            submited: false,
+        }
+    }
+
+    /// Validate that the hackathon structure is correct
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.registration_start < self.registration_end
+            && self.registration_end <= self.submission_end
+            && self.submission_end < self.judging_end)
+        {
+            return Err(
+                "Deadlines must run registration start < registration end <= submission end < judging end."
+                    .to_string(),
+            );
+        }
+        if self.max_team_size == 0 {
+            return Err("Teams must allow at least one member.".to_string());
+        }
+        if self.rubric.is_empty() {
+            return Err("Hackathon must have at least one rubric criterion.".to_string());
+        }
+        for criterion in self.rubric.iter() {
+            if criterion.weight <= 0.0 || criterion.max_score == 0 {
+                return Err(format!(
+                    "Rubric criterion {} needs a positive weight and max score.",
+                    criterion.id
+                ));
+            }
+        }
+        if self.prize_tracks.is_empty() {
+            return Err("Hackathon must have at least one prize track.".to_string());
+        }
+        for track in self.prize_tracks.iter() {
+            if track.rewards.is_empty() {
+                return Err(format!(
+                    "Prize track {} needs at least one reward.",
+                    track.id
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn phase(&self, now: i64) -> HackathonPhase {
+        if now < self.registration_start {
+            HackathonPhase::NotStarted
+        } else if now <= self.registration_end {
+            HackathonPhase::Registration
+        } else if now <= self.submission_end {
+            HackathonPhase::Building
+        } else if now <= self.judging_end {
+            HackathonPhase::Judging
+        } else {
+            HackathonPhase::Closed
+        }
+    }
+
+    pub fn total_reward(&self) -> TokenAmount {
+        self.prize_tracks
+            .iter()
+            .map(|track| track.total_reward())
+            .sum()
+    }
+
+    /// Largest single payout, used as the per-user cap when settling on-chain.
+    pub fn max_reward_per_user(&self) -> TokenAmount {
+        self.prize_tracks
+            .iter()
+            .flat_map(|track| track.rewards.iter())
+            .copied()
+            .max()
+            .unwrap_or(TokenAmount::ZERO)
+    }
+
+    pub fn participant(&self, user_uuid: &str) -> Option<&HackathonParticipant> {
+        self.participants.iter().find(|p| p.user_uuid == user_uuid)
+    }
+
+    pub fn team_of(&self, user_uuid: &str) -> Option<&Team> {
+        self.teams
+            .iter()
+            .find(|team| team.members.iter().any(|member| member == user_uuid))
+    }
+
+    pub fn team_mut(&mut self, team_uuid: &str) -> Option<&mut Team> {
+        self.teams.iter_mut().find(|team| team.uuid == team_uuid)
+    }
+
+    pub fn register(&mut self, user_uuid: String, wallet_address: String) -> bool {
+        if self.participant(&user_uuid).is_some() {
+            return false;
+        }
+        self.participants.push(HackathonParticipant {
+            user_uuid,
+            wallet_address,
+            registered_at: Utc::now().timestamp(),
+        });
+        true
+    }
+
+    pub fn create_team(&mut self, name: String, leader_uuid: String) -> Result<Team, String> {
+        if self.team_of(&leader_uuid).is_some() {
+            return Err("User already belongs to a team".to_string());
+        }
+        if self
+            .teams
+            .iter()
+            .any(|team| team.name.eq_ignore_ascii_case(&name))
+        {
+            return Err("Team name already taken".to_string());
+        }
+        let team = Team {
+            uuid: Uuid::new_v4().to_string(),
+            name,
+            leader_uuid: leader_uuid.clone(),
+            members: vec![leader_uuid],
+            invitations: Vec::new(),
+            submission: None,
+            scores: Vec::new(),
+        };
+        self.teams.push(team.clone());
+        Ok(team)
+    }
+
+    pub fn is_judge(&self, user_uuid: &str) -> bool {
+        self.judges.iter().any(|judge| judge == user_uuid)
+    }
+
+    pub fn add_judge(&mut self, judge_uuid: String) -> bool {
+        if self.is_judge(&judge_uuid) {
+            return false;
+        }
+        self.judges.push(judge_uuid);
+        true
+    }
+
+    /// Strips judges' marks, which stay private until results are settled.
+    pub fn without_scores(mut self) -> Self {
+        for team in self.teams.iter_mut() {
+            team.scores.clear();
+        }
+        self
+    }
+
+    /// Only teams that submitted a project take part in settlement.
+    pub fn into_offchain_hackathon_data(&self) -> HackathonOffchainData {
+        let teams = self
+            .teams
+            .iter()
+            .filter_map(|team| {
+                let submission = team.submission.as_ref()?;
+                Some(TeamOffchainData {
+                    uuid: team.uuid.clone(),
+                    member_wallets: team
+                        .members
+                        .iter()
+                        .filter_map(|member| self.participant(member))
+                        .map(|participant| participant.wallet_address.clone())
+                        .collect(),
+                    track_ids: submission.track_ids.clone(),
+                    scores: team.scores.clone(),
+                })
+            })
+            .collect();
+
+        HackathonOffchainData {
+            uuid: self.uuid.clone(),
+            protocol: self.protocol.clone(),
+            rubric: self.rubric.clone(),
+            prize_tracks: self.prize_tracks.clone(),
+            judges: self.judges.clone(),
+            teams,
+        }
+    }
+}
+
+impl Team {
+    pub fn pending_invitation_mut(&mut self, invitee_uuid: &str) -> Option<&mut Invitation> {
+        self.invitations.iter_mut().find(|invitation| {
+            invitation.invitee_uuid == invitee_uuid
+                && invitation.status == InvitationStatus::Pending
+        })
+    }
+
+    /// Members plus invitations that could still be accepted.
+    pub fn reserved_seats(&self) -> usize {
+        self.members.len()
+            + self
+                .invitations
+                .iter()
+                .filter(|invitation| invitation.status == InvitationStatus::Pending)
+                .count()
+    }
+
+    /// Records `judge_uuid`'s marks, replacing any they gave earlier.
+    pub fn record_score(&mut self, score: JudgeScore) {
+        self.scores
+            .retain(|existing| existing.judge_uuid != score.judge_uuid);
+        self.scores.push(score);
+    }
+}
@@ -1,4 +1,9 @@
+pub mod chain_model;
+pub mod event_model;
 pub mod hackathon_model;
 pub mod protocol_model;
 pub mod quiz_model;
+pub mod settlement_model;
+pub mod transaction_model;
 pub mod user_model;
+pub mod wallet_challenge_model;
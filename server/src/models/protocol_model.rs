@@ -1,17 +1,81 @@
-use serde::{Deserialize, Serialize};
+use super::chain_model::ChainInfo;
+use openquest_types::TokenAmount;
+use serde::{Deserialize, Deserializer, Serialize};
 use uuid::Uuid;
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(from = "StoredProtocol")]
 pub struct Protocol {
     pub protocol_uuid: String,
     pub name: String,
-    pub staffs: Vec<String>,
+    pub staffs: Vec<StaffMember>,
     pub protocol_users: Vec<ProtocolUsers>,
     pub created_at: String,
     pub created_by: String,
     pub quizes: Vec<String>,
-    pub total_expense: f64,
+    #[serde(default)]
+    pub hackathons: Vec<String>,
+    /// Base units of the reward token committed to quizzes and hackathons.
+    pub total_expense: TokenAmount,
     pub contract_address: String,
+    /// Chain the protocol contract is deployed on. Protocols registered before chains
+    /// were recorded have none and live on the registry's default chain.
+    #[serde(default)]
+    pub chain_id: Option<u64>,
+}
+
+/// A protocol as saved in the database, which may predate staff roles.
+#[derive(Deserialize)]
+struct StoredProtocol {
+    protocol_uuid: String,
+    name: String,
+    #[serde(deserialize_with = "deserialize_staffs")]
+    staffs: Vec<StaffMember>,
+    protocol_users: Vec<ProtocolUsers>,
+    created_at: String,
+    created_by: String,
+    quizes: Vec<String>,
+    #[serde(default)]
+    hackathons: Vec<String>,
+    total_expense: TokenAmount,
+    contract_address: String,
+    #[serde(default)]
+    chain_id: Option<u64>,
+}
+
+/// A protocol as returned by the API, with the chain its contract lives on.
+#[derive(Serialize, Debug, Clone)]
+pub struct ProtocolView {
+    #[serde(flatten)]
+    pub protocol: Protocol,
+    /// `None` when the protocol's chain is no longer in the registry.
+    pub chain: Option<ChainInfo>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum StaffRole {
+    Owner,
+    Admin,
+    QuizAuthor,
+    Analyst,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Permission {
+    /// Grant, change or revoke the Owner and Admin roles.
+    ManageAdmins,
+    /// Grant, change or revoke the QuizAuthor and Analyst roles.
+    ManageStaff,
+    CreateQuiz,
+    ManageHackathons,
+    ViewAnalytics,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StaffMember {
+    pub user_uuid: String,
+    pub role: StaffRole,
+    pub assigned_at: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -21,23 +85,77 @@ pub struct ProtocolUsers {
 }
 
 impl Protocol {
-    pub fn new(name: String, creator_uuid: String) -> Protocol {
+    pub fn new(name: String, creator_uuid: String, chain_id: u64) -> Protocol {
         Protocol {
             protocol_uuid: Uuid::new_v4().to_string(),
             name,
-            staffs: Vec::new(),
+            staffs: vec![StaffMember::new(creator_uuid.clone(), StaffRole::Owner)],
             protocol_users: Vec::new(),
             created_at: chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
             created_by: creator_uuid,
-            total_expense: 0.0,
+            total_expense: TokenAmount::ZERO,
             quizes: Vec::new(),
+            hackathons: Vec::new(),
             contract_address: String::from(" "),
+            chain_id: Some(chain_id),
+        }
+    }
+
+    pub fn add_staff(&mut self, staff_uuid: String, role: StaffRole) -> bool {
+        if self.role_of(&staff_uuid).is_some() {
+            return false;
+        }
+        self.staffs.push(StaffMember::new(staff_uuid, role));
+        true
+    }
+
+    pub fn role_of(&self, user_uuid: &str) -> Option<StaffRole> {
+        self.staffs
+            .iter()
+            .find(|staff| staff.user_uuid == user_uuid)
+            .map(|staff| staff.role)
+    }
+
+    pub fn has_permission(&self, user_uuid: &str, permission: Permission) -> bool {
+        match self.role_of(user_uuid) {
+            Some(role) => role.can(permission),
+            None => false,
+        }
+    }
+
+    pub fn update_staff_role(&mut self, staff_uuid: &str, role: StaffRole) -> Result<(), String> {
+        if self.is_last_owner(staff_uuid) && role != StaffRole::Owner {
+            return Err("A protocol must keep at least one owner".to_string());
+        }
+        match self.staffs.iter_mut().find(|s| s.user_uuid == staff_uuid) {
+            Some(staff) => {
+                staff.role = role;
+                staff.assigned_at = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+                Ok(())
+            }
+            None => Err("User is not a member of the protocol team".to_string()),
+        }
+    }
+
+    pub fn remove_staff(&mut self, staff_uuid: &str) -> Result<(), String> {
+        if self.role_of(staff_uuid).is_none() {
+            return Err("User is not a member of the protocol team".to_string());
+        }
+        if self.is_last_owner(staff_uuid) {
+            return Err("A protocol must keep at least one owner".to_string());
         }
+        self.staffs.retain(|staff| staff.user_uuid != staff_uuid);
+        Ok(())
     }
 
-    pub fn add_staff(&mut self, staff_uuid: String) -> bool {
-        self.staffs.push(staff_uuid);
-        return true;
+    fn is_last_owner(&self, user_uuid: &str) -> bool {
+        self.role_of(user_uuid) == Some(StaffRole::Owner)
+            && self
+                .staffs
+                .iter()
+                .filter(|staff| staff.role == StaffRole::Owner)
+                .count()
+                == 1
     }
 
     pub fn add_protocol_user(&mut self, user_uuid: String) -> bool {
@@ -45,17 +163,22 @@ impl Protocol {
             user_uuid,
             leaderboard_point: 0,
         });
-        return true;
+        true
     }
 
-    pub fn update_total_expense(&mut self, expense: f64) -> bool {
-        self.total_expense += expense;
-        return true;
+    pub fn update_total_expense(&mut self, expense: TokenAmount) -> bool {
+        self.total_expense = self.total_expense.saturating_add(expense);
+        true
     }
 
     pub fn add_quiz(&mut self, quiz_uuid: String) -> bool {
         self.quizes.push(quiz_uuid);
-        return true;
+        true
+    }
+
+    pub fn add_hackathon(&mut self, hackathon_uuid: String) -> bool {
+        self.hackathons.push(hackathon_uuid);
+        true
     }
 
     pub fn update_protocol_user_leaderboard_point(
@@ -69,9 +192,124 @@ impl Protocol {
             .find(|p| p.user_uuid == user_uuid)
         {
             protocol_user.leaderboard_point = leaderboard_point;
-            return true;
+            true
         } else {
-            return false;
+            false
+        }
+    }
+}
+
+impl StaffRole {
+    pub fn can(&self, permission: Permission) -> bool {
+        match permission {
+            Permission::ManageAdmins => matches!(self, StaffRole::Owner),
+            Permission::ManageStaff => matches!(self, StaffRole::Owner | StaffRole::Admin),
+            Permission::CreateQuiz => matches!(
+                self,
+                StaffRole::Owner | StaffRole::Admin | StaffRole::QuizAuthor
+            ),
+            Permission::ManageHackathons => matches!(self, StaffRole::Owner | StaffRole::Admin),
+            Permission::ViewAnalytics => true,
         }
     }
+
+    /// The permission a caller needs to grant, or take away, this role.
+    pub fn managed_by(&self) -> Permission {
+        match self {
+            StaffRole::Owner | StaffRole::Admin => Permission::ManageAdmins,
+            StaffRole::QuizAuthor | StaffRole::Analyst => Permission::ManageStaff,
+        }
+    }
+
+    pub fn to_string(&self) -> &'static str {
+        match self {
+            StaffRole::Owner => "Owner",
+            StaffRole::Admin => "Admin",
+            StaffRole::QuizAuthor => "QuizAuthor",
+            StaffRole::Analyst => "Analyst",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<StaffRole> {
+        match value.to_lowercase().as_str() {
+            "owner" => Some(StaffRole::Owner),
+            "admin" => Some(StaffRole::Admin),
+            "quiz_author" | "quizauthor" => Some(StaffRole::QuizAuthor),
+            "analyst" => Some(StaffRole::Analyst),
+            _ => None,
+        }
+    }
+}
+
+impl StaffMember {
+    pub fn new(user_uuid: String, role: StaffRole) -> StaffMember {
+        StaffMember {
+            user_uuid,
+            role,
+            assigned_at: chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        }
+    }
+}
+
+impl From<StoredProtocol> for Protocol {
+    /// Legacy protocols have no owner, and only an owner can appoint one, so the
+    /// creator is made the owner of any protocol that is read back without one.
+    fn from(stored: StoredProtocol) -> Protocol {
+        let mut protocol = Protocol {
+            protocol_uuid: stored.protocol_uuid,
+            name: stored.name,
+            staffs: stored.staffs,
+            protocol_users: stored.protocol_users,
+            created_at: stored.created_at,
+            created_by: stored.created_by,
+            quizes: stored.quizes,
+            hackathons: stored.hackathons,
+            total_expense: stored.total_expense,
+            contract_address: stored.contract_address,
+            chain_id: stored.chain_id,
+        };
+        let has_owner = protocol
+            .staffs
+            .iter()
+            .any(|staff| staff.role == StaffRole::Owner);
+        if !has_owner {
+            let creator = protocol.created_by.clone();
+            match protocol.staffs.iter_mut().find(|s| s.user_uuid == creator) {
+                Some(staff) => staff.role = StaffRole::Owner,
+                None => protocol.staffs.push(StaffMember {
+                    user_uuid: creator,
+                    role: StaffRole::Owner,
+                    assigned_at: String::from("0000-00-00 00:00:00"),
+                }),
+            }
+        }
+        protocol
+    }
+}
+
+/// Protocols saved before roles existed store `staffs` as bare user uuids; those
+/// members are read back as analysts, for the owner to promote as needed.
+fn deserialize_staffs<'de, D>(deserializer: D) -> Result<Vec<StaffMember>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StaffEntry {
+        Member(StaffMember),
+        Legacy(String),
+    }
+
+    let entries = Vec::<StaffEntry>::deserialize(deserializer)?;
+    Ok(entries
+        .into_iter()
+        .map(|entry| match entry {
+            StaffEntry::Member(member) => member,
+            StaffEntry::Legacy(user_uuid) => StaffMember {
+                user_uuid,
+                role: StaffRole::Analyst,
+                assigned_at: String::from("0000-00-00 00:00:00"),
+            },
+        })
+        .collect())
 }
@@ -1,28 +1,21 @@
-use chrono::{DateTime, Duration, Utc};
+use chrono::Utc;
 // use mongodb::bson::Uuid;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use openquest_types::merkle::{hash_from_hex, hash_to_hex, MerkleTree};
+pub use openquest_types::{
+    AttemptWindow, DifficultyLevel, OptionIndex, Participant, Question, QuizAnswer,
+    QuizOffchainData, QuizOption, QuizResponse, RejectionReport, RewardData, RewardType,
+    TokenAmount, MAX_TOKEN_DECIMALS,
+};
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum QuizAccess {
     Public,
     Private,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub enum RewardType {
-    DistributedByRankToTopFive,
-    DistributedEqullyToTopFive,
-    DistributedByLottery,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub enum DifficultyLevel {
-    Easy,
-    Medium,
-    Hard,
-}
-
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum Status {
     Pending,
@@ -32,82 +25,171 @@ pub enum Status {
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub enum OptionIndex {
-    A,
-    B,
-    C,
-    D,
+pub struct Quiz {
+    pub uuid: String,
+    pub name: String,
+    pub difficulty: DifficultyLevel,
+    pub protocol: String,
+    pub description: String,
+    pub num_questions: usize,
+    pub questions: Vec<Question>,
+    pub access: QuizAccess,
+    /// Amounts are in base units of the reward token.
+    pub total_reward: TokenAmount,
+    pub max_reward_per_user: TokenAmount,
+    /// Decimals of the reward token. Quizzes stored before this was recorded default to 0,
+    /// matching the whole units their amounts were sent on-chain as.
+    #[serde(default)]
+    pub token_decimals: u8,
+    pub duration_in_sec_timestamp: i64,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub created_at: i64,
+    pub created_by: String,
+    pub participants: Vec<Participant>,
+    pub status: Status,
+    pub submited: bool,
+    pub reward_type: RewardType,
+    /// Time each participant has to submit after joining; `None` leaves only the quiz window.
+    #[serde(default)]
+    pub attempt_duration_in_sec: Option<i64>,
+    #[serde(default)]
+    pub submission_grace_in_sec: i64,
+    /// Why settlement refused to send the quiz for grading, if it did.
+    #[serde(default)]
+    pub grading_rejection: Option<RejectionReport>,
+    /// The settled results and the Merkle tree the coprocessor's notice committed to.
+    #[serde(default)]
+    pub results_tree: Option<ResultsTree>,
 }
 
+/// The results published in a quiz's notice with the leaves of the Merkle tree over
+/// them, from which a proof for any one result can be rebuilt.
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Question {
-    pub id: usize, // Question index
-    pub question_text: String,
-    pub options: [QuizOption; 4],    // Options A-D
-    pub correct_answer: OptionIndex, // 'A', 'B', 'C', or 'D'
+pub struct ResultsTree {
+    /// `0x` hex, equal to the notice's `results_root`.
+    pub root: String,
+    pub leaves: Vec<String>,
+    pub results: Vec<RewardData>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct QuizOption {
-    pub text: String,
-    pub option_index: OptionIndex,
+/// One result with the proof that it is part of the tree with root `root`.
+#[derive(Debug, Serialize, Clone)]
+pub struct ResultProof {
+    pub quiz_uuid: String,
+    pub root: String,
+    /// Position of the result in the notice, and of its leaf in the tree.
+    pub index: usize,
+    pub leaf: String,
+    /// Sibling hashes from the leaf up to the root.
+    pub proof: Vec<String>,
+    pub result: RewardData,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct QuizAnswer {
-    pub question_id: usize,
-    pub answer: OptionIndex,
+impl ResultsTree {
+    /// The tree over a notice's results. Fails if the notice commits to no root, or to a
+    /// different one, since proofs from this tree could not be checked against it.
+    pub fn from_response(response: &QuizResponse) -> Result<Self, String> {
+        let tree = MerkleTree::from_results(&response.results);
+        let root = hash_to_hex(&tree.root());
+        match response.results_root.as_deref() {
+            Some(committed) if committed.eq_ignore_ascii_case(&root) => Ok(ResultsTree {
+                root,
+                leaves: tree.leaves().iter().map(hash_to_hex).collect(),
+                results: response.results.clone(),
+            }),
+            Some(committed) => Err(format!(
+                "Notice commits to results root {} but its results hash to {}",
+                committed, root
+            )),
+            None => Err("Notice does not commit to a results root".to_string()),
+        }
+    }
+
+    /// The proof for the result paid to `address`, if there is one.
+    pub fn proof(&self, quiz_uuid: &str, address: &str) -> Result<Option<ResultProof>, String> {
+        let Some(index) = self
+            .results
+            .iter()
+            .position(|result| result.user_address.eq_ignore_ascii_case(address))
+        else {
+            return Ok(None);
+        };
+        let leaves = self
+            .leaves
+            .iter()
+            .map(|leaf| hash_from_hex(leaf))
+            .collect::<Result<Vec<_>, String>>()?;
+        let proof = MerkleTree::new(leaves)
+            .proof(index)
+            .ok_or("Results tree has fewer leaves than results")?;
+        Ok(Some(ResultProof {
+            quiz_uuid: quiz_uuid.to_string(),
+            root: self.root.clone(),
+            index,
+            leaf: self.leaves[index].clone(),
+            proof: proof.iter().map(hash_to_hex).collect(),
+            result: self.results[index].clone(),
+        }))
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Participant {
-    pub user_uuid: String,
-    pub wallet_address: String,
-    pub score: i64,
-    pub answered_questions: Vec<QuizAnswer>, // Question index and answer index
-    pub submission_time: i64,
-    pub start_time: i64,
-    pub reward: f64,
+/// Who a quiz is being read by, which decides how much of it they may see.
+#[derive(Debug, Clone, PartialEq)]
+pub enum QuizAudience {
+    /// Anonymous callers and users who have not joined the quiz.
+    Public,
+    /// A user who joined the quiz, identified by their uuid.
+    Participant(String),
+    /// A staff member of the protocol that owns the quiz.
+    Staff,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct QuizOffchainData {
-    pub uuid: String,
-    pub protocol: String,
-    pub num_questions: usize,
-    pub questions: Vec<Question>,
-    pub total_reward: f64,
-    pub max_reward_per_user: f64,
-    pub participants: Vec<Participant>,
-    pub reward_type: RewardType,
-    pub difficulty: DifficultyLevel,
+/// A question as shown to a given audience; `correct_answer` is only filled in
+/// for staff or once the quiz is completed.
+#[derive(Debug, Serialize, Clone)]
+pub struct QuestionView {
+    pub id: usize,
+    pub question_text: String,
+    pub options: [QuizOption; 4],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub correct_answer: Option<OptionIndex>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Quiz {
+/// The projection of a `Quiz` returned by the read endpoints.
+#[derive(Debug, Serialize, Clone)]
+pub struct QuizView {
     pub uuid: String,
     pub name: String,
     pub difficulty: DifficultyLevel,
     pub protocol: String,
     pub description: String,
     pub num_questions: usize,
-    pub questions: Vec<Question>,
+    pub questions: Vec<QuestionView>,
     pub access: QuizAccess,
-    pub total_reward: f64,
-    pub max_reward_per_user: f64,
+    /// Amounts are decimal strings in whole tokens, such as `"12.5"`.
+    pub total_reward: String,
+    pub max_reward_per_user: String,
+    pub token_decimals: u8,
     pub duration_in_sec_timestamp: i64,
     pub start_time: i64,
     pub end_time: i64,
     pub created_at: i64,
     pub created_by: String,
+    pub num_participants: usize,
     pub participants: Vec<Participant>,
     pub status: Status,
     pub submited: bool,
     pub reward_type: RewardType,
+    pub attempt_duration_in_sec: Option<i64>,
+    /// Only shown to staff.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grading_rejection: Option<RejectionReport>,
 }
 
 impl Quiz {
     /// Create a new quiz instance
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         name: String,
         difficulty: DifficultyLevel,
@@ -116,11 +198,13 @@ impl Quiz {
         created_by: String,
         questions: Vec<Question>,
         access: QuizAccess,
-        total_reward: f64,
-        max_reward_per_user: f64,
+        total_reward: TokenAmount,
+        max_reward_per_user: TokenAmount,
+        token_decimals: u8,
         duration_in_sec_timestamp: i64,
         start_time: i64,
         reward_type: RewardType,
+        attempt_duration_in_sec: Option<i64>,
     ) -> Self {
         Self {
             uuid: Uuid::new_v4().to_string(),
@@ -134,6 +218,7 @@ impl Quiz {
             access,
             total_reward,
             max_reward_per_user,
+            token_decimals,
             duration_in_sec_timestamp,
             start_time,
             end_time: start_time + duration_in_sec_timestamp,
@@ -142,6 +227,10 @@ impl Quiz {
             status: Status::Pending,
             submited: false,
             reward_type,
+            attempt_duration_in_sec,
+            submission_grace_in_sec: submission_grace_from_env(),
+            grading_rejection: None,
+            results_tree: None,
         }
     }
 
@@ -151,15 +240,39 @@ impl Quiz {
             protocol: self.protocol.clone(),
             num_questions: self.num_questions,
             questions: self.questions.clone(),
-            total_reward: self.total_reward.clone(),
-            max_reward_per_user: self.max_reward_per_user.clone(),
+            total_reward: self.total_reward,
+            max_reward_per_user: self.max_reward_per_user,
             participants: self.participants.clone(),
             reward_type: self.reward_type.clone(),
             difficulty: self.difficulty.clone(),
+            attempt_window: self.attempt_window(),
+        }
+    }
+
+    pub fn attempt_window(&self) -> AttemptWindow {
+        AttemptWindow {
+            end_time: self.end_time,
+            attempt_duration_in_sec: self.attempt_duration_in_sec,
+            grace_in_sec: self.submission_grace_in_sec,
         }
     }
 
+    /// When settlement is due: submissions are accepted until the grace period after
+    /// `end_time` is over, and the payload taken at settlement must include them.
+    pub fn settles_at(&self) -> i64 {
+        self.end_time + self.submission_grace_in_sec
+    }
+
+    /// Adds a participant, refusing a user or wallet that has already joined.
     pub fn add_participant(&mut self, uuid: String, wallet_address: String) -> bool {
+        if self.is_participant(&uuid)
+            || self
+                .participants
+                .iter()
+                .any(|p| p.wallet_address.eq_ignore_ascii_case(&wallet_address))
+        {
+            return false;
+        }
         self.participants.push(Participant {
             user_uuid: uuid,
             wallet_address,
@@ -167,9 +280,74 @@ impl Quiz {
             answered_questions: Vec::new(),
             submission_time: 0,
             start_time: Utc::now().timestamp(),
-            reward: 0.0,
+            reward: TokenAmount::ZERO,
         });
-        return true;
+        true
+    }
+
+    pub fn is_participant(&self, user_uuid: &str) -> bool {
+        self.participants.iter().any(|p| p.user_uuid == user_uuid)
+    }
+
+    /// Project the quiz for `audience`. Answers are only revealed to staff or after the
+    /// quiz is completed, and non-staff only ever see their own submission.
+    pub fn view(&self, audience: &QuizAudience) -> QuizView {
+        let reveal_answers =
+            *audience == QuizAudience::Staff || matches!(self.status, Status::Completed);
+        let questions = self
+            .questions
+            .iter()
+            .map(|question| QuestionView {
+                id: question.id,
+                question_text: question.question_text.clone(),
+                options: question.options.clone(),
+                correct_answer: if reveal_answers {
+                    Some(question.correct_answer.clone())
+                } else {
+                    None
+                },
+            })
+            .collect();
+        let participants = match audience {
+            QuizAudience::Staff => self.participants.clone(),
+            QuizAudience::Participant(user_uuid) => self
+                .participants
+                .iter()
+                .filter(|p| &p.user_uuid == user_uuid)
+                .cloned()
+                .collect(),
+            QuizAudience::Public => Vec::new(),
+        };
+
+        QuizView {
+            uuid: self.uuid.clone(),
+            name: self.name.clone(),
+            difficulty: self.difficulty.clone(),
+            protocol: self.protocol.clone(),
+            description: self.description.clone(),
+            num_questions: self.num_questions,
+            questions,
+            access: self.access.clone(),
+            total_reward: self.total_reward.format_units(self.token_decimals),
+            max_reward_per_user: self.max_reward_per_user.format_units(self.token_decimals),
+            token_decimals: self.token_decimals,
+            duration_in_sec_timestamp: self.duration_in_sec_timestamp,
+            start_time: self.start_time,
+            end_time: self.end_time,
+            created_at: self.created_at,
+            created_by: self.created_by.clone(),
+            num_participants: self.participants.len(),
+            participants,
+            status: self.status.clone(),
+            submited: self.submited,
+            reward_type: self.reward_type.clone(),
+            attempt_duration_in_sec: self.attempt_duration_in_sec,
+            grading_rejection: if *audience == QuizAudience::Staff {
+                self.grading_rejection.clone()
+            } else {
+                None
+            },
+        }
     }
 
     /// Check if the quiz is currently active
@@ -182,9 +360,9 @@ impl Quiz {
     pub fn remaining_time(&self) -> Option<i64> {
         let now = Utc::now().timestamp();
         if now > self.end_time {
-            return None;
+            None
         } else {
-            return Some(self.end_time - now);
+            Some(self.end_time - now)
         }
     }
 
@@ -198,6 +376,15 @@ impl Quiz {
             return Err("Total reward cannot be less than max reward per user.".to_string());
         }
 
+        if let Some(attempt_duration) = self.attempt_duration_in_sec {
+            if attempt_duration <= 0 || attempt_duration > self.duration_in_sec_timestamp {
+                return Err(
+                    "Attempt duration must be positive and fit within the quiz duration."
+                        .to_string(),
+                );
+            }
+        }
+
         for (index, question) in self.questions.iter().enumerate() {
             if question.options.len() != 4 {
                 return Err(format!(
@@ -209,86 +396,59 @@ impl Quiz {
 
         Ok(())
     }
-}
-
-impl QuizAccess {
-    pub fn to_string(&self) -> &'static str {
-        match self {
-            QuizAccess::Public => "Public",
-            QuizAccess::Private => "Private",
-        }
-    }
 
-    pub fn from_str(value: &str) -> Option<QuizAccess> {
-        match value {
-            "Public" => Some(QuizAccess::Public),
-            "Private" => Some(QuizAccess::Private),
-            _ => None,
+    /// Checks a submission only answers questions the quiz has, each at most once.
+    pub fn check_answers(&self, answers: &[QuizAnswer]) -> Result<(), String> {
+        let mut answered = std::collections::HashSet::new();
+        for answer in answers.iter() {
+            if !self.questions.iter().any(|q| q.id == answer.question_id) {
+                return Err(format!(
+                    "Question {} is not part of this quiz.",
+                    answer.question_id
+                ));
+            }
+            if !answered.insert(answer.question_id) {
+                return Err(format!(
+                    "Question {} is answered more than once.",
+                    answer.question_id
+                ));
+            }
         }
+        Ok(())
     }
 }
 
-impl RewardType {
-    pub fn to_string(&self) -> &'static str {
-        match self {
-            RewardType::DistributedByRankToTopFive => "DistributedByRankToTopFive",
-            RewardType::DistributedEqullyToTopFive => "DistributedEqullyToTopFive",
-            RewardType::DistributedByLottery => "DistributedByLottery",
-        }
-    }
-    pub fn from_str(value: &str) -> Option<RewardType> {
-        match value.to_lowercase().as_str() {
-            "distributed_by_rank" => Some(RewardType::DistributedByRankToTopFive),
-            "distributed_equally" => Some(RewardType::DistributedEqullyToTopFive),
-            "distributed_by_lottery" => Some(RewardType::DistributedByLottery),
-            _ => None,
-        }
-    }
+/// Decimals of the platform's reward token, read from `REWARD_TOKEN_DECIMALS`.
+pub fn reward_token_decimals_from_env() -> u8 {
+    std::env::var("REWARD_TOKEN_DECIMALS")
+        .ok()
+        .and_then(|value| value.parse::<u8>().ok())
+        .filter(|value| *value <= MAX_TOKEN_DECIMALS)
+        .unwrap_or(18)
 }
 
-impl DifficultyLevel {
-    pub fn to_string(&self) -> &'static str {
-        match self {
-            DifficultyLevel::Easy => "Easy",
-            DifficultyLevel::Medium => "Medium",
-            DifficultyLevel::Hard => "Hard",
-        }
-    }
-    pub fn from_str(value: &str) -> Option<DifficultyLevel> {
-        match value.to_lowercase().as_str() {
-            "easy" => Some(DifficultyLevel::Easy),
-            "medium" => Some(DifficultyLevel::Medium),
-            "hard" => Some(DifficultyLevel::Hard),
-            _ => None,
-        }
-    }
+/// Slack given to submissions past their deadline, read from `QUIZ_SUBMISSION_GRACE_SECS`.
+fn submission_grace_from_env() -> i64 {
+    std::env::var("QUIZ_SUBMISSION_GRACE_SECS")
+        .ok()
+        .and_then(|value| value.parse::<i64>().ok())
+        .filter(|value| *value >= 0)
+        .unwrap_or(30)
 }
 
-impl OptionIndex {
+impl QuizAccess {
     pub fn to_string(&self) -> &'static str {
         match self {
-            OptionIndex::A => "A",
-            OptionIndex::B => "B",
-            OptionIndex::C => "C",
-            OptionIndex::D => "D",
+            QuizAccess::Public => "Public",
+            QuizAccess::Private => "Private",
         }
     }
 
-    pub fn from_str(value: &str) -> Option<OptionIndex> {
-        match value.to_lowercase().as_str() {
-            "a" => Some(OptionIndex::A),
-            "b" => Some(OptionIndex::B),
-            "c" => Some(OptionIndex::C),
-            "d" => Some(OptionIndex::D),
+    pub fn parse(value: &str) -> Option<QuizAccess> {
+        match value {
+            "Public" => Some(QuizAccess::Public),
+            "Private" => Some(QuizAccess::Private),
             _ => None,
         }
     }
 }
-
-impl Participant {
-    pub fn submit_quiz(&mut self, answered_questions: Vec<QuizAnswer>) -> bool {
-        self.answered_questions = answered_questions;
-        self.submission_time = Utc::now().timestamp();
-        return true;
-    }
-}
@@ -0,0 +1,159 @@
+use chrono::Utc;
+use openquest_types::QuizResponse;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// What a settlement job pays out.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum SettlementTarget {
+    Quiz,
+    Hackathon,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum SettlementStage {
+    /// Waiting for the target to close before the payload is sent on-chain.
+    Queued,
+    /// `gradeQuiz` was mined; the coprocessor has the task.
+    SubmittedOnchain,
+    /// Polling the protocol contract for the coprocessor's notice.
+    AwaitingResult,
+    /// The notice was decoded and is being written to users.
+    ApplyingResults,
+    Settled,
+    /// Gave up after exhausting its attempts; `last_error` says why. A job whose
+    /// transaction was mined still takes a result that arrives later.
+    Failed,
+}
+
+/// A durable record of one quiz or hackathon moving through settlement.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SettlementJob {
+    pub uuid: String,
+    pub target: SettlementTarget,
+    pub target_uuid: String,
+    pub stage: SettlementStage,
+    /// Failed attempts at the current stage.
+    pub attempts: u32,
+    pub next_attempt_at: i64,
+    pub last_error: Option<String>,
+    /// Hex payload sent to `gradeQuiz`; kept so result polling hashes the exact same bytes.
+    pub payload: Option<String>,
+    pub contract_address: Option<String>,
+    /// Chain `contract_address` is on; jobs submitted before chains were recorded have
+    /// none and are polled on the default chain.
+    #[serde(default)]
+    pub chain_id: Option<u64>,
+    /// Hash of the `gradeQuiz` transaction, as tracked by the transaction manager.
+    #[serde(default)]
+    pub tx_hash: Option<String>,
+    pub result: Option<QuizResponse>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl SettlementStage {
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, SettlementStage::Settled | SettlementStage::Failed)
+    }
+
+    pub fn to_string(&self) -> &'static str {
+        match self {
+            SettlementStage::Queued => "Queued",
+            SettlementStage::SubmittedOnchain => "SubmittedOnchain",
+            SettlementStage::AwaitingResult => "AwaitingResult",
+            SettlementStage::ApplyingResults => "ApplyingResults",
+            SettlementStage::Settled => "Settled",
+            SettlementStage::Failed => "Failed",
+        }
+    }
+}
+
+impl SettlementJob {
+    /// A job that becomes due once the target closes at `due_at`.
+    pub fn new(target: SettlementTarget, target_uuid: String, due_at: i64) -> Self {
+        let now = Utc::now().timestamp();
+        SettlementJob {
+            uuid: Uuid::new_v4().to_string(),
+            target,
+            target_uuid,
+            stage: SettlementStage::Queued,
+            attempts: 0,
+            next_attempt_at: due_at,
+            last_error: None,
+            payload: None,
+            contract_address: None,
+            chain_id: None,
+            tx_hash: None,
+            result: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Moves to `stage` and makes the job due again straight away.
+    pub fn advance(&mut self, stage: SettlementStage) {
+        let now = Utc::now().timestamp();
+        self.stage = stage;
+        self.attempts = 0;
+        self.last_error = None;
+        self.next_attempt_at = now;
+        self.updated_at = now;
+    }
+
+    /// Checks again after `policy`'s base delay without using up an attempt, for a job
+    /// that is waiting on the coprocessor rather than failing.
+    pub fn wait(&mut self, policy: &RetryPolicy) {
+        let now = Utc::now().timestamp();
+        self.stage = SettlementStage::AwaitingResult;
+        self.updated_at = now;
+        self.next_attempt_at = now + policy.base_delay_secs;
+    }
+
+    /// Records a failed attempt and schedules the next one with exponential backoff,
+    /// or marks the job failed once `policy.max_attempts` is reached.
+    pub fn record_failure(&mut self, error: String, policy: &RetryPolicy) {
+        let now = Utc::now().timestamp();
+        self.attempts += 1;
+        self.last_error = Some(error);
+        self.updated_at = now;
+        if self.attempts >= policy.max_attempts {
+            self.stage = SettlementStage::Failed;
+        } else {
+            self.next_attempt_at = now + policy.backoff(self.attempts);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay_secs: i64,
+    pub max_delay_secs: i64,
+}
+
+impl RetryPolicy {
+    /// Reads `SETTLEMENT_MAX_ATTEMPTS`, `SETTLEMENT_BASE_DELAY_SECS` and
+    /// `SETTLEMENT_MAX_DELAY_SECS`, defaulting to 10 attempts between 30 seconds and 1 hour apart.
+    pub fn from_env() -> Self {
+        fn read<T: std::str::FromStr>(name: &str, default: T) -> T {
+            std::env::var(name)
+                .ok()
+                .and_then(|value| value.parse::<T>().ok())
+                .unwrap_or(default)
+        }
+        RetryPolicy {
+            max_attempts: read("SETTLEMENT_MAX_ATTEMPTS", 10).max(1),
+            base_delay_secs: read("SETTLEMENT_BASE_DELAY_SECS", 30).max(1),
+            max_delay_secs: read("SETTLEMENT_MAX_DELAY_SECS", 3600).max(1),
+        }
+    }
+
+    /// Delay before retry number `attempt` (1-based).
+    pub fn backoff(&self, attempt: u32) -> i64 {
+        let exponent = attempt.saturating_sub(1).min(30);
+        self.base_delay_secs
+            .saturating_mul(1i64 << exponent)
+            .min(self.max_delay_secs)
+    }
+}
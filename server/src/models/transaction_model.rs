@@ -0,0 +1,230 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum TransactionPurpose {
+    /// `createProtocol` on the factory; the target is the protocol uuid.
+    CreateProtocol,
+    /// `gradeQuiz` on a protocol contract; the target is the quiz or hackathon uuid.
+    GradeQuiz,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum TransactionStatus {
+    /// Saved with its nonce and hash but not yet in a block; see `broadcast` for whether
+    /// the node has accepted it.
+    Pending,
+    /// In a block that is not yet `TX_CONFIRMATIONS` deep.
+    Mined,
+    Confirmed,
+    /// Confirmed, but execution reverted.
+    Reverted,
+    /// Never reached the node, so its nonce was handed out again.
+    Dropped,
+}
+
+/// One outgoing transaction from the server's signer, including every fee-bumped
+/// replacement sent with the same nonce.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChainTransaction {
+    pub uuid: String,
+    /// Chain the transaction was sent on. Transactions saved before chains were recorded
+    /// have none and are no longer followed.
+    #[serde(default)]
+    pub chain_id: Option<u64>,
+    pub purpose: TransactionPurpose,
+    pub target_uuid: String,
+    /// Name of the protocol the transaction belongs to.
+    pub protocol: String,
+    pub from: String,
+    pub to: String,
+    /// `0x` hex calldata, kept so a stuck transaction can be re-signed with higher fees.
+    pub input: String,
+    pub nonce: i64,
+    /// Hash of the latest broadcast.
+    pub tx_hash: String,
+    /// Hashes of earlier broadcasts that this one replaced; any of them may still be mined.
+    pub replaced_hashes: Vec<String>,
+    /// Fees are in wei, as decimal strings.
+    pub max_fee_per_gas: String,
+    pub max_priority_fee_per_gas: String,
+    pub fee_bumps: u32,
+    pub gas_used: Option<i64>,
+    pub status: TransactionStatus,
+    pub block_number: Option<i64>,
+    pub block_hash: Option<String>,
+    pub last_error: Option<String>,
+    /// Whether the node accepted the signed transaction. Records are saved before they
+    /// are broadcast, so one lost mid-send is still followed by the monitor.
+    #[serde(default = "already_broadcast")]
+    pub broadcast: bool,
+    /// When the latest broadcast went out.
+    pub sent_at: i64,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl TransactionPurpose {
+    pub fn to_string(&self) -> &'static str {
+        match self {
+            TransactionPurpose::CreateProtocol => "CreateProtocol",
+            TransactionPurpose::GradeQuiz => "GradeQuiz",
+        }
+    }
+}
+
+impl TransactionStatus {
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            TransactionStatus::Confirmed | TransactionStatus::Reverted | TransactionStatus::Dropped
+        )
+    }
+
+    pub fn to_string(&self) -> &'static str {
+        match self {
+            TransactionStatus::Pending => "Pending",
+            TransactionStatus::Mined => "Mined",
+            TransactionStatus::Confirmed => "Confirmed",
+            TransactionStatus::Reverted => "Reverted",
+            TransactionStatus::Dropped => "Dropped",
+        }
+    }
+}
+
+impl ChainTransaction {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        chain_id: u64,
+        purpose: TransactionPurpose,
+        target_uuid: String,
+        protocol: String,
+        from: String,
+        to: String,
+        input: String,
+        nonce: i64,
+        tx_hash: String,
+        max_fee_per_gas: u128,
+        max_priority_fee_per_gas: u128,
+    ) -> Self {
+        let now = Utc::now().timestamp();
+        ChainTransaction {
+            uuid: Uuid::new_v4().to_string(),
+            chain_id: Some(chain_id),
+            purpose,
+            target_uuid,
+            protocol,
+            from,
+            to,
+            input,
+            nonce,
+            tx_hash,
+            replaced_hashes: Vec::new(),
+            max_fee_per_gas: max_fee_per_gas.to_string(),
+            max_priority_fee_per_gas: max_priority_fee_per_gas.to_string(),
+            fee_bumps: 0,
+            gas_used: None,
+            status: TransactionStatus::Pending,
+            block_number: None,
+            block_hash: None,
+            last_error: None,
+            broadcast: false,
+            sent_at: now,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Every hash this transaction has been broadcast under, latest first.
+    pub fn hashes(&self) -> Vec<String> {
+        let mut hashes = vec![self.tx_hash.clone()];
+        hashes.extend(self.replaced_hashes.iter().rev().cloned());
+        hashes
+    }
+
+    pub fn fees(&self) -> (u128, u128) {
+        (
+            self.max_fee_per_gas.parse().unwrap_or(0),
+            self.max_priority_fee_per_gas.parse().unwrap_or(0),
+        )
+    }
+
+    /// Records a fee-bumped broadcast with the same nonce.
+    pub fn replace(
+        &mut self,
+        tx_hash: String,
+        max_fee_per_gas: u128,
+        max_priority_fee_per_gas: u128,
+    ) {
+        let now = Utc::now().timestamp();
+        let replaced = std::mem::replace(&mut self.tx_hash, tx_hash);
+        self.replaced_hashes.push(replaced);
+        self.max_fee_per_gas = max_fee_per_gas.to_string();
+        self.max_priority_fee_per_gas = max_priority_fee_per_gas.to_string();
+        self.fee_bumps += 1;
+        self.broadcast = true;
+        self.last_error = None;
+        self.sent_at = now;
+        self.updated_at = now;
+    }
+
+    /// Records the block one of this transaction's hashes was mined in.
+    pub fn record_inclusion(
+        &mut self,
+        tx_hash: String,
+        block_number: i64,
+        block_hash: String,
+        gas_used: i64,
+    ) {
+        if tx_hash != self.tx_hash {
+            self.replaced_hashes.retain(|hash| *hash != tx_hash);
+            let latest = std::mem::replace(&mut self.tx_hash, tx_hash);
+            self.replaced_hashes.push(latest);
+        }
+        self.status = TransactionStatus::Mined;
+        self.block_number = Some(block_number);
+        self.block_hash = Some(block_hash);
+        self.gas_used = Some(gas_used);
+        self.last_error = None;
+        self.updated_at = Utc::now().timestamp();
+    }
+
+    /// The block the transaction was mined in was reorged away; wait for it to be mined again.
+    pub fn record_reorg(&mut self) {
+        self.status = TransactionStatus::Pending;
+        self.block_number = None;
+        self.block_hash = None;
+        self.gas_used = None;
+        self.updated_at = Utc::now().timestamp();
+    }
+
+    /// The node accepted the latest signed transaction.
+    pub fn record_broadcast(&mut self) {
+        let now = Utc::now().timestamp();
+        self.broadcast = true;
+        self.sent_at = now;
+        self.updated_at = now;
+    }
+
+    /// The node never took the transaction; it stops being followed.
+    pub fn record_dropped(&mut self, error: String) {
+        self.status = TransactionStatus::Dropped;
+        self.last_error = Some(error);
+        self.updated_at = Utc::now().timestamp();
+    }
+
+    pub fn finalize(&mut self, succeeded: bool) {
+        self.status = if succeeded {
+            TransactionStatus::Confirmed
+        } else {
+            TransactionStatus::Reverted
+        };
+        self.updated_at = Utc::now().timestamp();
+    }
+}
+
+/// Transactions saved before `broadcast` existed were only saved once sent.
+fn already_broadcast() -> bool {
+    true
+}
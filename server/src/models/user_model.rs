@@ -1,43 +1,116 @@
-use std::{collections::HashMap, string};
+use std::collections::HashMap;
 
-use serde::{Deserialize, Serialize};
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use serde::{Deserialize, Deserializer, Serialize};
 // use std::error::Error;
 // use sha2::digest::Update;
 use sha2::{Digest, Sha256};
+use std::env;
 use uuid::Uuid;
 
+use super::quiz_model::{reward_token_decimals_from_env, TokenAmount};
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct User {
     pub user_uuid: String,
     pub user_name: String,
-    pub email: Email,
+    /// `None` for accounts created by signing in with a wallet.
+    pub email: Option<Email>,
     pub wallet: Wallet,
-    pub password: Password,
+    /// `None` when the user has only ever signed in with a wallet.
+    pub password: Option<Password>,
     pub created_at: String,
-    pub total_reward: f64,
+    /// Rewards earned, per reward token.
+    #[serde(deserialize_with = "deserialize_total_reward")]
+    pub total_reward: Vec<TokenTotal>,
     pub quizes: Vec<QuizResult>,
     pub leaderboard_score: HashMap<String, f64>,
 }
 
+/// The reward token of a chain, which is what quizzes and hackathons on it pay out in.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct RewardToken {
+    /// `None` for the default chain, which protocols registered before chains were
+    /// recorded are on.
+    pub chain_id: Option<u64>,
+    pub decimals: u8,
+}
+
+impl RewardToken {
+    /// The token rewards were paid in before chains had their own reward token.
+    pub fn legacy() -> RewardToken {
+        RewardToken {
+            chain_id: None,
+            decimals: reward_token_decimals_from_env(),
+        }
+    }
+}
+
+/// Base units of one reward token a user has earned.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TokenTotal {
+    pub token: RewardToken,
+    pub amount: TokenAmount,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct QuizResult {
     pub quiz_uuid: String,
     pub score: f64,
-    pub reward: f64,
+    pub reward: TokenAmount,
+    #[serde(default = "RewardToken::legacy")]
+    pub token: RewardToken,
+}
+
+/// A `QuizResult` with the reward as a decimal string in whole tokens.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct QuizResultView {
+    pub quiz_uuid: String,
+    pub score: f64,
+    pub reward: String,
+    pub chain_id: Option<u64>,
+}
+
+/// A `TokenTotal` as a decimal string in whole tokens.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TokenTotalView {
+    pub chain_id: Option<u64>,
+    pub amount: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct SimpleUserStruct {
     pub user_uuid: String,
     pub user_name: String,
-    pub email: String,
+    pub email: Option<String>,
     pub wallet_address: Option<String>,
 }
 
+/// Everything about a user that is safe to hand back through the API.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UserProfile {
+    pub user_uuid: String,
+    pub user_name: String,
+    pub email: Option<Email>,
+    pub wallet: Wallet,
+    pub created_at: String,
+    /// Decimal strings in whole tokens, such as `"12.5"`, one per reward token.
+    pub total_reward: Vec<TokenTotalView>,
+    pub quizes: Vec<QuizResultView>,
+    pub leaderboard_score: HashMap<String, f64>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Wallet {
     pub wallet_address: Option<String>,
     pub updated_at: String,
+    /// Set once the user has signed a challenge with the address, which also lets them
+    /// sign in with it.
+    #[serde(default)]
+    pub verified: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -46,6 +119,16 @@ pub struct Password {
     pub updated_at: String,
 }
 
+/// Rules a new password has to satisfy, read from the `PASSWORD_*` environment variables.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub require_lowercase: bool,
+    pub require_uppercase: bool,
+    pub require_digit: bool,
+    pub require_symbol: bool,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Email {
     pub email: String,
@@ -59,24 +142,45 @@ impl User {
         let wallet = Wallet {
             wallet_address: None,
             updated_at: String::from("0000-00-00 00:00:00"),
+            verified: false,
         };
         let created_at = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
 
         let email_struct = Email::new(email)?;
+        PasswordPolicy::from_env().validate(&password)?;
         let password_struct = Password::new(password)?;
         Ok(User {
             user_uuid,
             user_name,
-            email: email_struct,
+            email: Some(email_struct),
             wallet,
-            password: password_struct,
+            password: Some(password_struct),
             created_at,
-            total_reward: 0.0,
+            total_reward: Vec::new(),
             quizes: Vec::new(),
             leaderboard_score: HashMap::new(),
         })
     }
 
+    /// A wallet-only account for someone signing in with `wallet_address` for the first
+    /// time. The address has already been proven, so the wallet starts out verified.
+    pub fn from_wallet(wallet_address: String) -> Self {
+        let created_at = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let mut wallet = Wallet::new(wallet_address.clone());
+        wallet.verified = true;
+        User {
+            user_uuid: Uuid::new_v4().to_string(),
+            user_name: wallet_address,
+            email: None,
+            wallet,
+            password: None,
+            created_at,
+            total_reward: Vec::new(),
+            quizes: Vec::new(),
+            leaderboard_score: HashMap::new(),
+        }
+    }
+
     pub fn update_leader_board_point(
         &mut self,
         protocol_name: String,
@@ -84,61 +188,140 @@ impl User {
     ) -> Result<Self, String> {
         if let Some(score) = self
             .leaderboard_score
-            .get_mut(&protocol_name.to_lowercase())
+            .get_mut(&protocol_name.to_uppercase())
         {
             *score += leaderboard_score;
         } else {
             self.leaderboard_score
                 .insert(protocol_name.to_uppercase(), leaderboard_score);
         }
-        return Ok(self.clone());
+        Ok(self.clone())
     }
 
-    pub fn update_total_reward(&mut self, reward: f64) -> Result<Self, String> {
-        self.total_reward += reward;
-        return Ok(self.clone());
+    pub fn update_total_reward(
+        &mut self,
+        reward: TokenAmount,
+        token: RewardToken,
+    ) -> Result<Self, String> {
+        match self
+            .total_reward
+            .iter_mut()
+            .find(|total| total.token == token)
+        {
+            Some(total) => {
+                total.amount = total
+                    .amount
+                    .checked_add(reward)
+                    .ok_or("Total reward overflowed")?
+            }
+            None => self.total_reward.push(TokenTotal {
+                token,
+                amount: reward,
+            }),
+        }
+        Ok(self.clone())
+    }
+
+    /// Base units earned in `token`.
+    pub fn total_reward_in(&self, token: RewardToken) -> TokenAmount {
+        self.total_reward
+            .iter()
+            .find(|total| total.token == token)
+            .map(|total| total.amount)
+            .unwrap_or(TokenAmount::ZERO)
     }
 
     pub fn display(&self) -> SimpleUserStruct {
         SimpleUserStruct {
             user_uuid: self.user_uuid.clone(),
             user_name: self.user_name.clone(),
-            email: self.email.email.clone(),
+            email: self.email.as_ref().map(|email| email.email.clone()),
             wallet_address: self.wallet.wallet_address.clone(),
         }
     }
 
+    pub fn profile(&self) -> UserProfile {
+        UserProfile {
+            user_uuid: self.user_uuid.clone(),
+            user_name: self.user_name.clone(),
+            email: self.email.clone(),
+            wallet: self.wallet.clone(),
+            created_at: self.created_at.clone(),
+            total_reward: self
+                .total_reward
+                .iter()
+                .map(|total| TokenTotalView {
+                    chain_id: total.token.chain_id,
+                    amount: total.amount.format_units(total.token.decimals),
+                })
+                .collect(),
+            quizes: self
+                .quizes
+                .iter()
+                .map(|result| QuizResultView {
+                    quiz_uuid: result.quiz_uuid.clone(),
+                    score: result.score,
+                    reward: result.reward.format_units(result.token.decimals),
+                    chain_id: result.token.chain_id,
+                })
+                .collect(),
+            leaderboard_score: self.leaderboard_score.clone(),
+        }
+    }
+
     pub fn view_user_leaderboard(&self, protocol_name: String) -> Result<f64, String> {
-        if let Some(score) = self.leaderboard_score.get(&protocol_name.to_lowercase()) {
-            return Ok(*score);
+        if let Some(score) = self.leaderboard_score.get(&protocol_name.to_uppercase()) {
+            Ok(*score)
         } else {
-            return Ok(0.0);
+            Ok(0.0)
         }
     }
 
     pub fn update_email(&mut self, email: String) -> Result<Self, String> {
-        self.email.update_email(email);
-        return Ok(self.clone());
+        match self.email.as_mut() {
+            Some(existing) => {
+                existing.update_email(email);
+            }
+            None => self.email = Some(Email::new(email)?),
+        }
+        Ok(self.clone())
     }
 
     pub fn change_password(&mut self, password: String) -> Result<Self, String> {
-        let mut hasher = Sha256::new();
-        hasher.update(password.as_bytes());
-        let password_hash = hex::encode(&hasher.finalize());
+        PasswordPolicy::from_env().validate(&password)?;
+        let password_hash = hash_password(&password)?;
 
-        self.password.update_password(password_hash);
-        return Ok(self.clone());
+        match self.password.as_mut() {
+            Some(existing) => {
+                existing.update_password(password_hash);
+            }
+            None => self.password = Some(Password::new(password)?),
+        }
+        Ok(self.clone())
     }
 
+    /// Always false for wallet-only accounts, which have no password to compare.
     pub fn compare_password(&self, password: String) -> bool {
-        let mut hasher = Sha256::new();
-        hasher.update(password.as_bytes());
-        let password_hash = hex::encode(&hasher.finalize());
+        match self.password.as_ref() {
+            Some(stored) => stored.verify(&password),
+            None => false,
+        }
+    }
 
-        if self.password.password_hash == password_hash {
-            return true;
+    /// Re-hashes a password stored in the legacy unsalted SHA-256 format.
+    ///
+    /// Only call this after `compare_password` succeeded with the same `password`.
+    /// Returns `Ok(true)` when the stored hash changed and the user must be saved.
+    pub fn upgrade_password_hash(&mut self, password: &str) -> Result<bool, String> {
+        let Some(stored) = self.password.as_mut() else {
+            return Ok(false);
+        };
+        if !stored.is_legacy() {
+            return Ok(false);
         }
-        return false;
+        let password_hash = hash_password(password)?;
+        stored.update_password(password_hash);
+        Ok(true)
     }
 }
 
@@ -150,12 +333,12 @@ impl Email {
             verified: false,
             updated_at: created_at,
         };
-        return Ok(email_obj);
+        Ok(email_obj)
     }
 
     pub fn verify(&mut self) -> Result<bool, String> {
         self.verified = true;
-        return Ok(self.verified);
+        Ok(self.verified)
     }
 
     pub fn get_email(&self) -> &str {
@@ -169,32 +352,51 @@ impl Email {
     pub fn update_email(&mut self, email: String) -> bool {
         self.email = email;
         self.verified = false;
-        return !self.verified;
+        !self.verified
     }
 }
 
 impl Password {
     pub fn new(password: String) -> Result<Password, String> {
         let created_at = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
-        let mut hasher = Sha256::new();
-        hasher.update(password.as_bytes());
-        let password_hash = hex::encode(&hasher.finalize());
+        let password_hash = hash_password(&password)?;
 
         let password_obj = Password {
             password_hash,
             updated_at: created_at,
         };
-        return Ok(password_obj);
+        Ok(password_obj)
     }
 
     pub fn get_password_hash(&self) -> &str {
         &self.password_hash
     }
 
+    /// Hashes written before Argon2id was introduced are bare SHA-256 hex digests.
+    pub fn is_legacy(&self) -> bool {
+        !self.password_hash.starts_with('$')
+    }
+
+    pub fn verify(&self, password: &str) -> bool {
+        if self.is_legacy() {
+            let mut hasher = Sha256::new();
+            hasher.update(password.as_bytes());
+            let password_hash = hex::encode(hasher.finalize());
+            return constant_time_eq(self.password_hash.as_bytes(), password_hash.as_bytes());
+        }
+
+        match PasswordHash::new(&self.password_hash) {
+            Ok(parsed_hash) => Argon2::default()
+                .verify_password(password.as_bytes(), &parsed_hash)
+                .is_ok(),
+            Err(_) => false,
+        }
+    }
+
     pub fn update_password(&mut self, password_hash: String) -> bool {
         self.password_hash = password_hash;
         self.updated_at = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
-        return !self.password_hash.is_empty();
+        !self.password_hash.is_empty()
     }
 }
 
@@ -204,12 +406,106 @@ impl Wallet {
         Wallet {
             wallet_address: Some(wallet_address),
             updated_at,
+            verified: false,
         }
     }
 
     pub fn update_wallet(&mut self, wallet_address: String) -> bool {
         self.wallet_address = Some(wallet_address);
         self.updated_at = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
-        return !self.wallet_address.is_none();
+        self.verified = false;
+        self.wallet_address.is_some()
     }
+
+    /// Links an address the user has signed a challenge with.
+    pub fn link_verified(&mut self, wallet_address: String) {
+        self.update_wallet(wallet_address);
+        self.verified = true;
+    }
+
+    /// Drops the linked address, such as when another account proves it owns it.
+    pub fn unlink(&mut self) {
+        self.wallet_address = None;
+        self.updated_at = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        self.verified = false;
+    }
+}
+
+impl PasswordPolicy {
+    pub fn from_env() -> PasswordPolicy {
+        let flag = |name: &str, default: bool| match env::var(name) {
+            Ok(value) => matches!(value.to_lowercase().as_str(), "1" | "true" | "yes"),
+            Err(_) => default,
+        };
+        PasswordPolicy {
+            min_length: env::var("PASSWORD_MIN_LENGTH")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(8),
+            require_lowercase: flag("PASSWORD_REQUIRE_LOWERCASE", true),
+            require_uppercase: flag("PASSWORD_REQUIRE_UPPERCASE", false),
+            require_digit: flag("PASSWORD_REQUIRE_DIGIT", true),
+            require_symbol: flag("PASSWORD_REQUIRE_SYMBOL", false),
+        }
+    }
+
+    pub fn validate(&self, password: &str) -> Result<(), String> {
+        if password.chars().count() < self.min_length {
+            return Err(format!(
+                "Password must be at least {} characters long",
+                self.min_length
+            ));
+        }
+        if self.require_lowercase && !password.chars().any(|c| c.is_lowercase()) {
+            return Err("Password must contain a lowercase letter".to_string());
+        }
+        if self.require_uppercase && !password.chars().any(|c| c.is_uppercase()) {
+            return Err("Password must contain an uppercase letter".to_string());
+        }
+        if self.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+            return Err("Password must contain a digit".to_string());
+        }
+        if self.require_symbol && !password.chars().any(|c| !c.is_alphanumeric()) {
+            return Err("Password must contain a symbol".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Argon2id with a fresh random salt, encoded as a PHC string.
+fn hash_password(password: &str) -> Result<String, String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| format!("Error hashing password: {}", e))
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Users credited before rewards were kept per token store `total_reward` as a single
+/// amount; it is read back as earned in the legacy token.
+fn deserialize_total_reward<'de, D>(deserializer: D) -> Result<Vec<TokenTotal>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StoredTotal {
+        PerToken(Vec<TokenTotal>),
+        Legacy(TokenAmount),
+    }
+
+    Ok(match StoredTotal::deserialize(deserializer)? {
+        StoredTotal::PerToken(totals) => totals,
+        StoredTotal::Legacy(amount) => vec![TokenTotal {
+            token: RewardToken::legacy(),
+            amount,
+        }],
+    })
 }
@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum ChallengePurpose {
+    /// Prove control of an address before linking it to a signed-in account.
+    LinkWallet,
+    /// Sign in with the address, creating a wallet-only account on first use.
+    SignIn,
+}
+
+/// A Sign-In with Ethereum (EIP-4361) message that has to be signed with `address`,
+/// either to link it to an account or to sign in with it. Each nonce can be redeemed
+/// once.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WalletChallenge {
+    pub nonce: String,
+    #[serde(default = "link_wallet")]
+    pub purpose: ChallengePurpose,
+    /// The account linking the wallet; `None` for sign-in challenges.
+    pub user_uuid: Option<String>,
+    /// EIP-55 checksummed.
+    pub address: String,
+    pub chain_id: u64,
+    /// The exact text the wallet is asked to sign.
+    pub message: String,
+    pub issued_at: i64,
+    pub expires_at: i64,
+    pub used: bool,
+}
+
+/// What a client needs to have the challenge signed.
+#[derive(Debug, Serialize, Clone)]
+pub struct WalletChallengeView {
+    pub nonce: String,
+    pub address: String,
+    pub message: String,
+    pub expires_at: i64,
+}
+
+impl ChallengePurpose {
+    pub fn to_string(&self) -> &'static str {
+        match self {
+            ChallengePurpose::LinkWallet => "LinkWallet",
+            ChallengePurpose::SignIn => "SignIn",
+        }
+    }
+}
+
+fn link_wallet() -> ChallengePurpose {
+    ChallengePurpose::LinkWallet
+}
+
+impl WalletChallenge {
+    pub fn is_expired(&self, now: i64) -> bool {
+        now >= self.expires_at
+    }
+
+    pub fn view(&self) -> WalletChallengeView {
+        WalletChallengeView {
+            nonce: self.nonce.clone(),
+            address: self.address.clone(),
+            message: self.message.clone(),
+            expires_at: self.expires_at,
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use crate::services::blob_store::BlobStore;
+use crate::utils::api_response::{ApiResponse, ErrorCode};
+use actix_web::{
+    get,
+    http::header::ContentType,
+    web::{Data, Path},
+    HttpResponse,
+};
+
+/// Serves a grading payload published off-chain, for the coprocessor to fetch and check
+/// against the hash it was given on-chain.
+#[get("/blobs/{hash}")]
+pub async fn get_blob(
+    store: Data<BlobStore>,
+    path: Path<String>,
+) -> Result<HttpResponse, ApiResponse> {
+    match store.get(&path.into_inner()) {
+        Ok(Some(blob)) => Ok(HttpResponse::Ok()
+            .content_type(ContentType::octet_stream())
+            .body(blob)),
+        Ok(None) => Err(ApiResponse::error(
+            404,
+            ErrorCode::NotFound,
+            "Blob not found",
+        )),
+        Err(message) => Err(ApiResponse::error(400, ErrorCode::BadRequest, message)),
+    }
+}
@@ -0,0 +1,9 @@
+use crate::services::chain_registry::ChainRegistry;
+use crate::utils::api_response::ApiResponse;
+use actix_web::{get, web::Data};
+
+/// The chains protocols can be registered on.
+#[get("/chains")]
+pub async fn get_chains(registry: Data<ChainRegistry>) -> ApiResponse {
+    ApiResponse::new(200, registry.infos())
+}
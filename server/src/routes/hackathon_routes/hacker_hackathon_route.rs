@@ -0,0 +1,284 @@
+use crate::models::hackathon_model::{
+    HackathonPhase, Invitation, InvitationStatus, ProjectSubmission,
+};
+use crate::services::storage::Storage;
+use crate::utils::{
+    api_response::{ApiResponse, ErrorCode, FieldError},
+    auth::AuthenticatedUser,
+};
+use actix_web::{
+    post,
+    web::{Data, Json},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+macro_rules! try_or_return {
+    ($result:expr) => {
+        match $result {
+            Ok(value) => value,
+            Err(e) => return ApiResponse::from(e),
+        }
+    };
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubmitRegisterHackathon {
+    pub hackathon_uuid: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubmitCreateTeam {
+    pub hackathon_uuid: String,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubmitInviteTeamMember {
+    pub hackathon_uuid: String,
+    pub team_uuid: String,
+    pub invitee_uuid: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubmitRespondInvitation {
+    pub hackathon_uuid: String,
+    pub team_uuid: String,
+    pub accept: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubmitProject {
+    pub hackathon_uuid: String,
+    pub repo_url: String,
+    pub demo_url: String,
+    pub description: String,
+    pub track_ids: Vec<String>,
+}
+
+fn not_open(message: &str) -> ApiResponse {
+    ApiResponse::error(400, ErrorCode::HackathonNotOpen, message)
+}
+
+#[post("hackathon/register")]
+pub async fn register_for_hackathon(
+    db: Data<dyn Storage>,
+    request: Json<SubmitRegisterHackathon>,
+    auth: AuthenticatedUser,
+) -> ApiResponse {
+    let mut hackathon = try_or_return!(
+        db.get_hackathon_via_uuid(request.hackathon_uuid.clone())
+            .await
+    );
+    if hackathon.phase(chrono::Utc::now().timestamp()) != HackathonPhase::Registration {
+        return not_open("Hackathon registration is closed");
+    }
+    let user = &auth.user;
+    let Some(wallet_address) = user.wallet.wallet_address.clone() else {
+        return ApiResponse::error(
+            400,
+            ErrorCode::WalletNotLinked,
+            "User does not have a linked wallet address",
+        );
+    };
+    let leaderboard_score = user
+        .view_user_leaderboard(hackathon.protocol.clone())
+        .unwrap_or(0.0);
+    if leaderboard_score < hackathon.min_leaderboard_score {
+        return ApiResponse::error(
+            403,
+            ErrorCode::NotEligible,
+            format!(
+                "A leaderboard score of {} on {} is required to register",
+                hackathon.min_leaderboard_score, hackathon.protocol
+            ),
+        );
+    }
+    if !hackathon.register(user.user_uuid.clone(), wallet_address) {
+        return ApiResponse::error(
+            409,
+            ErrorCode::Conflict,
+            "Already registered for this hackathon",
+        );
+    }
+    try_or_return!(db.update_hackathon(hackathon).await);
+    ApiResponse::message(200, "Registered for hackathon successfully")
+}
+
+#[post("hackathon/team/create")]
+pub async fn create_hackathon_team(
+    db: Data<dyn Storage>,
+    request: Json<SubmitCreateTeam>,
+    auth: AuthenticatedUser,
+) -> ApiResponse {
+    let mut hackathon = try_or_return!(
+        db.get_hackathon_via_uuid(request.hackathon_uuid.clone())
+            .await
+    );
+    match hackathon.phase(chrono::Utc::now().timestamp()) {
+        HackathonPhase::Registration | HackathonPhase::Building => {}
+        _ => return not_open("Teams can no longer be formed"),
+    }
+    if hackathon.participant(auth.user_uuid()).is_none() {
+        return ApiResponse::error(
+            403,
+            ErrorCode::Forbidden,
+            "Register for the hackathon before creating a team",
+        );
+    }
+    let team = match hackathon.create_team(request.name.clone(), auth.user_uuid().to_string()) {
+        Ok(team) => team,
+        Err(message) => return ApiResponse::error(409, ErrorCode::Conflict, message),
+    };
+    try_or_return!(db.update_hackathon(hackathon).await);
+    ApiResponse::new(201, json!({ "team_uuid": team.uuid }))
+}
+
+#[post("hackathon/team/invite")]
+pub async fn invite_team_member(
+    db: Data<dyn Storage>,
+    request: Json<SubmitInviteTeamMember>,
+    auth: AuthenticatedUser,
+) -> ApiResponse {
+    let mut hackathon = try_or_return!(
+        db.get_hackathon_via_uuid(request.hackathon_uuid.clone())
+            .await
+    );
+    match hackathon.phase(chrono::Utc::now().timestamp()) {
+        HackathonPhase::Registration | HackathonPhase::Building => {}
+        _ => return not_open("Teams can no longer be changed"),
+    }
+    if hackathon.participant(&request.invitee_uuid).is_none() {
+        return ApiResponse::error(
+            400,
+            ErrorCode::BadRequest,
+            "Invitee is not registered for the hackathon",
+        );
+    }
+    if hackathon.team_of(&request.invitee_uuid).is_some() {
+        return ApiResponse::error(409, ErrorCode::Conflict, "Invitee already has a team");
+    }
+    let max_team_size = hackathon.max_team_size;
+    let Some(team) = hackathon.team_mut(&request.team_uuid) else {
+        return ApiResponse::error(404, ErrorCode::NotFound, "Team not found");
+    };
+    if team.leader_uuid != auth.user_uuid() {
+        return ApiResponse::error(
+            403,
+            ErrorCode::Forbidden,
+            "Only the team leader can send invitations",
+        );
+    }
+    if team.pending_invitation_mut(&request.invitee_uuid).is_some() {
+        return ApiResponse::error(409, ErrorCode::Conflict, "Invitation already pending");
+    }
+    if team.reserved_seats() >= max_team_size {
+        return ApiResponse::error(409, ErrorCode::Conflict, "Team is full");
+    }
+    team.invitations.push(Invitation {
+        invitee_uuid: request.invitee_uuid.clone(),
+        invited_by: auth.user_uuid().to_string(),
+        status: InvitationStatus::Pending,
+        created_at: chrono::Utc::now().timestamp(),
+    });
+    try_or_return!(db.update_hackathon(hackathon).await);
+    ApiResponse::message(200, "Invitation sent successfully")
+}
+
+#[post("hackathon/team/respond")]
+pub async fn respond_team_invitation(
+    db: Data<dyn Storage>,
+    request: Json<SubmitRespondInvitation>,
+    auth: AuthenticatedUser,
+) -> ApiResponse {
+    let mut hackathon = try_or_return!(
+        db.get_hackathon_via_uuid(request.hackathon_uuid.clone())
+            .await
+    );
+    match hackathon.phase(chrono::Utc::now().timestamp()) {
+        HackathonPhase::Registration | HackathonPhase::Building => {}
+        _ => return not_open("Teams can no longer be changed"),
+    }
+    if request.accept && hackathon.team_of(auth.user_uuid()).is_some() {
+        return ApiResponse::error(409, ErrorCode::Conflict, "You already have a team");
+    }
+    let Some(team) = hackathon.team_mut(&request.team_uuid) else {
+        return ApiResponse::error(404, ErrorCode::NotFound, "Team not found");
+    };
+    let Some(invitation) = team.pending_invitation_mut(auth.user_uuid()) else {
+        return ApiResponse::error(404, ErrorCode::NotFound, "No pending invitation");
+    };
+    if request.accept {
+        invitation.status = InvitationStatus::Accepted;
+        team.members.push(auth.user_uuid().to_string());
+    } else {
+        invitation.status = InvitationStatus::Declined;
+    }
+    try_or_return!(db.update_hackathon(hackathon).await);
+    ApiResponse::message(200, "Invitation answered successfully")
+}
+
+#[post("hackathon/submit")]
+pub async fn submit_hackathon_project(
+    db: Data<dyn Storage>,
+    request: Json<SubmitProject>,
+    auth: AuthenticatedUser,
+) -> ApiResponse {
+    let mut hackathon = try_or_return!(
+        db.get_hackathon_via_uuid(request.hackathon_uuid.clone())
+            .await
+    );
+    match hackathon.phase(chrono::Utc::now().timestamp()) {
+        HackathonPhase::Registration | HackathonPhase::Building => {}
+        _ => return not_open("Submission period is over"),
+    }
+
+    let mut invalid_fields = Vec::new();
+    if !request.repo_url.starts_with("https://") {
+        invalid_fields.push(FieldError::new("repo_url", "Expected an https:// URL"));
+    }
+    if !request.demo_url.is_empty() && !request.demo_url.starts_with("https://") {
+        invalid_fields.push(FieldError::new("demo_url", "Expected an https:// URL"));
+    }
+    if request.description.trim().is_empty() {
+        invalid_fields.push(FieldError::new("description", "Description is required"));
+    }
+    if request.track_ids.is_empty()
+        || request.track_ids.iter().any(|track_id| {
+            !hackathon
+                .prize_tracks
+                .iter()
+                .any(|track| &track.id == track_id)
+        })
+    {
+        invalid_fields.push(FieldError::new(
+            "track_ids",
+            "Expected one or more of the hackathon's prize track ids",
+        ));
+    }
+    if !invalid_fields.is_empty() {
+        return ApiResponse::validation_error("Invalid project submission", invalid_fields);
+    }
+
+    let Some(team_uuid) = hackathon
+        .team_of(auth.user_uuid())
+        .map(|team| team.uuid.clone())
+    else {
+        return ApiResponse::error(
+            403,
+            ErrorCode::Forbidden,
+            "Join or create a team before submitting",
+        );
+    };
+    if let Some(team) = hackathon.team_mut(&team_uuid) {
+        team.submission = Some(ProjectSubmission {
+            repo_url: request.repo_url.clone(),
+            demo_url: request.demo_url.clone(),
+            description: request.description.clone(),
+            track_ids: request.track_ids.clone(),
+            submitted_at: chrono::Utc::now().timestamp(),
+        });
+    }
+    try_or_return!(db.update_hackathon(hackathon).await);
+    ApiResponse::message(200, "Project submitted successfully")
+}
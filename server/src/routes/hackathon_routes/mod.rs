@@ -0,0 +1,2 @@
+pub mod hacker_hackathon_route;
+pub mod protocol_hackathon_route;
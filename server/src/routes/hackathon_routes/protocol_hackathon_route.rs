@@ -0,0 +1,291 @@
+use crate::models::hackathon_model::{
+    CriterionScore, Hackathon, HackathonPhase, HackathonStatus, JudgeScore, PrizeTrack,
+    RubricCriterion, TokenAmount,
+};
+use crate::models::protocol_model::Permission;
+use crate::models::settlement_model::{SettlementJob, SettlementTarget};
+use crate::services::chain_registry::ChainRegistry;
+use crate::services::storage::Storage;
+use crate::utils::{
+    api_response::{ApiResponse, ErrorCode, FieldError},
+    auth::AuthenticatedUser,
+};
+use actix_web::{
+    get, post,
+    web::Path,
+    web::{Data, Json},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+macro_rules! try_or_return {
+    ($result:expr) => {
+        match $result {
+            Ok(value) => value,
+            Err(e) => return ApiResponse::from(e),
+        }
+    };
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubmitCreateHackathon {
+    name: String,
+    description: String,
+    protocol: String,
+    registration_start: i64,
+    registration_end: i64,
+    submission_end: i64,
+    judging_end: i64,
+    max_team_size: usize,
+    #[serde(default)]
+    min_leaderboard_score: f64,
+    rubric: Vec<RubricCriterion>,
+    prize_tracks: Vec<SubmitPrizeTrack>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubmitPrizeTrack {
+    id: String,
+    name: String,
+    description: String,
+    /// Decimal amounts in whole tokens, such as `"12.5"`.
+    rewards: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubmitAddJudge {
+    pub hackathon_uuid: String,
+    pub judge_uuid: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubmitScoreTeam {
+    pub hackathon_uuid: String,
+    pub team_uuid: String,
+    pub scores: Vec<CriterionScore>,
+    #[serde(default)]
+    pub comment: String,
+}
+
+#[post("hackathon/create")]
+pub async fn create_hackathon(
+    db: Data<dyn Storage>,
+    registry: Data<ChainRegistry>,
+    request: Json<SubmitCreateHackathon>,
+    auth: AuthenticatedUser,
+) -> ApiResponse {
+    let mut protocol = try_or_return!(db.get_protocol_via_name(request.protocol.clone()).await);
+    try_or_return!(auth.require_permission(&protocol, Permission::ManageHackathons));
+
+    let token_decimals = match registry.resolve(protocol.chain_id) {
+        Ok(chain) => chain.config.reward_token_decimals(),
+        Err(message) => return ApiResponse::error(500, ErrorCode::InternalError, message),
+    };
+    let mut prize_tracks = Vec::with_capacity(request.prize_tracks.len());
+    let mut invalid_fields = Vec::new();
+    for track in request.prize_tracks.iter() {
+        let mut rewards = Vec::with_capacity(track.rewards.len());
+        for reward in track.rewards.iter() {
+            match TokenAmount::parse_units(reward, token_decimals) {
+                Ok(amount) => rewards.push(amount),
+                Err(e) => invalid_fields.push(FieldError::new(
+                    "prize_tracks",
+                    &format!("Track {}: {}", track.id, e),
+                )),
+            }
+        }
+        prize_tracks.push(PrizeTrack {
+            id: track.id.clone(),
+            name: track.name.clone(),
+            description: track.description.clone(),
+            rewards,
+        });
+    }
+    if !invalid_fields.is_empty() {
+        return ApiResponse::validation_error("Invalid hackathon details", invalid_fields);
+    }
+
+    let hackathon = Hackathon::new(
+        request.name.clone(),
+        request.description.clone(),
+        protocol.name.clone(),
+        auth.user_uuid().to_string(),
+        request.registration_start,
+        request.registration_end,
+        request.submission_end,
+        request.judging_end,
+        request.max_team_size,
+        request.min_leaderboard_score,
+        request.rubric.clone(),
+        prize_tracks,
+        token_decimals,
+    );
+    if let Err(message) = hackathon.validate() {
+        return ApiResponse::error(422, ErrorCode::ValidationFailed, message);
+    }
+
+    try_or_return!(db.create_hackathon(hackathon.clone()).await);
+    try_or_return!(
+        db.create_settlement_job(SettlementJob::new(
+            SettlementTarget::Hackathon,
+            hackathon.uuid.clone(),
+            hackathon.judging_end,
+        ))
+        .await
+    );
+    protocol.update_total_expense(hackathon.total_reward());
+    protocol.add_hackathon(hackathon.uuid.clone());
+    match db.update_protocol(protocol).await {
+        Ok(_result) => ApiResponse::new(201, json!({ "hackathon_uuid": hackathon.uuid })),
+        Err(e) => ApiResponse::from(e),
+    }
+}
+
+#[post("hackathon/judges")]
+pub async fn add_hackathon_judge(
+    db: Data<dyn Storage>,
+    request: Json<SubmitAddJudge>,
+    auth: AuthenticatedUser,
+) -> ApiResponse {
+    let mut hackathon = try_or_return!(
+        db.get_hackathon_via_uuid(request.hackathon_uuid.clone())
+            .await
+    );
+    let protocol = try_or_return!(db.get_protocol_via_name(hackathon.protocol.clone()).await);
+    try_or_return!(auth.require_permission(&protocol, Permission::ManageHackathons));
+
+    if protocol.role_of(&request.judge_uuid).is_none() {
+        return ApiResponse::error(
+            400,
+            ErrorCode::BadRequest,
+            "Judges must be members of the protocol team",
+        );
+    }
+    if !hackathon.add_judge(request.judge_uuid.clone()) {
+        return ApiResponse::error(409, ErrorCode::Conflict, "User is already a judge");
+    }
+    try_or_return!(db.update_hackathon(hackathon).await);
+    ApiResponse::message(200, "Judge added successfully")
+}
+
+#[post("hackathon/score")]
+pub async fn score_hackathon_team(
+    db: Data<dyn Storage>,
+    request: Json<SubmitScoreTeam>,
+    auth: AuthenticatedUser,
+) -> ApiResponse {
+    let mut hackathon = try_or_return!(
+        db.get_hackathon_via_uuid(request.hackathon_uuid.clone())
+            .await
+    );
+    if !hackathon.is_judge(auth.user_uuid()) {
+        return ApiResponse::error(
+            403,
+            ErrorCode::Forbidden,
+            "Only the hackathon's judges can score teams",
+        );
+    }
+    if hackathon.phase(chrono::Utc::now().timestamp()) != HackathonPhase::Judging {
+        return ApiResponse::error(
+            400,
+            ErrorCode::HackathonNotOpen,
+            "Hackathon is not in its judging period",
+        );
+    }
+    for score in request.scores.iter() {
+        let Some(criterion) = hackathon
+            .rubric
+            .iter()
+            .find(|criterion| criterion.id == score.criterion_id)
+        else {
+            return ApiResponse::error(
+                422,
+                ErrorCode::ValidationFailed,
+                format!("Unknown rubric criterion {}", score.criterion_id),
+            );
+        };
+        if score.score > criterion.max_score {
+            return ApiResponse::error(
+                422,
+                ErrorCode::ValidationFailed,
+                format!(
+                    "Score for {} cannot exceed {}",
+                    criterion.id, criterion.max_score
+                ),
+            );
+        }
+    }
+
+    let Some(team) = hackathon.team_mut(&request.team_uuid) else {
+        return ApiResponse::error(404, ErrorCode::NotFound, "Team not found");
+    };
+    if team.submission.is_none() {
+        return ApiResponse::error(
+            400,
+            ErrorCode::BadRequest,
+            "Team has not submitted a project",
+        );
+    }
+    team.record_score(JudgeScore {
+        judge_uuid: auth.user_uuid().to_string(),
+        scores: request.scores.clone(),
+        comment: request.comment.clone(),
+        scored_at: chrono::Utc::now().timestamp(),
+    });
+    try_or_return!(db.update_hackathon(hackathon).await);
+    ApiResponse::message(200, "Score recorded successfully")
+}
+
+/// Judges' marks are only shown to protocol staff until the hackathon is settled.
+async fn hackathon_for_viewer(
+    db: &Data<dyn Storage>,
+    hackathon: Hackathon,
+    viewer: Option<&AuthenticatedUser>,
+) -> Hackathon {
+    if hackathon.status == HackathonStatus::Completed {
+        return hackathon;
+    }
+    if let Some(viewer) = viewer {
+        if let Ok(protocol) = db.get_protocol_via_name(hackathon.protocol.clone()).await {
+            if protocol.has_permission(viewer.user_uuid(), Permission::ViewAnalytics) {
+                return hackathon;
+            }
+        }
+    }
+    hackathon.without_scores()
+}
+
+#[get("/hackathons")]
+pub async fn get_all_hackathons(
+    db: Data<dyn Storage>,
+    auth: Option<AuthenticatedUser>,
+) -> ApiResponse {
+    let hackathons = try_or_return!(db.get_all_hackathons().await);
+    let mut views = Vec::with_capacity(hackathons.len());
+    for hackathon in hackathons {
+        views.push(hackathon_for_viewer(&db, hackathon, auth.as_ref()).await);
+    }
+    ApiResponse::new(200, views)
+}
+
+#[get("/hackathon/by-id/{hackathon_id}")]
+pub async fn get_hackathon_by_id(
+    db: Data<dyn Storage>,
+    path: Path<String>,
+    auth: Option<AuthenticatedUser>,
+) -> ApiResponse {
+    let hackathon_id = path.into_inner();
+    let hackathon = try_or_return!(db.get_hackathon_via_uuid(hackathon_id).await);
+    ApiResponse::new(
+        200,
+        hackathon_for_viewer(&db, hackathon, auth.as_ref()).await,
+    )
+}
+
+#[get("/hackathon/by-id/{hackathon_id}/transactions")]
+pub async fn get_hackathon_transactions(db: Data<dyn Storage>, path: Path<String>) -> ApiResponse {
+    let hackathon_id = path.into_inner();
+    try_or_return!(db.get_hackathon_via_uuid(hackathon_id.clone()).await);
+    let transactions = try_or_return!(db.get_transactions_via_target(hackathon_id).await);
+    ApiResponse::new(200, transactions)
+}
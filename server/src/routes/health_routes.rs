@@ -3,5 +3,5 @@ use actix_web::get;
 
 #[get("/health")]
 pub async fn health_check() -> ApiResponse {
-    ApiResponse::new(200, "Server is healthy".to_string())
+    ApiResponse::message(200, "Server is healthy")
 }
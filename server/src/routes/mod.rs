@@ -1,3 +1,5 @@
+pub mod blob_routes;
+pub mod chain_routes;
 pub mod hackathon_routes;
 pub mod health_routes;
 pub mod protocol_routes;
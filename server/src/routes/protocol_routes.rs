@@ -1,166 +1,248 @@
-use crate::models::protocol_model::Protocol;
-use crate::services::{db::Database, quiz_services::createProtocolOnchain};
-use crate::utils::jwt::is_valid_token;
-use crate::utils::{api_response::ApiResponse, jwt::decode_token};
+use crate::models::protocol_model::{Protocol, ProtocolView, StaffRole};
+use crate::services::{chain_registry::ChainRegistry, storage::Storage};
+use crate::utils::{
+    api_response::{ApiResponse, ErrorCode, FieldError},
+    auth::AuthenticatedUser,
+};
 use actix_web::{
-    cookie::{self, Cookie},
-    get, post,
+    delete, get, patch, post,
     web::Path,
     web::{Data, Json},
 };
-use actix_web::{HttpRequest, HttpResponse};
 use serde::{Deserialize, Serialize};
-use std::env;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SubmitRegisterProtocol {
     pub name: String,
+    /// Chain to deploy the protocol contract on; the registry's default when omitted.
+    #[serde(default)]
+    pub chain_id: Option<u64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SubmitAddProtocolStaff {
     pub protocol_name: String,
     pub staff_uuid: String,
+    pub role: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubmitUpdateStaffRole {
+    pub protocol_name: String,
+    pub staff_uuid: String,
+    pub role: String,
+}
+
+#[allow(clippy::result_large_err)]
+fn parse_role(role: &str) -> Result<StaffRole, ApiResponse> {
+    StaffRole::parse(role).ok_or_else(|| {
+        ApiResponse::validation_error(
+            "Invalid staff role",
+            vec![FieldError::new(
+                "role",
+                "Expected one of owner, admin, quiz_author or analyst",
+            )],
+        )
+    })
+}
+
+fn protocol_view(registry: &ChainRegistry, protocol: Protocol) -> ProtocolView {
+    ProtocolView {
+        chain: registry.info(protocol.chain_id),
+        protocol,
+    }
 }
 
 macro_rules! try_or_return {
     ($result:expr) => {
         match $result {
             Ok(value) => value,
-            Err(e) => return ApiResponse::new(e.error_code, e.message),
+            Err(e) => return ApiResponse::from(e),
         }
     };
 }
 
 #[post("protocol/register")]
 pub async fn register_protocol(
-    db: Data<Database>,
+    db: Data<dyn Storage>,
+    registry: Data<ChainRegistry>,
     request: Json<SubmitRegisterProtocol>,
-    req: HttpRequest,
+    auth: AuthenticatedUser,
 ) -> ApiResponse {
-    if let Some(cookie) = req.cookie("token") {
-        let jwt_secret = env::var("JWT_SECRET").expect("JWT SECRET IS NOT DEFINED");
-        match decode_token(&cookie.value().to_string(), &jwt_secret) {
-            Ok(token_data) => {
-                if is_valid_token(&token_data.claims) {
-                    let existing_protocol = db.get_protocol_via_name(request.name.clone()).await;
-                    match existing_protocol {
-                        Ok(_) => ApiResponse::new(409, "Protocol already registered".to_string()),
-                        Err(e) => match e.error_code {
-                            404 => {
-                                let mut protocol = Protocol::new(
-                                    request.name.clone(),
-                                    token_data.claims.user_uuid.clone(),
-                                );
-                                let result = createProtocolOnchain(
-                                    request.name.clone(),
-                                    protocol.protocol_uuid.clone(),
-                                )
-                                .await;
-                                if let Ok(address) = result {
-                                    protocol.contract_address = address;
-                                    protocol.staffs.push(token_data.claims.user_uuid.clone());
-                                    match db.create_protocol(protocol.clone()).await {
-                                        Ok(_result) => {
-                                            return ApiResponse::new(
-                                                201,
-                                                format!("{:?}", protocol.name),
-                                            )
-                                        }
-                                        Err(err) => {
-                                            return ApiResponse::new(err.error_code, err.message)
-                                        }
-                                    }
-                                } else {
-                                    return ApiResponse::new(
-                                        500,
-                                        "Error creating protocol onChain".to_string(),
-                                    );
-                                }
-                            }
-                            _ => return ApiResponse::new(e.error_code, e.message),
-                        },
+    let chain = match registry.resolve(request.chain_id) {
+        Ok(chain) => chain,
+        Err(message) => {
+            return ApiResponse::validation_error(
+                "Unknown chain",
+                vec![FieldError::new("chain_id", &message)],
+            )
+        }
+    };
+    let existing_protocol = db.get_protocol_via_name(request.name.clone()).await;
+    match existing_protocol {
+        Ok(_) => ApiResponse::error(409, ErrorCode::Conflict, "Protocol already registered"),
+        Err(e) => match e.error_code {
+            404 => {
+                let protocol = Protocol::new(
+                    request.name.clone(),
+                    auth.user_uuid().to_string(),
+                    chain.config.chain_id,
+                );
+                let result = chain
+                    .client
+                    .create_protocol(&request.name, &protocol.protocol_uuid)
+                    .await;
+                // The contract address is filled in by the indexer once the deployment is mined.
+                if result.is_ok() {
+                    match db.create_protocol(protocol.clone()).await {
+                        Ok(_result) => ApiResponse::new(202, protocol_view(&registry, protocol)),
+                        Err(err) => ApiResponse::from(err),
                     }
                 } else {
-                    return ApiResponse::new(401, "Invalid or expired token".to_string());
+                    ApiResponse::error(
+                        500,
+                        ErrorCode::OnchainFailure,
+                        "Error creating protocol onChain",
+                    )
                 }
             }
-            Err(_) => return ApiResponse::new(401, "Invalid token".to_string()),
-        }
-    } else {
-        return ApiResponse::new(401, "Missing token".to_string());
+            _ => ApiResponse::from(e),
+        },
     }
 }
 
-// Mofify this to ensure only admin can register new users
 #[post("protocol/team")]
 pub async fn add_protocol_staff(
-    db: Data<Database>,
+    db: Data<dyn Storage>,
+    registry: Data<ChainRegistry>,
     request: Json<SubmitAddProtocolStaff>,
-    req: HttpRequest,
+    auth: AuthenticatedUser,
 ) -> ApiResponse {
-    if let Some(cookie) = req.cookie("token") {
-        let jwt_secret = env::var("JWT_SECRET").expect("JWT SECRET IS NOT DEFINED");
-        match decode_token(&cookie.value().to_string(), &jwt_secret) {
-            Ok(token_data) => {
-                if is_valid_token(&token_data.claims) {
-                    let existing_protocol = db
-                        .get_protocol_via_name(request.protocol_name.clone())
-                        .await;
-                    match existing_protocol {
-                        Ok(mut protocol) => {
-                            if protocol.staffs.contains(&request.staff_uuid) {
-                                return ApiResponse::new(
-                                    409,
-                                    "User already added to the protocol team".to_string(),
-                                );
-                            } else {
-                                protocol.staffs.push(request.staff_uuid.clone());
-                                match db.update_protocol_team(protocol.clone()).await {
-                                    Ok(_result) => {
-                                        return ApiResponse::new(
-                                            200,
-                                            format!("{:?}", protocol.name),
-                                        )
-                                    }
-                                    Err(err) => {
-                                        return ApiResponse::new(err.error_code, err.message)
-                                    }
-                                }
-                            }
-                        }
-                        Err(e) => return ApiResponse::new(e.error_code, e.message),
-                    }
-                } else {
-                    return ApiResponse::new(401, "Invalid or expired token".to_string());
-                }
-            }
-            Err(_) => return ApiResponse::new(401, "Invalid token".to_string()),
-        }
-    } else {
-        return ApiResponse::new(401, "Missing token".to_string());
+    let role = try_or_return!(parse_role(&request.role));
+    let mut protocol = try_or_return!(
+        db.get_protocol_via_name(request.protocol_name.clone())
+            .await
+    );
+    try_or_return!(auth.require_permission(&protocol, role.managed_by()));
+    try_or_return!(db.get_user_via_uuid(request.staff_uuid.clone()).await);
+
+    if !protocol.add_staff(request.staff_uuid.clone(), role) {
+        return ApiResponse::error(
+            409,
+            ErrorCode::Conflict,
+            "User already added to the protocol team",
+        );
+    }
+    match db.update_protocol_team(protocol.clone()).await {
+        Ok(_result) => ApiResponse::new(200, protocol_view(&registry, protocol)),
+        Err(err) => ApiResponse::from(err),
+    }
+}
+
+#[patch("protocol/team")]
+pub async fn update_protocol_staff_role(
+    db: Data<dyn Storage>,
+    registry: Data<ChainRegistry>,
+    request: Json<SubmitUpdateStaffRole>,
+    auth: AuthenticatedUser,
+) -> ApiResponse {
+    let role = try_or_return!(parse_role(&request.role));
+    let mut protocol = try_or_return!(
+        db.get_protocol_via_name(request.protocol_name.clone())
+            .await
+    );
+    let Some(current_role) = protocol.role_of(&request.staff_uuid) else {
+        return ApiResponse::error(
+            404,
+            ErrorCode::NotFound,
+            "User is not a member of the protocol team",
+        );
+    };
+    // Both the role being taken away and the one being granted must be within the caller's reach.
+    try_or_return!(auth.require_permission(&protocol, current_role.managed_by()));
+    try_or_return!(auth.require_permission(&protocol, role.managed_by()));
+
+    if let Err(message) = protocol.update_staff_role(&request.staff_uuid, role) {
+        return ApiResponse::error(409, ErrorCode::Conflict, message);
+    }
+    match db.update_protocol_team(protocol.clone()).await {
+        Ok(_result) => ApiResponse::new(200, protocol_view(&registry, protocol)),
+        Err(err) => ApiResponse::from(err),
+    }
+}
+
+#[delete("/protocol/by-name/{name}/team/{staff_uuid}")]
+pub async fn remove_protocol_staff(
+    db: Data<dyn Storage>,
+    registry: Data<ChainRegistry>,
+    path: Path<(String, String)>,
+    auth: AuthenticatedUser,
+) -> ApiResponse {
+    let (name, staff_uuid) = path.into_inner();
+    let mut protocol = try_or_return!(db.get_protocol_via_name(name).await);
+    let Some(current_role) = protocol.role_of(&staff_uuid) else {
+        return ApiResponse::error(
+            404,
+            ErrorCode::NotFound,
+            "User is not a member of the protocol team",
+        );
+    };
+    try_or_return!(auth.require_permission(&protocol, current_role.managed_by()));
+
+    if let Err(message) = protocol.remove_staff(&staff_uuid) {
+        return ApiResponse::error(409, ErrorCode::Conflict, message);
+    }
+    match db.update_protocol_team(protocol.clone()).await {
+        Ok(_result) => ApiResponse::new(200, protocol_view(&registry, protocol)),
+        Err(err) => ApiResponse::from(err),
     }
 }
 
 #[get("/protocols")]
-pub async fn get_all_protocols(db: Data<Database>) -> ApiResponse {
+pub async fn get_all_protocols(
+    db: Data<dyn Storage>,
+    registry: Data<ChainRegistry>,
+) -> ApiResponse {
     match db.get_all_protocols().await {
-        Ok(users) => ApiResponse::new(200, format!("{:?}", users)),
-        Err(e) => ApiResponse::new(e.error_code, e.message),
+        Ok(protocols) => {
+            let views: Vec<ProtocolView> = protocols
+                .into_iter()
+                .map(|protocol| protocol_view(&registry, protocol))
+                .collect();
+            ApiResponse::new(200, views)
+        }
+        Err(e) => ApiResponse::from(e),
     }
 }
 
 #[get("/protocol/by-name/{name}")]
-pub async fn get_protocol_via_name(db: Data<Database>, request: Path<String>) -> ApiResponse {
+pub async fn get_protocol_via_name(
+    db: Data<dyn Storage>,
+    registry: Data<ChainRegistry>,
+    request: Path<String>,
+) -> ApiResponse {
     let name = request.into_inner();
 
     let protocol: Protocol = try_or_return!(db.get_protocol_via_name(name).await);
-    return ApiResponse::new(200, format!("{:?}", protocol));
+    ApiResponse::new(200, protocol_view(&registry, protocol))
 }
 
 #[get("/protocol/by-id/{protocol_id}")]
-pub async fn get_protocol_by_id(db: Data<Database>, path: Path<String>) -> ApiResponse {
+pub async fn get_protocol_by_id(
+    db: Data<dyn Storage>,
+    registry: Data<ChainRegistry>,
+    path: Path<String>,
+) -> ApiResponse {
     let protocol_id = path.into_inner();
     let protocol: Protocol = try_or_return!(db.get_protocol_via_id(protocol_id.clone()).await);
-    ApiResponse::new(200, format!("{:?}", protocol))
+    ApiResponse::new(200, protocol_view(&registry, protocol))
+}
+
+/// Every transaction sent for the protocol: its deployment and each `gradeQuiz`.
+#[get("/protocol/by-id/{protocol_id}/transactions")]
+pub async fn get_protocol_transactions(db: Data<dyn Storage>, path: Path<String>) -> ApiResponse {
+    let protocol_id = path.into_inner();
+    let protocol: Protocol = try_or_return!(db.get_protocol_via_id(protocol_id).await);
+    let transactions = try_or_return!(db.get_transactions_via_protocol(protocol.name).await);
+    ApiResponse::new(200, transactions)
 }
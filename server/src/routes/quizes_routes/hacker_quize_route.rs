@@ -1,15 +1,15 @@
-use crate::models::quiz_model::{Quiz, QuizAnswer, Status};
-use crate::services::db::Database;
-use crate::utils::jwt::is_valid_token;
-use crate::utils::{api_response::ApiResponse, jwt::decode_token};
+use crate::models::quiz_model::{QuizAnswer, Status};
+use crate::models::settlement_model::SettlementStage;
+use crate::services::storage::Storage;
+use crate::utils::{
+    api_response::{ApiResponse, ErrorCode},
+    auth::AuthenticatedUser,
+};
 use actix_web::{
-    cookie::{self, Cookie},
-    get, post, put,
+    post,
     web::{Data, Json},
 };
-use actix_web::{HttpRequest, HttpResponse};
 use serde::{Deserialize, Serialize};
-use std::env;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SubmitStartQuiz {
@@ -26,182 +26,163 @@ macro_rules! try_or_return {
     ($result:expr) => {
         match $result {
             Ok(value) => value,
-            Err(e) => return ApiResponse::new(e.error_code, e.message),
+            Err(e) => return ApiResponse::from(e),
         }
     };
 }
 
 #[post("quiz/join")]
 pub async fn start_quiz(
-    db: Data<Database>,
+    db: Data<dyn Storage>,
     request: Json<SubmitStartQuiz>,
-    req: HttpRequest,
+    auth: AuthenticatedUser,
 ) -> ApiResponse {
-    if let Some(cookie) = req.cookie("token") {
-        let jwt_secret = env::var("JWT_SECRET").expect("JWT SECRET IS NOT DEFINED");
-        match decode_token(&cookie.value().to_string(), &jwt_secret) {
-            Ok(token_data) => {
-                if is_valid_token(&token_data.claims) {
-                    let quiz =
-                        try_or_return!(db.get_quiz_via_uuid(request.quiz_uuid.clone()).await);
-                    match quiz.status {
-                        Status::Completed => {
-                            return ApiResponse::new(
-                                400,
-                                "Quiz has already been completed".to_string(),
-                            );
-                        }
-                        Status::Pending => {
-                            if quiz.start_time <= chrono::Utc::now().timestamp() {
-                                if quiz.end_time < chrono::Utc::now().timestamp() {
-                                    return ApiResponse::new(
-                                        400,
-                                        "Submission period exceeded".to_string(),
-                                    );
-                                }
-                                let user = db
-                                    .get_user_via_uuid(token_data.claims.user_uuid.clone())
-                                    .await
-                                    .unwrap();
-                                if user.wallet.wallet_address.clone().is_none() {
-                                    return ApiResponse::new(
-                                        400,
-                                        "User does not have a linked wallet address".to_string(),
-                                    );
-                                }
-                                let mut quiz_obj = quiz.clone();
-                                quiz_obj.status = Status::Ongoing;
-                                quiz_obj.add_participant(
-                                    token_data.claims.user_uuid.clone(),
-                                    user.wallet.wallet_address.clone().unwrap(),
-                                );
+    let quiz = try_or_return!(db.get_quiz_via_uuid(request.quiz_uuid.clone()).await);
+    match quiz.status {
+        Status::Completed => ApiResponse::error(
+            400,
+            ErrorCode::QuizNotOpen,
+            "Quiz has already been completed",
+        ),
+        Status::Pending => {
+            if quiz.start_time <= chrono::Utc::now().timestamp() {
+                if quiz.end_time < chrono::Utc::now().timestamp() {
+                    return ApiResponse::error(
+                        400,
+                        ErrorCode::QuizNotOpen,
+                        "Submission period exceeded",
+                    );
+                }
+                let user = &auth.user;
+                if user.wallet.wallet_address.clone().is_none() {
+                    return ApiResponse::error(
+                        400,
+                        ErrorCode::WalletNotLinked,
+                        "User does not have a linked wallet address",
+                    );
+                }
+                let mut quiz_obj = quiz.clone();
+                quiz_obj.status = Status::Ongoing;
+                if !quiz_obj.add_participant(
+                    user.user_uuid.clone(),
+                    user.wallet.wallet_address.clone().unwrap(),
+                ) {
+                    return ApiResponse::error(
+                        409,
+                        ErrorCode::Conflict,
+                        "Wallet address has already joined this quiz",
+                    );
+                }
 
-                                try_or_return!(db.update_quiz(quiz_obj).await);
-                                return ApiResponse::new(
-                                    200,
-                                    "Quiz started successfully".to_string(),
-                                );
-                            } else {
-                                return ApiResponse::new(
-                                    400,
-                                    "Quiz is currently pending".to_string(),
-                                );
-                            }
-                        }
-                        Status::Canceled => {
-                            return ApiResponse::new(
-                                400,
-                                "Quiz has already been Canceled".to_string(),
-                            );
-                        }
-                        Status::Ongoing => {
-                            let user = db
-                                .get_user_via_uuid(token_data.claims.user_uuid.clone())
-                                .await
-                                .unwrap();
-                            if user.wallet.wallet_address.clone().is_none() {
-                                return ApiResponse::new(
-                                    400,
-                                    "User does not have a linked wallet address".to_string(),
-                                );
-                            }
-                            let mut quiz_obj = quiz.clone();
+                try_or_return!(db.update_quiz(quiz_obj).await);
+                ApiResponse::message(200, "Quiz started successfully")
+            } else {
+                ApiResponse::error(400, ErrorCode::QuizNotOpen, "Quiz is currently pending")
+            }
+        }
+        Status::Canceled => ApiResponse::error(
+            400,
+            ErrorCode::QuizNotOpen,
+            "Quiz has already been Canceled",
+        ),
+        Status::Ongoing => {
+            if quiz.end_time < chrono::Utc::now().timestamp() {
+                return ApiResponse::error(
+                    400,
+                    ErrorCode::QuizNotOpen,
+                    "Submission period exceeded",
+                );
+            }
+            let user = &auth.user;
+            if user.wallet.wallet_address.clone().is_none() {
+                return ApiResponse::error(
+                    400,
+                    ErrorCode::WalletNotLinked,
+                    "User does not have a linked wallet address",
+                );
+            }
+            if quiz.is_participant(&user.user_uuid) {
+                return ApiResponse::message(200, "Quiz already started");
+            }
+            let mut quiz_obj = quiz.clone();
 
-                            quiz_obj.add_participant(
-                                token_data.claims.user_uuid.clone(),
-                                user.wallet.wallet_address.clone().unwrap(),
-                            );
-                            try_or_return!(db.update_quiz(quiz_obj).await);
-                            return ApiResponse::new(200, "Quiz started successfully".to_string());
-                        }
-                    }
-                } else {
-                    return ApiResponse::new(401, "Invalid or expired token".to_string());
-                }
+            if !quiz_obj.add_participant(
+                user.user_uuid.clone(),
+                user.wallet.wallet_address.clone().unwrap(),
+            ) {
+                return ApiResponse::error(
+                    409,
+                    ErrorCode::Conflict,
+                    "Wallet address has already joined this quiz",
+                );
             }
-            Err(_) => return ApiResponse::new(401, "Invalid token".to_string()),
+            try_or_return!(db.update_quiz(quiz_obj).await);
+            ApiResponse::message(200, "Quiz started successfully")
         }
-    } else {
-        return ApiResponse::new(401, "Missing token".to_string());
     }
 }
 
 #[post("quiz/submit")]
 pub async fn submit_quiz(
-    db: Data<Database>,
+    db: Data<dyn Storage>,
     request: Json<SubmitSubmitQuiz>,
-    req: HttpRequest,
+    auth: AuthenticatedUser,
 ) -> ApiResponse {
-    if let Some(cookie) = req.cookie("token") {
-        let jwt_secret = env::var("JWT_SECRET").expect("JWT SECRET IS NOT DEFINED");
-        match decode_token(&cookie.value().to_string(), &jwt_secret) {
-            Ok(token_data) => {
-                if is_valid_token(&token_data.claims) {
-                    let mut quiz =
-                        try_or_return!(db.get_quiz_via_uuid(request.quiz_uuid.clone()).await);
-                    match quiz.status {
-                        Status::Completed => {
-                            return ApiResponse::new(
-                                400,
-                                "Quiz has already been completed".to_string(),
-                            );
-                        }
-                        Status::Pending => {
-                            return ApiResponse::new(400, "Quiz is currently pending".to_string());
-                        }
-                        Status::Canceled => {
-                            return ApiResponse::new(
-                                400,
-                                "Quiz has already been Canceled".to_string(),
-                            );
-                        }
-                        Status::Ongoing => {
-                            // if let Some(&participant) == quiz.participants.iter().find(|p| p.user_uuid == token_data.claims.user_uuid) {}
-                            if let Some(participant) = quiz
-                                .participants
-                                .iter_mut()
-                                .find(|p| p.user_uuid == token_data.claims.user_uuid)
-                            {
-                                participant.submit_quiz(request.answers.clone());
-                                try_or_return!(db.update_quiz(quiz).await);
-                                return ApiResponse::new(
-                                    200,
-                                    "Quiz submitted successfully".to_string(),
-                                );
-                            } else {
-                                ApiResponse::new(404, format!("Not a registered quiz participant"))
-                            }
-                        }
-                    }
-                } else {
-                    return ApiResponse::new(401, "Invalid or expired token".to_string());
+    let mut quiz = try_or_return!(db.get_quiz_via_uuid(request.quiz_uuid.clone()).await);
+    match quiz.status {
+        Status::Completed => ApiResponse::error(
+            400,
+            ErrorCode::QuizNotOpen,
+            "Quiz has already been completed",
+        ),
+        Status::Pending => {
+            ApiResponse::error(400, ErrorCode::QuizNotOpen, "Quiz is currently pending")
+        }
+        Status::Canceled => ApiResponse::error(
+            400,
+            ErrorCode::QuizNotOpen,
+            "Quiz has already been Canceled",
+        ),
+        Status::Ongoing => {
+            if let Err(message) = quiz.check_answers(&request.answers) {
+                return ApiResponse::error(422, ErrorCode::ValidationFailed, message);
+            }
+            // Answers saved after settlement took its payload would never be graded.
+            match db.get_settlement_job_via_target(quiz.uuid.clone()).await {
+                Ok(job) if job.stage != SettlementStage::Queued => {
+                    return ApiResponse::error(
+                        400,
+                        ErrorCode::QuizNotOpen,
+                        "Quiz is already being graded",
+                    );
                 }
+                Err(e) if e.error_code != 404 => return ApiResponse::from(e),
+                _ => {}
+            }
+            let attempt_window = quiz.attempt_window();
+            if let Some(participant) = quiz
+                .participants
+                .iter_mut()
+                .find(|p| p.user_uuid == auth.user_uuid())
+            {
+                let now = chrono::Utc::now().timestamp();
+                if now > attempt_window.deadline(participant.start_time) {
+                    return ApiResponse::error(
+                        400,
+                        ErrorCode::AttemptExpired,
+                        "Attempt time limit exceeded",
+                    );
+                }
+                participant.submit_quiz(request.answers.clone(), now);
+                try_or_return!(db.update_quiz(quiz).await);
+                ApiResponse::message(200, "Quiz submitted successfully")
+            } else {
+                ApiResponse::error(
+                    404,
+                    ErrorCode::NotFound,
+                    "Not a registered quiz participant",
+                )
             }
-            Err(_) => return ApiResponse::new(401, "Invalid token".to_string()),
         }
-    } else {
-        return ApiResponse::new(401, "Missing token".to_string());
     }
 }
-
-// pub async fn create_quiz(
-//     db: Data<Database>,
-//     request: Json<SubmitCreateQuiz>,
-//     req: HttpRequest,
-// ) -> ApiResponse {
-//     if let Some(cookie) = req.cookie("token") {
-//         let jwt_secret = env::var("JWT_SECRET").expect("JWT SECRET IS NOT DEFINED");
-//         match decode_token(&cookie.value().to_string(), &jwt_secret) {
-//             Ok(token_data) => {
-//                 if is_valid_token(&token_data.claims) {
-
-//                 } else {
-//                     return ApiResponse::new(401, "Invalid or expired token".to_string());
-//                 }
-//             }
-//             Err(_) => return ApiResponse::new(401, "Invalid token".to_string()),
-//         }
-//     } else {
-//         return ApiResponse::new(401, "Missing token".to_string());
-//     }
-// }
@@ -1,23 +1,29 @@
-use crate::models::protocol_model::Protocol;
-use crate::models::quiz_model::{DifficultyLevel, Question, Quiz, QuizAccess, RewardType};
-use crate::services::db::Database;
-use crate::utils::jwt::is_valid_token;
-use crate::utils::{api_response::ApiResponse, jwt::decode_token};
+use crate::models::protocol_model::Permission;
+use crate::models::quiz_model::{
+    DifficultyLevel, Question, Quiz, QuizAccess, QuizView, RewardType, TokenAmount,
+};
+use crate::models::settlement_model::{SettlementJob, SettlementTarget};
+use crate::services::chain_registry::ChainRegistry;
+use crate::services::inspect::InspectClient;
+use crate::services::storage::Storage;
+use crate::utils::{
+    api_response::{ApiResponse, ErrorCode, FieldError},
+    auth::AuthenticatedUser,
+};
 use actix_web::{
-    cookie::{self, Cookie},
     get, post,
     web::Path,
     web::{Data, Json},
 };
-use actix_web::{HttpRequest, HttpResponse};
+use openquest_types::{validate_payload, GradingPayload, InputProblem};
 use serde::{Deserialize, Serialize};
-use std::env;
+use serde_json::json;
 
 macro_rules! try_or_return {
     ($result:expr) => {
         match $result {
             Ok(value) => value,
-            Err(e) => return ApiResponse::new(e.error_code, e.message),
+            Err(e) => return ApiResponse::from(e),
         }
     };
 }
@@ -30,100 +36,197 @@ pub struct SubmitCreateQuiz {
     protocol: String,
     questions: Vec<Question>,
     access: String,
-    total_reward: f64,
-    max_reward_per_user: f64,
+    /// Decimal amounts in whole tokens, such as `"12.5"`.
+    total_reward: String,
+    max_reward_per_user: String,
     duration_in_sec_timestamp: i64,
     start_time: i64,
     reward_type: RewardType,
+    #[serde(default)]
+    attempt_duration_in_sec: Option<i64>,
 }
 
 #[post("quiz/create")]
 pub async fn create_quiz(
-    db: Data<Database>,
+    db: Data<dyn Storage>,
+    registry: Data<ChainRegistry>,
     request: Json<SubmitCreateQuiz>,
-    req: HttpRequest,
+    auth: AuthenticatedUser,
 ) -> ApiResponse {
-    if let Some(cookie) = req.cookie("token") {
-        let jwt_secret = env::var("JWT_SECRET").expect("JWT SECRET IS NOT DEFINED");
-        match decode_token(&cookie.value().to_string(), &jwt_secret) {
-            Ok(token_data) => {
-                if is_valid_token(&token_data.claims) {
-                    let new_quiz = Quiz::new(
-                        request.name.clone(),
-                        DifficultyLevel::from_str(&request.difficulty.clone()).unwrap(),
-                        request.description.clone(),
-                        request.protocol.clone(),
-                        token_data.claims.user_uuid.clone(),
-                        request.questions.clone(),
-                        QuizAccess::from_str(&request.access.clone()).unwrap(),
-                        request.total_reward.clone(),
-                        request.max_reward_per_user.clone(),
-                        request.duration_in_sec_timestamp.clone(),
-                        request.start_time.clone(),
-                        request.reward_type.clone(),
-                    );
+    let mut protocol = try_or_return!(db.get_protocol_via_name(request.protocol.clone()).await);
+    try_or_return!(auth.require_permission(&protocol, Permission::CreateQuiz));
+
+    let mut invalid_fields = Vec::new();
+    let difficulty = DifficultyLevel::parse(&request.difficulty);
+    if difficulty.is_none() {
+        invalid_fields.push(FieldError::new(
+            "difficulty",
+            "Expected one of easy, medium or hard",
+        ));
+    }
+    let access = QuizAccess::parse(&request.access);
+    if access.is_none() {
+        invalid_fields.push(FieldError::new(
+            "access",
+            "Expected one of Public or Private",
+        ));
+    }
+    let token_decimals = match registry.resolve(protocol.chain_id) {
+        Ok(chain) => chain.config.reward_token_decimals(),
+        Err(message) => return ApiResponse::error(500, ErrorCode::InternalError, message),
+    };
+    let mut parse_amount = |field: &str, value: &str| {
+        TokenAmount::parse_units(value, token_decimals)
+            .map_err(|e| invalid_fields.push(FieldError::new(field, &e.to_string())))
+            .ok()
+    };
+    let total_reward = parse_amount("total_reward", &request.total_reward);
+    let max_reward_per_user = parse_amount("max_reward_per_user", &request.max_reward_per_user);
+    let (Some(difficulty), Some(access), Some(total_reward), Some(max_reward_per_user)) =
+        (difficulty, access, total_reward, max_reward_per_user)
+    else {
+        return ApiResponse::validation_error("Invalid quiz details", invalid_fields);
+    };
 
-                    match db.add_quiz(new_quiz.clone()).await {
-                        Ok(_) => {
-                            let mut protocol = try_or_return!(
-                                db.get_protocol_via_name(request.protocol.clone()).await
-                            );
-                            protocol.update_total_expense(request.total_reward.clone());
-                            protocol.add_quiz(new_quiz.uuid.clone());
-                            match db.update_protocol(protocol.clone()).await {
-                                Ok(_result) => {
-                                    return ApiResponse::new(201, format!("{:?}", new_quiz.uuid));
-                                }
-                                Err(e) => ApiResponse::new(e.error_code, e.message),
-                            };
-                            ApiResponse::new(201, format!("{:?}", new_quiz.uuid))
-                        }
-                        Err(e) => ApiResponse::new(e.error_code, e.message),
-                    }
-                } else {
-                    return ApiResponse::new(401, "Invalid or expired token".to_string());
-                }
+    let new_quiz = Quiz::new(
+        request.name.clone(),
+        difficulty,
+        request.description.clone(),
+        request.protocol.clone(),
+        auth.user_uuid().to_string(),
+        request.questions.clone(),
+        access,
+        total_reward,
+        max_reward_per_user,
+        token_decimals,
+        request.duration_in_sec_timestamp,
+        request.start_time,
+        request.reward_type.clone(),
+        request.attempt_duration_in_sec,
+    );
+    if let Err(message) = new_quiz.validate() {
+        return ApiResponse::error(422, ErrorCode::ValidationFailed, message);
+    }
+
+    match db.add_quiz(new_quiz.clone()).await {
+        Ok(_) => {
+            try_or_return!(
+                db.create_settlement_job(SettlementJob::new(
+                    SettlementTarget::Quiz,
+                    new_quiz.uuid.clone(),
+                    new_quiz.settles_at(),
+                ))
+                .await
+            );
+            protocol.update_total_expense(new_quiz.total_reward);
+            protocol.add_quiz(new_quiz.uuid.clone());
+            match db.update_protocol(protocol.clone()).await {
+                Ok(_result) => ApiResponse::new(201, json!({ "quiz_uuid": new_quiz.uuid })),
+                Err(e) => ApiResponse::from(e),
             }
-            Err(_) => return ApiResponse::new(401, "Invalid token".to_string()),
         }
-    } else {
-        return ApiResponse::new(401, "Missing token".to_string());
+        Err(e) => ApiResponse::from(e),
     }
 }
 
-// pub async fn create_quiz(
-//     db: Data<Database>,
-//     request: Json<SubmitCreateQuiz>,
-//     req: HttpRequest,
-// ) -> ApiResponse {
-//     if let Some(cookie) = req.cookie("token") {
-//         let jwt_secret = env::var("JWT_SECRET").expect("JWT SECRET IS NOT DEFINED");
-//         match decode_token(&cookie.value().to_string(), &jwt_secret) {
-//             Ok(token_data) => {
-//                 if is_valid_token(&token_data.claims) {
-
-//                 } else {
-//                     return ApiResponse::new(401, "Invalid or expired token".to_string());
-//                 }
-//             }
-//             Err(_) => return ApiResponse::new(401, "Invalid token".to_string()),
-//         }
-//     } else {
-//         return ApiResponse::new(401, "Missing token".to_string());
-//     }
-// }
-
 #[get("/quizes")]
-pub async fn get_all_quiz(db: Data<Database>) -> ApiResponse {
-    match db.get_all_quizes().await {
-        Ok(quizzes) => ApiResponse::new(200, format!("{:?}", quizzes)),
-        Err(e) => ApiResponse::new(e.error_code, e.message),
+pub async fn get_all_quiz(db: Data<dyn Storage>, auth: Option<AuthenticatedUser>) -> ApiResponse {
+    let viewer = auth.map(|auth| auth.user_uuid().to_string());
+    match db.get_all_quiz_views(viewer).await {
+        Ok(quizzes) => ApiResponse::new(200, quizzes),
+        Err(e) => ApiResponse::from(e),
     }
 }
 
 #[get("/quiz/by-id/{quiz_id}")]
-pub async fn get_quiz_by_id(db: Data<Database>, path: Path<String>) -> ApiResponse {
+pub async fn get_quiz_by_id(
+    db: Data<dyn Storage>,
+    path: Path<String>,
+    auth: Option<AuthenticatedUser>,
+) -> ApiResponse {
+    let quiz_id = path.into_inner();
+    let viewer = auth.map(|auth| auth.user_uuid().to_string());
+    let quiz: QuizView = try_or_return!(db.get_quiz_view(quiz_id.clone(), viewer).await);
+    ApiResponse::new(200, quiz)
+}
+
+#[get("/quiz/by-id/{quiz_id}/transactions")]
+pub async fn get_quiz_transactions(db: Data<dyn Storage>, path: Path<String>) -> ApiResponse {
     let quiz_id = path.into_inner();
-    let quiz: Quiz = try_or_return!(db.get_quiz_via_uuid(quiz_id.clone()).await);
-    ApiResponse::new(200, format!("{:?}", quiz))
+    try_or_return!(db.get_quiz_via_uuid(quiz_id.clone()).await);
+    let transactions = try_or_return!(db.get_transactions_via_target(quiz_id).await);
+    ApiResponse::new(200, transactions)
+}
+
+/// The Merkle proof of the result paid to `address` in a settled quiz, which anyone can
+/// check against the `results_root` of the coprocessor's notice.
+#[get("/quiz/by-id/{quiz_id}/proof/{address}")]
+pub async fn get_result_proof(db: Data<dyn Storage>, path: Path<(String, String)>) -> ApiResponse {
+    let (quiz_id, address) = path.into_inner();
+    let quiz = try_or_return!(db.get_quiz_via_uuid(quiz_id).await);
+    let Some(tree) = quiz.results_tree.as_ref() else {
+        return ApiResponse::error(
+            404,
+            ErrorCode::NotFound,
+            "The quiz has no committed results",
+        );
+    };
+    match tree.proof(&quiz.uuid, &address) {
+        Ok(Some(proof)) => ApiResponse::new(200, proof),
+        Ok(None) => ApiResponse::error(
+            404,
+            ErrorCode::NotFound,
+            format!("No result for {} in this quiz", address),
+        ),
+        Err(e) => ApiResponse::error(500, ErrorCode::InternalError, e),
+    }
+}
+
+/// What the coprocessor would publish if the quiz were settled now: every participant's
+/// score, reward and leaderboard points, graded by the same program through an inspect
+/// request. Nothing is sent on-chain.
+#[get("/quiz/by-id/{quiz_id}/grading-preview")]
+pub async fn preview_quiz_grading(
+    db: Data<dyn Storage>,
+    inspect: Data<InspectClient>,
+    path: Path<String>,
+    auth: AuthenticatedUser,
+) -> ApiResponse {
+    let quiz = try_or_return!(db.get_quiz_via_uuid(path.into_inner()).await);
+    let protocol = try_or_return!(db.get_protocol_via_name(quiz.protocol.clone()).await);
+    try_or_return!(auth.require_permission(&protocol, Permission::ViewAnalytics));
+    if !inspect.is_configured() {
+        return ApiResponse::error(
+            503,
+            ErrorCode::CoprocessorFailure,
+            "Grading previews are not configured",
+        );
+    }
+
+    let payload = GradingPayload::Quiz(quiz.into_offchain_quiz_data());
+    if let Err(report) = validate_payload(&payload) {
+        let details = report
+            .problems
+            .iter()
+            .map(|problem| FieldError::new(problem_field(problem), &problem.to_string()))
+            .collect();
+        return ApiResponse::validation_error("The coprocessor would reject this quiz", details);
+    }
+    match inspect.preview_grading(&payload).await {
+        Ok(preview) => ApiResponse::new(200, preview),
+        Err(e) => ApiResponse::error(502, ErrorCode::CoprocessorFailure, e),
+    }
+}
+
+/// The part of a quiz an input problem is about.
+fn problem_field(problem: &InputProblem) -> &'static str {
+    match problem {
+        InputProblem::NoQuestions
+        | InputProblem::UnknownQuestion { .. }
+        | InputProblem::DuplicateAnswer { .. } => "questions",
+        InputProblem::NoParticipants
+        | InputProblem::DuplicateParticipant { .. }
+        | InputProblem::InvalidWallet { .. } => "participants",
+        _ => "payload",
+    }
 }
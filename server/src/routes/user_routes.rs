@@ -1,10 +1,16 @@
-use crate::services::db::Database;
-use crate::utils::api_response::ApiResponse;
+use crate::models::wallet_challenge_model::{ChallengePurpose, WalletChallenge};
+use crate::services::{
+    chain_registry::ChainRegistry,
+    siwe::{issue_challenge, parse_wallet_address, verify_challenge_signature, SiweConfig},
+    storage::Storage,
+};
+use crate::utils::{
+    api_response::{ApiResponse, ErrorCode, FieldError},
+    auth::AuthenticatedUser,
+};
 use crate::{models::user_model::User, utils::jwt::generate_jwt};
 use actix_web::cookie::time::Duration;
 use actix_web::cookie::SameSite;
-use actix_web::http::StatusCode;
-use actix_web::HttpResponse;
 use actix_web::{
     cookie::Cookie,
     get, post,
@@ -12,6 +18,7 @@ use actix_web::{
     web::{Data, Json},
 };
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::env;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -22,9 +29,19 @@ pub struct SubmitRegisterUser {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct SubmitLinkWalletAddress {
-    pub user_uuid: String,
+pub struct SubmitWalletChallenge {
     pub wallet_address: String,
+    /// Chain id written into the message; the registry's default when omitted.
+    #[serde(default)]
+    pub chain_id: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubmitSignedChallenge {
+    /// Nonce returned by `/user/wallet/challenge` or `/user/siwe/nonce`.
+    pub nonce: String,
+    /// `personal_sign` signature over the challenge message, as 0x-prefixed hex.
+    pub signature: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -38,29 +55,23 @@ pub struct SubmitGetProfileViaEmail {
     pub email: String,
 }
 
-macro_rules! try_or_return_string {
-    ($result:expr) => {
-        match $result {
-            Ok(value) => value,
-            Err(e) => return Err(DatabaseResponse::new(500, e.to_string())),
-        }
-    };
-}
-
 macro_rules! try_or_return {
     ($result:expr) => {
         match $result {
             Ok(value) => value,
-            Err(e) => return ApiResponse::new(e.error_code, e.message),
+            Err(e) => return ApiResponse::from(e),
         }
     };
 }
 
 #[post("/user/register")]
-pub async fn register_user(db: Data<Database>, request: Json<SubmitRegisterUser>) -> ApiResponse {
+pub async fn register_user(
+    db: Data<dyn Storage>,
+    request: Json<SubmitRegisterUser>,
+) -> ApiResponse {
     let existing_user = db.get_user_via_email(request.email.clone()).await;
     match existing_user {
-        Ok(_) => ApiResponse::new(409, "Email already registered".to_string()),
+        Ok(_) => ApiResponse::error(409, ErrorCode::Conflict, "Email already registered"),
         Err(e) => match e.error_code {
             404 => {
                 let user = User::new(
@@ -70,89 +81,270 @@ pub async fn register_user(db: Data<Database>, request: Json<SubmitRegisterUser>
                 );
                 match user {
                     Ok(user_struct) => match db.create_user(user_struct.clone()).await {
-                        Ok(_result) => {
-                            ApiResponse::new(201, format!("{:?}", user_struct.user_uuid))
-                        }
-                        Err(err) => ApiResponse::new(err.error_code, err.message),
+                        Ok(_result) => ApiResponse::new(201, user_struct.display()),
+                        Err(err) => ApiResponse::from(err),
                     },
-                    Err(err) => ApiResponse::new(400, err),
+                    Err(err) => ApiResponse::error(400, ErrorCode::BadRequest, err),
                 }
             }
-            _ => ApiResponse::new(e.error_code, e.message),
+            _ => ApiResponse::from(e),
         },
     }
 }
 
 #[get("/users")]
-pub async fn get_all_users(db: Data<Database>) -> ApiResponse {
+pub async fn get_all_users(db: Data<dyn Storage>) -> ApiResponse {
     match db.get_all_users().await {
-        Ok(users) => ApiResponse::new(200, format!("{:?}", users)),
-        Err(e) => ApiResponse::new(e.error_code, e.message),
+        Ok(users) => ApiResponse::new(200, users),
+        Err(e) => ApiResponse::from(e),
     }
 }
+/// Starts a `purpose` challenge for `wallet_address` and returns what has to be signed.
+async fn issue_wallet_challenge(
+    db: &Data<dyn Storage>,
+    registry: &ChainRegistry,
+    purpose: ChallengePurpose,
+    user_uuid: Option<String>,
+    request: &SubmitWalletChallenge,
+) -> ApiResponse {
+    let address = match parse_wallet_address(&request.wallet_address) {
+        Ok(address) => address,
+        Err(message) => {
+            return ApiResponse::validation_error(
+                "Invalid wallet address",
+                vec![FieldError::new("wallet_address", &message)],
+            )
+        }
+    };
+    let chain_id = request
+        .chain_id
+        .or_else(|| registry.default_chain_id())
+        .unwrap_or(1);
+    let challenge = issue_challenge(
+        &SiweConfig::from_env(),
+        purpose,
+        user_uuid,
+        address,
+        chain_id,
+        chrono::Utc::now().timestamp(),
+    );
+    try_or_return!(db.create_wallet_challenge(challenge.clone()).await);
+    ApiResponse::new(201, challenge.view())
+}
+
+/// Redeems a challenge, which only succeeds once per nonce, and checks its signature.
+async fn redeem_wallet_challenge(
+    db: &Data<dyn Storage>,
+    purpose: ChallengePurpose,
+    user_uuid: Option<String>,
+    request: &SubmitSignedChallenge,
+) -> Result<WalletChallenge, ApiResponse> {
+    let challenge = match db
+        .consume_wallet_challenge(request.nonce.clone(), purpose, user_uuid)
+        .await
+    {
+        Ok(challenge) => challenge,
+        Err(e) if e.error_code == 404 => {
+            return Err(ApiResponse::error(
+                400,
+                ErrorCode::BadRequest,
+                "Unknown or already used wallet challenge",
+            ))
+        }
+        Err(e) => return Err(ApiResponse::from(e)),
+    };
+    if challenge.is_expired(chrono::Utc::now().timestamp()) {
+        return Err(ApiResponse::error(
+            400,
+            ErrorCode::BadRequest,
+            "Wallet challenge has expired",
+        ));
+    }
+    if let Err(message) = verify_challenge_signature(&challenge, &request.signature) {
+        return Err(ApiResponse::validation_error(
+            "Invalid signature",
+            vec![FieldError::new("signature", &message)],
+        ));
+    }
+    Ok(challenge)
+}
+
+/// Clears an address `owner` linked without a signature, so the account that just
+/// signed for it can take it over.
+async fn release_unverified_link(
+    db: &Data<dyn Storage>,
+    mut owner: User,
+) -> Result<(), ApiResponse> {
+    owner.wallet.unlink();
+    db.update_user_wallet(owner.user_uuid.clone(), owner.wallet)
+        .await
+        .map_err(ApiResponse::from)
+}
+
+/// Issues the session JWT, both as the `token` cookie and in the body for clients that
+/// send it as a Bearer header.
+fn session_response(status_code: u16, user: &User) -> ApiResponse {
+    let jwt_secret = env::var("JWT_SECRET").expect("JWT SECRET IS NOT DEFINED");
+    let token = generate_jwt(user.user_uuid.clone(), jwt_secret).unwrap();
+    let cookie = Cookie::build("token", token.clone())
+        .path("/")
+        .max_age(Duration::seconds(3600))
+        .same_site(SameSite::None)
+        .http_only(true)
+        .finish();
+    ApiResponse::new(
+        status_code,
+        json!({ "token": token, "user": user.display() }),
+    )
+    .with_cookie(cookie)
+}
+
+#[post("/user/wallet/challenge")]
+pub async fn request_wallet_challenge(
+    db: Data<dyn Storage>,
+    registry: Data<ChainRegistry>,
+    request: Json<SubmitWalletChallenge>,
+    auth: AuthenticatedUser,
+) -> ApiResponse {
+    issue_wallet_challenge(
+        &db,
+        &registry,
+        ChallengePurpose::LinkWallet,
+        Some(auth.user_uuid().to_string()),
+        &request,
+    )
+    .await
+}
+
+/// Links a wallet to the signed-in account. Linking also enables signing in with it
+/// through `/user/siwe/login`. Only a verified link on another account blocks it; an
+/// unverified one is taken over.
 #[post("/user/wallet")]
 pub async fn link_wallet_address(
-    db: Data<Database>,
-    request: Json<SubmitLinkWalletAddress>,
+    db: Data<dyn Storage>,
+    request: Json<SubmitSignedChallenge>,
+    auth: AuthenticatedUser,
 ) -> ApiResponse {
-    let existing_user = db.get_user_via_uuid(request.user_uuid.clone()).await;
-    match existing_user {
-        Ok(mut user) => {
-            user.wallet.update_wallet(request.wallet_address.clone());
-            match db
-                .update_user_wallet(request.user_uuid.clone(), user.wallet.clone())
-                .await
-            {
-                Ok(_result) => {
-                    return ApiResponse::new(200, format!("{:?}", user.clone().display()))
-                }
-                Err(err) => return ApiResponse::new(err.error_code, err.message),
+    let challenge = match redeem_wallet_challenge(
+        &db,
+        ChallengePurpose::LinkWallet,
+        Some(auth.user_uuid().to_string()),
+        &request,
+    )
+    .await
+    {
+        Ok(challenge) => challenge,
+        Err(response) => return response,
+    };
+    match db.get_user_via_address(challenge.address.clone()).await {
+        Ok(owner) if owner.user_uuid != auth.user_uuid() && owner.wallet.verified => {
+            return ApiResponse::error(
+                409,
+                ErrorCode::Conflict,
+                "Wallet is already linked to another account",
+            )
+        }
+        Ok(owner) if owner.user_uuid != auth.user_uuid() => {
+            if let Err(response) = release_unverified_link(&db, owner).await {
+                return response;
             }
         }
-        Err(e) => return ApiResponse::new(e.error_code, e.message),
+        Ok(_) => {}
+        Err(e) if e.error_code == 404 => {}
+        Err(e) => return ApiResponse::from(e),
     }
+
+    let mut user = auth.user;
+    user.wallet.link_verified(challenge.address);
+    try_or_return!(
+        db.update_user_wallet(user.user_uuid.clone(), user.wallet.clone())
+            .await
+    );
+    ApiResponse::new(200, user.display())
+}
+
+#[post("/user/siwe/nonce")]
+pub async fn request_siwe_nonce(
+    db: Data<dyn Storage>,
+    registry: Data<ChainRegistry>,
+    request: Json<SubmitWalletChallenge>,
+) -> ApiResponse {
+    issue_wallet_challenge(&db, &registry, ChallengePurpose::SignIn, None, &request).await
+}
+
+/// Signs in with a wallet. The account the wallet is linked to is signed in, and a
+/// wallet-only account is created (with status 201) the first time an unknown wallet
+/// signs in, taking the wallet over from any account that linked it without a signature.
+#[post("/user/siwe/login")]
+pub async fn siwe_login(
+    db: Data<dyn Storage>,
+    request: Json<SubmitSignedChallenge>,
+) -> ApiResponse {
+    let challenge =
+        match redeem_wallet_challenge(&db, ChallengePurpose::SignIn, None, &request).await {
+            Ok(challenge) => challenge,
+            Err(response) => return response,
+        };
+    match db.get_user_via_address(challenge.address.clone()).await {
+        Ok(user) if user.wallet.verified => return session_response(200, &user),
+        // Addresses linked before links needed a signature prove nothing about the
+        // account, so the signer gets the wallet and the old link is dropped.
+        Ok(owner) => {
+            if let Err(response) = release_unverified_link(&db, owner).await {
+                return response;
+            }
+        }
+        Err(e) if e.error_code == 404 => {}
+        Err(e) => return ApiResponse::from(e),
+    }
+    let user = User::from_wallet(challenge.address);
+    try_or_return!(db.create_user(user.clone()).await);
+    session_response(201, &user)
 }
 
 #[post("/user/login")]
-pub async fn login_user(db: Data<Database>, request: Json<SubmitLogin>) -> HttpResponse {
-    let jwt_secret = env::var("JWT_SECRET").expect("JWT SECRET IS NOT DEFINED");
+pub async fn login_user(db: Data<dyn Storage>, request: Json<SubmitLogin>) -> ApiResponse {
     let existing_user = db.get_user_via_email(request.email.clone()).await;
     match existing_user {
-        Ok(user) => {
+        Ok(mut user) => {
             println!("User Found!!!!");
             if user.compare_password(request.password.clone()) {
-                let cookie = Cookie::build(
-                    "token",
-                    generate_jwt(user.user_uuid.clone(), jwt_secret).unwrap(),
-                )
-                .path("/")
-                .max_age(Duration::seconds(3600))
-                .same_site(SameSite::None)
-                .http_only(true)
-                .finish();
-                return HttpResponse::Ok().cookie(cookie).finish();
+                // Transparently move accounts off the legacy SHA-256 hashes
+                match user.upgrade_password_hash(&request.password) {
+                    Ok(true) => {
+                        if let Err(e) = db.update_user(user.clone()).await {
+                            println!("Error upgrading password hash: {}", e.message);
+                        }
+                    }
+                    Ok(false) => {}
+                    Err(e) => println!("Error upgrading password hash: {}", e),
+                }
+                session_response(200, &user)
             } else {
-                return HttpResponse::Unauthorized().body("Invalid username or password");
+                ApiResponse::error(401, ErrorCode::Unauthorized, "Invalid username or password")
             }
         }
-        Err(e) => return HttpResponse::InternalServerError().body(e.message),
+        Err(e) => match e.error_code {
+            404 => ApiResponse::error(401, ErrorCode::Unauthorized, "Invalid username or password"),
+            _ => ApiResponse::from(e),
+        },
     }
 }
 
 #[get("/user/by-email/{email}")]
 pub async fn get_user_via_email(
-    db: Data<Database>,
+    db: Data<dyn Storage>,
     request: Path<SubmitGetProfileViaEmail>,
 ) -> ApiResponse {
     let email_address = request.into_inner().email;
 
     let user: User = try_or_return!(db.get_user_via_email(email_address).await);
-    return ApiResponse::new(200, format!("{:?}", user));
+    ApiResponse::new(200, user.profile())
 }
 
 #[get("/user/by-id/{user_id}")]
-pub async fn get_user_by_id(db: Data<Database>, path: Path<String>) -> ApiResponse {
+pub async fn get_user_by_id(db: Data<dyn Storage>, path: Path<String>) -> ApiResponse {
     let user_id = path.into_inner();
     let user: User = try_or_return!(db.get_user_via_uuid(user_id.clone()).await);
-    ApiResponse::new(200, format!("{:?}", user))
+    ApiResponse::new(200, user.profile())
 }
@@ -0,0 +1,262 @@
+use super::chain::{ChainClient, DeployedProtocol, GradingSubmission};
+use super::signer::TransactionSigner;
+use super::storage::Storage;
+use super::tx_manager::{TxManager, TxManagerConfig};
+use crate::models::{chain_model::ChainConfig, transaction_model::TransactionPurpose};
+use alloy::{
+    primitives::{keccak256, Address, Bytes},
+    providers::{DynProvider, Provider, ProviderBuilder},
+    rpc::types::{Filter, Log},
+    sol,
+    sol_types::SolCall,
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::Arc;
+
+sol!(
+    #[derive(Debug, Deserialize, Serialize)]
+    #[allow(missing_docs, clippy::too_many_arguments)]
+    #[sol(rpc)]
+    ProtocolABI,
+    "abi/ProtocolABI.json"
+);
+
+sol!(
+    #[derive(Debug, Deserialize, Serialize)]
+    #[allow(missing_docs, clippy::too_many_arguments)]
+    #[sol(rpc)]
+    FACTORY,
+    "abi/ProtocolFactoryABI.json"
+);
+
+/// `ChainClient` backed by a JSON-RPC node. Writes go through a `TxManager` signing with
+/// the configured `TransactionSigner`.
+pub struct AlloyChainClient {
+    provider: DynProvider,
+    factory_address: Option<Address>,
+    tx_manager: Arc<TxManager>,
+}
+
+impl AlloyChainClient {
+    /// Connects to `chain`, signing with `signer`.
+    pub fn new(
+        chain: &ChainConfig,
+        signer: Arc<dyn TransactionSigner>,
+        db: Arc<dyn Storage>,
+        tx_config: TxManagerConfig,
+    ) -> Result<Self, String> {
+        let provider = ProviderBuilder::new()
+            .on_http(
+                chain
+                    .rpc_url
+                    .parse()
+                    .map_err(|_| "RPC is not a valid URL")?,
+            )
+            .erased();
+        let factory_address = match chain.factory_address.as_deref() {
+            Some(address) => Some(Address::from_str(address).map_err(|e| e.to_string())?),
+            None => None,
+        };
+        let tx_manager = Arc::new(TxManager::new(
+            provider.clone(),
+            signer,
+            chain.chain_id,
+            db,
+            tx_config,
+        ));
+        Ok(AlloyChainClient {
+            provider,
+            factory_address,
+            tx_manager,
+        })
+    }
+
+    pub fn tx_manager(&self) -> Arc<TxManager> {
+        self.tx_manager.clone()
+    }
+
+    fn factory(&self) -> Result<Address, String> {
+        self.factory_address
+            .ok_or_else(|| "No factory address is configured for this chain".to_string())
+    }
+}
+
+#[async_trait]
+impl ChainClient for AlloyChainClient {
+    fn signer_address(&self) -> Option<String> {
+        Some(self.tx_manager.signer_address().to_string())
+    }
+
+    async fn create_protocol(&self, name: &str, protocol_id: &str) -> Result<String, String> {
+        let factory_address = self.factory()?;
+        let input = FACTORY::createProtocolCall {
+            name: name.into(),
+            protocol_id: protocol_id.into(),
+        }
+        .abi_encode();
+
+        let tx = self
+            .tx_manager
+            .send(
+                TransactionPurpose::CreateProtocol,
+                protocol_id.to_string(),
+                name.to_string(),
+                factory_address,
+                input.into(),
+            )
+            .await?;
+        println!("Protocol creation sent, TX-Hash is: {}", tx.tx_hash);
+        Ok(tx.tx_hash)
+    }
+
+    async fn deployed_protocol(
+        &self,
+        protocol_id: &str,
+    ) -> Result<Option<DeployedProtocol>, String> {
+        let factory = FACTORY::new(self.factory()?, self.provider.clone());
+        let details = factory
+            .getProtocolDetailsViaId(protocol_id.to_string())
+            .call()
+            .await
+            .map_err(|e| e.to_string())?;
+        let details = details._0;
+        // Unknown ids read back as an empty record.
+        if details.contract_add == Address::ZERO {
+            return Ok(None);
+        }
+        Ok(Some(DeployedProtocol {
+            name: details.name,
+            protocol_id: details.protocol_id,
+            contract_address: details.contract_add.to_string(),
+            admin: details.admin.to_string(),
+        }))
+    }
+
+    async fn protocol_address_via_name(&self, name: &str) -> Result<Option<String>, String> {
+        let factory = FACTORY::new(self.factory()?, self.provider.clone());
+        let address = factory
+            .getProtocolAddress(name.to_string())
+            .call()
+            .await
+            .map_err(|e| e.to_string())?;
+        let address = address._0;
+        if address == Address::ZERO {
+            return Ok(None);
+        }
+        Ok(Some(address.to_string()))
+    }
+
+    async fn submit_grading(&self, submission: GradingSubmission) -> Result<String, String> {
+        let protocol_addr =
+            Address::from_str(&submission.contract_address).map_err(|e| e.to_string())?;
+        let bytes: Bytes = submission
+            .payload
+            .parse::<Bytes>()
+            .map_err(|e| e.to_string())?;
+
+        let input = ProtocolABI::gradeQuizCall {
+            quiz_id: submission.uuid.as_str().into(),
+            name: submission.name.as_str().into(),
+            total_reward: submission.total_reward.base_units(),
+            max_user_reward: submission.max_reward_per_user.base_units(),
+            created_by: submission
+                .created_by
+                .parse()
+                .map_err(|_| "Invalid creator wallet")?,
+            protocol_name: submission.protocol.as_str().into(),
+            access: submission.access.as_str().into(),
+            compressed_data: bytes,
+            endTime: submission
+                .end_time
+                .try_into()
+                .map_err(|_| "Invalid end time")?,
+        }
+        .abi_encode();
+
+        let tx = self
+            .tx_manager
+            .send(
+                TransactionPurpose::GradeQuiz,
+                submission.uuid.clone(),
+                submission.protocol.clone(),
+                protocol_addr,
+                input.into(),
+            )
+            .await?;
+        println!("Transaction submitted, TX-Hash is: {}", tx.tx_hash);
+        Ok(tx.tx_hash)
+    }
+
+    async fn quiz_response(
+        &self,
+        contract_address: &str,
+        payload: &str,
+    ) -> Result<Option<Vec<u8>>, String> {
+        let protocol_addr = Address::from_str(contract_address).map_err(|e| e.to_string())?;
+        let protocol_instance = ProtocolABI::new(protocol_addr, self.provider.clone());
+
+        // Notices are keyed by the keccak256 hash of the exact payload bytes.
+        let bytes: Bytes = payload.parse::<Bytes>().map_err(|e| e.to_string())?;
+        let response_bytes = protocol_instance
+            .checkQuizResponse(keccak256(bytes))
+            .call()
+            .await
+            .map_err(|e| e.to_string())?;
+        let response = response_bytes._0;
+
+        if response.len() < 4 {
+            return Ok(None);
+        }
+        Ok(Some(response.to_vec()))
+    }
+
+    async fn settled_results_root(
+        &self,
+        contract_address: &str,
+        uuid: &str,
+    ) -> Result<Option<String>, String> {
+        let protocol_addr = Address::from_str(contract_address).map_err(|e| e.to_string())?;
+        let protocol_instance = ProtocolABI::new(protocol_addr, self.provider.clone());
+
+        let settled = protocol_instance
+            .quizSettled(uuid.to_string())
+            .call()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !settled._0 {
+            return Ok(None);
+        }
+        let root = protocol_instance
+            .quizResultsRoot(uuid.to_string())
+            .call()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(Some(root._0.to_string()))
+    }
+
+    async fn block_number(&self) -> Result<u64, String> {
+        self.provider
+            .get_block_number()
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    async fn logs(
+        &self,
+        contract_address: &str,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<Vec<Log>, String> {
+        let address = Address::from_str(contract_address).map_err(|e| e.to_string())?;
+        let filter = Filter::new()
+            .address(address)
+            .from_block(from_block)
+            .to_block(to_block);
+        self.provider
+            .get_logs(&filter)
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
@@ -0,0 +1,51 @@
+use openquest_types::BlobRef;
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+/// Content-addressed files under `BLOB_STORE_DIR`, each named by the keccak256 hash of
+/// its bytes. Holds grading payloads too large to send on-chain.
+#[derive(Debug, Clone)]
+pub struct BlobStore {
+    dir: PathBuf,
+}
+
+impl BlobStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        BlobStore { dir: dir.into() }
+    }
+
+    pub fn from_env() -> Self {
+        BlobStore::new(env::var("BLOB_STORE_DIR").unwrap_or_else(|_| String::from("blobs")))
+    }
+
+    /// Saves `blob` and returns its reference. Saving the same bytes twice is a no-op.
+    pub fn put(&self, blob: &[u8]) -> Result<BlobRef, String> {
+        let reference = BlobRef::of(blob);
+        let path = self.dir.join(reference.hash_hex());
+        if path.exists() {
+            return Ok(reference);
+        }
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Creating {}: {}", self.dir.display(), e))?;
+        // Written aside and renamed so a reader never sees a partial blob.
+        let partial = self.dir.join(format!("{}.partial", reference.hash_hex()));
+        fs::write(&partial, blob).map_err(|e| format!("Writing {}: {}", partial.display(), e))?;
+        fs::rename(&partial, &path).map_err(|e| format!("Writing {}: {}", path.display(), e))?;
+        Ok(reference)
+    }
+
+    /// The blob whose hash is `hash`, as 64 hex digits with or without `0x`.
+    pub fn get(&self, hash: &str) -> Result<Option<Vec<u8>>, String> {
+        let hash = hash.trim_start_matches("0x").to_lowercase();
+        if hash.len() != 64 || !hash.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return Err("Blob hash must be 32 bytes of hex".to_string());
+        }
+        match fs::read(self.dir.join(&hash)) {
+            Ok(blob) => Ok(Some(blob)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
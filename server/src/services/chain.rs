@@ -0,0 +1,180 @@
+use alloy::{rpc::types::Log, sol};
+use async_trait::async_trait;
+use openquest_types::TokenAmount;
+
+sol! {
+    /// Events the server follows on the factory and protocol contracts.
+    interface OpenQuestEvents {
+        event ResultReceived(bytes data);
+        event QuizCreated(address indexed by, string indexed quiz_id);
+        event RewardsDistributed(address[] winners, uint256 rewardPerWinner, uint256 time);
+        event TriviaCanceled(address indexed admin, string reason, uint256 time);
+        event StaffAdded(address indexed admin, address staff, uint256 time);
+        event StaffRemoved(address indexed admin, address staff, uint256 time);
+        event PtotocolCreated(
+            address indexed admin,
+            uint256 time,
+            string indexed protocol_id,
+            address indexed protocolContract
+        );
+    }
+}
+
+/// Everything `gradeQuiz` is called with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GradingSubmission {
+    pub contract_address: String,
+    pub uuid: String,
+    pub name: String,
+    /// Amounts are in base units of the reward token.
+    pub total_reward: TokenAmount,
+    pub max_reward_per_user: TokenAmount,
+    /// Wallet of the user who created the quiz or hackathon.
+    pub created_by: String,
+    pub protocol: String,
+    pub access: String,
+    /// `0x` hex payload handed to the coprocessor.
+    pub payload: String,
+    pub end_time: i64,
+}
+
+/// What the factory has on record for one protocol id.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeployedProtocol {
+    pub name: String,
+    pub protocol_id: String,
+    pub contract_address: String,
+    /// Account that sent `createProtocol`, normally the server's signer.
+    pub admin: String,
+}
+
+/// The server's view of the chain: the factory, the protocol contracts and their logs.
+///
+/// `AlloyChainClient` talks to a node over JSON-RPC; `FakeChainClient` is scripted
+/// from tests.
+#[async_trait]
+pub trait ChainClient: Send + Sync {
+    /// Sends `createProtocol` to the factory and returns the transaction hash without
+    /// waiting for it. The contract address arrives later with the `PtotocolCreated` log.
+    async fn create_protocol(&self, name: &str, protocol_id: &str) -> Result<String, String>;
+
+    /// The factory's record for `protocol_id` from `getProtocolDetailsViaId`, or `None`
+    /// if it never deployed one under that id.
+    async fn deployed_protocol(
+        &self,
+        protocol_id: &str,
+    ) -> Result<Option<DeployedProtocol>, String>;
+
+    /// The contract the factory last deployed under `name`, from `getProtocolAddress`.
+    /// The name registry does not record who deployed it.
+    async fn protocol_address_via_name(&self, name: &str) -> Result<Option<String>, String>;
+
+    /// Account the server sends transactions from, if it has one.
+    fn signer_address(&self) -> Option<String>;
+
+    /// Whether a protocol whose factory record lists `admin` was deployed by this server.
+    /// Anyone can call `createProtocol` under any id, so only these are trusted.
+    fn deployed_by_server(&self, admin: &str) -> bool {
+        self.signer_address()
+            .is_some_and(|signer| signer.eq_ignore_ascii_case(admin))
+    }
+
+    /// Sends `gradeQuiz` and returns the transaction hash without waiting for it to be mined.
+    async fn submit_grading(&self, submission: GradingSubmission) -> Result<String, String>;
+
+    /// The coprocessor's notice for `payload`, or `None` while it has not arrived.
+    async fn quiz_response(
+        &self,
+        contract_address: &str,
+        payload: &str,
+    ) -> Result<Option<Vec<u8>>, String>;
+
+    /// The results root the contract recorded when the coprocessor settled `uuid`, from
+    /// `quizSettled` and `quizResultsRoot`, or `None` while it has not settled.
+    async fn settled_results_root(
+        &self,
+        contract_address: &str,
+        uuid: &str,
+    ) -> Result<Option<String>, String>;
+
+    async fn block_number(&self) -> Result<u64, String>;
+
+    /// Logs emitted by `contract_address` between two blocks, both inclusive.
+    async fn logs(
+        &self,
+        contract_address: &str,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<Vec<Log>, String>;
+}
+
+/// Stands in for a chain the server could not connect to, so it still starts and
+/// every on-chain step fails with the reason instead.
+pub struct DisabledChainClient {
+    reason: String,
+}
+
+impl DisabledChainClient {
+    pub fn new(reason: String) -> Self {
+        DisabledChainClient { reason }
+    }
+
+    fn error<T>(&self) -> Result<T, String> {
+        Err(format!("Chain client is disabled: {}", self.reason))
+    }
+}
+
+#[async_trait]
+impl ChainClient for DisabledChainClient {
+    fn signer_address(&self) -> Option<String> {
+        None
+    }
+
+    async fn create_protocol(&self, _name: &str, _protocol_id: &str) -> Result<String, String> {
+        self.error()
+    }
+
+    async fn deployed_protocol(
+        &self,
+        _protocol_id: &str,
+    ) -> Result<Option<DeployedProtocol>, String> {
+        self.error()
+    }
+
+    async fn protocol_address_via_name(&self, _name: &str) -> Result<Option<String>, String> {
+        self.error()
+    }
+
+    async fn submit_grading(&self, _submission: GradingSubmission) -> Result<String, String> {
+        self.error()
+    }
+
+    async fn quiz_response(
+        &self,
+        _contract_address: &str,
+        _payload: &str,
+    ) -> Result<Option<Vec<u8>>, String> {
+        self.error()
+    }
+
+    async fn settled_results_root(
+        &self,
+        _contract_address: &str,
+        _uuid: &str,
+    ) -> Result<Option<String>, String> {
+        self.error()
+    }
+
+    async fn block_number(&self) -> Result<u64, String> {
+        self.error()
+    }
+
+    async fn logs(
+        &self,
+        _contract_address: &str,
+        _from_block: u64,
+        _to_block: u64,
+    ) -> Result<Vec<Log>, String> {
+        self.error()
+    }
+}
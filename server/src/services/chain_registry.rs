@@ -0,0 +1,210 @@
+use super::alloy_chain::AlloyChainClient;
+use super::chain::{ChainClient, DisabledChainClient};
+use super::signer::signer_from_env;
+use super::storage::Storage;
+use super::tx_manager::{TxManager, TxManagerConfig};
+use crate::models::chain_model::{ChainConfig, ChainInfo};
+use crate::models::quiz_model::MAX_TOKEN_DECIMALS;
+use std::env;
+use std::fs;
+use std::sync::Arc;
+
+/// A chain from the registry and the client used to reach it.
+pub struct RegisteredChain {
+    pub config: ChainConfig,
+    pub client: Arc<dyn ChainClient>,
+    /// False when the client is a `DisabledChainClient` standing in for a failed connection.
+    pub enabled: bool,
+}
+
+/// Every chain protocols can be deployed on. Protocols registered before chains were
+/// recorded have no chain id and belong to the default chain.
+pub struct ChainRegistry {
+    chains: Vec<RegisteredChain>,
+    default_chain_id: Option<u64>,
+}
+
+impl ChainRegistry {
+    pub fn new() -> Self {
+        ChainRegistry {
+            chains: Vec::new(),
+            default_chain_id: None,
+        }
+    }
+
+    /// Adds a chain, replacing any earlier entry with the same id. The first chain added
+    /// becomes the default.
+    pub fn register(&mut self, config: ChainConfig, client: Arc<dyn ChainClient>) {
+        self.insert(RegisteredChain {
+            config,
+            client,
+            enabled: true,
+        });
+    }
+
+    /// Adds a chain whose on-chain steps all fail with `reason`.
+    pub fn register_disabled(&mut self, config: ChainConfig, reason: String) {
+        self.insert(RegisteredChain {
+            config,
+            client: Arc::new(DisabledChainClient::new(reason)),
+            enabled: false,
+        });
+    }
+
+    fn insert(&mut self, chain: RegisteredChain) {
+        self.default_chain_id.get_or_insert(chain.config.chain_id);
+        self.chains
+            .retain(|existing| existing.config.chain_id != chain.config.chain_id);
+        self.chains.push(chain);
+    }
+
+    pub fn set_default(&mut self, chain_id: u64) -> Result<(), String> {
+        if self.get(chain_id).is_none() {
+            return Err(format!("Chain {} is not in the registry", chain_id));
+        }
+        self.default_chain_id = Some(chain_id);
+        Ok(())
+    }
+
+    pub fn default_chain_id(&self) -> Option<u64> {
+        self.default_chain_id
+    }
+
+    pub fn chains(&self) -> &[RegisteredChain] {
+        &self.chains
+    }
+
+    pub fn get(&self, chain_id: u64) -> Option<&RegisteredChain> {
+        self.chains
+            .iter()
+            .find(|chain| chain.config.chain_id == chain_id)
+    }
+
+    /// The chain with `chain_id`, or the default chain when it is `None`.
+    pub fn resolve(&self, chain_id: Option<u64>) -> Result<&RegisteredChain, String> {
+        let chain_id = chain_id
+            .or(self.default_chain_id)
+            .ok_or("No chain is configured")?;
+        self.get(chain_id)
+            .ok_or_else(|| format!("Chain {} is not in the registry", chain_id))
+    }
+
+    pub fn info(&self, chain_id: Option<u64>) -> Option<ChainInfo> {
+        self.resolve(chain_id)
+            .ok()
+            .map(|chain| chain.config.info(chain.enabled))
+    }
+
+    pub fn infos(&self) -> Vec<ChainInfo> {
+        self.chains
+            .iter()
+            .map(|chain| chain.config.info(chain.enabled))
+            .collect()
+    }
+}
+
+impl Default for ChainRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Reads the chain registry from the JSON file named by `CHAIN_REGISTRY`, a list of
+/// `ChainConfig` objects. Without it, a single chain is built from `CHAIN_ID`, `RPC`,
+/// `OPENQUEST_FACTORY`, `TASK_ISSUER` and, if set, `CHAIN_NAME` and `EXPLORER_URL`; its
+/// reward token uses `REWARD_TOKEN_DECIMALS`.
+pub fn load_chain_configs() -> Result<Vec<ChainConfig>, String> {
+    let configs = match env::var("CHAIN_REGISTRY") {
+        Ok(path) => {
+            let contents = fs::read_to_string(&path)
+                .map_err(|e| format!("Unable to read chain registry {}: {}", path, e))?;
+            serde_json::from_str::<Vec<ChainConfig>>(&contents)
+                .map_err(|e| format!("Invalid chain registry {}: {}", path, e))?
+        }
+        Err(_) => {
+            let rpc_url = env::var("RPC").map_err(|_| "CHAIN_REGISTRY or RPC must be set")?;
+            let chain_id = env::var("CHAIN_ID")
+                .ok()
+                .and_then(|value| value.parse::<u64>().ok())
+                .ok_or("CHAIN_ID must be set to the chain id of RPC")?;
+            vec![ChainConfig {
+                chain_id,
+                name: env::var("CHAIN_NAME").unwrap_or_else(|_| format!("Chain {}", chain_id)),
+                rpc_url,
+                factory_address: env::var("OPENQUEST_FACTORY").ok(),
+                task_issuer: env::var("TASK_ISSUER").ok(),
+                explorer_url: env::var("EXPLORER_URL").ok(),
+                reward_token_decimals: None,
+            }]
+        }
+    };
+
+    for (index, config) in configs.iter().enumerate() {
+        if configs[..index]
+            .iter()
+            .any(|other| other.chain_id == config.chain_id)
+        {
+            return Err(format!(
+                "Chain {} is listed more than once in the registry",
+                config.chain_id
+            ));
+        }
+        if config
+            .reward_token_decimals
+            .is_some_and(|decimals| decimals > MAX_TOKEN_DECIMALS)
+        {
+            return Err(format!(
+                "Chain {} has more than {} reward token decimals",
+                config.chain_id, MAX_TOKEN_DECIMALS
+            ));
+        }
+    }
+    Ok(configs)
+}
+
+/// `DEFAULT_CHAIN_ID`, if set; otherwise the first chain in the registry is the default.
+fn default_chain_id_from_env() -> Option<u64> {
+    env::var("DEFAULT_CHAIN_ID")
+        .ok()
+        .and_then(|value| value.parse::<u64>().ok())
+}
+
+/// Connects to every chain in the registry with the signer chosen by `SIGNER_BACKEND`.
+/// A chain that cannot be reached stays listed and its on-chain steps fail with the
+/// reason. Returns the transaction managers of the connected chains, which the caller
+/// has to run.
+pub fn connect_from_env(db: Arc<dyn Storage>) -> (ChainRegistry, Vec<Arc<TxManager>>) {
+    let mut registry = ChainRegistry::new();
+    let mut tx_managers = Vec::new();
+    // One signer for every chain, so a keystore is only unlocked once.
+    let signer = signer_from_env();
+    if let Err(e) = signer.as_ref() {
+        println!("No transaction signer: {}", e);
+    }
+    match load_chain_configs() {
+        Ok(configs) => {
+            for config in configs {
+                let client = signer.clone().and_then(|signer| {
+                    AlloyChainClient::new(&config, signer, db.clone(), TxManagerConfig::from_env())
+                });
+                match client {
+                    Ok(client) => {
+                        tx_managers.push(client.tx_manager());
+                        registry.register(config, Arc::new(client));
+                    }
+                    Err(e) => {
+                        println!("Chain {} disabled: {}", config.chain_id, e);
+                        registry.register_disabled(config, e);
+                    }
+                }
+            }
+        }
+        Err(e) => println!("No chains configured: {}", e),
+    }
+    if let Some(chain_id) = default_chain_id_from_env() {
+        if let Err(e) = registry.set_default(chain_id) {
+            println!("Ignoring DEFAULT_CHAIN_ID: {}", e);
+        }
+    }
+    (registry, tx_managers)
+}
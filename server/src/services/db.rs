@@ -1,40 +1,29 @@
+use crate::models::event_model::{ContractEvent, IndexerCursor};
+use crate::models::hackathon_model::Hackathon;
 use crate::models::protocol_model::Protocol;
 use crate::models::quiz_model::{Participant, Quiz};
+use crate::models::settlement_model::{SettlementJob, SettlementStage};
+use crate::models::transaction_model::{ChainTransaction, TransactionPurpose, TransactionStatus};
 use crate::models::user_model::{SimpleUserStruct, User, Wallet};
+use crate::models::wallet_challenge_model::{ChallengePurpose, WalletChallenge};
+use crate::services::storage::{DatabaseResponse, Storage};
+use async_trait::async_trait;
 use dotenv::dotenv;
 use futures::{StreamExt, TryStreamExt};
-use mongodb::bson::from_document;
-use mongodb::error::Error;
-use mongodb::{
-    bson::doc,
-    bson::{to_bson, Bson},
-    options::IndexOptions,
-    results::{DeleteResult, InsertOneResult, UpdateResult},
-    Client, Collection, IndexModel,
-};
+use mongodb::{bson::doc, bson::to_bson, options::ReturnDocument, Client, Collection};
 use std::env;
-use std::result;
 
 #[derive(Debug, Clone)]
 pub struct Database {
     users: Collection<User>,
     protocols: Collection<Protocol>,
     quizes: Collection<Quiz>,
-}
-
-#[derive(Debug)]
-pub struct DatabaseResponse {
-    pub error_code: u16,
-    pub message: String,
-}
-
-impl DatabaseResponse {
-    pub fn new(error_code: u16, message: String) -> Self {
-        DatabaseResponse {
-            error_code,
-            message,
-        }
-    }
+    hackathons: Collection<Hackathon>,
+    settlement_jobs: Collection<SettlementJob>,
+    events: Collection<ContractEvent>,
+    indexer_cursors: Collection<IndexerCursor>,
+    transactions: Collection<ChainTransaction>,
+    wallet_challenges: Collection<WalletChallenge>,
 }
 
 macro_rules! try_or_return_string {
@@ -47,29 +36,45 @@ macro_rules! try_or_return_string {
 }
 
 impl Database {
-    pub async fn init() -> Self {
+    pub async fn init() -> Result<Self, DatabaseResponse> {
         dotenv().ok();
-        let db_url = env::var("DB_URL").expect("DB_URL must be set");
+        let db_url = env::var("DB_URL")
+            .map_err(|_| DatabaseResponse::new(500, "DB_URL must be set".to_string()))?;
 
         let client = Client::with_uri_str(db_url)
             .await
-            .expect("failed to connect");
+            .map_err(|e| DatabaseResponse::new(500, format!("failed to connect: {}", e)))?;
         let db = client.database("OpenQuest");
 
         let users = db.collection("users");
         let protocols = db.collection("protocols");
         let quizes = db.collection("quizes");
+        let hackathons = db.collection("hackathons");
+        let settlement_jobs = db.collection("settlement_jobs");
+        let events = db.collection("events");
+        let indexer_cursors = db.collection("indexer_cursors");
+        let transactions = db.collection("transactions");
+        let wallet_challenges = db.collection("wallet_challenges");
         println!("DATABASE CONNECTION SUCCESSFUL!!!!");
-        return Database {
+        Ok(Database {
             users,
             protocols,
             quizes,
-        };
+            hackathons,
+            settlement_jobs,
+            events,
+            indexer_cursors,
+            transactions,
+            wallet_challenges,
+        })
     }
+}
 
-    pub async fn create_user(&self, user: User) -> Result<InsertOneResult, DatabaseResponse> {
+#[async_trait]
+impl Storage for Database {
+    async fn create_user(&self, user: User) -> Result<(), DatabaseResponse> {
         match self.users.insert_one(user).await {
-            Ok(result) => Ok(result),
+            Ok(_result) => Ok(()),
             Err(e) => Err(DatabaseResponse::new(
                 500,
                 format!("{}, {}", "Error creating user", e),
@@ -77,7 +82,7 @@ impl Database {
         }
     }
 
-    pub async fn get_all_users(&self) -> Result<Vec<SimpleUserStruct>, DatabaseResponse> {
+    async fn get_all_users(&self) -> Result<Vec<SimpleUserStruct>, DatabaseResponse> {
         let result = self.users.find(doc! {}).await;
 
         match result {
@@ -101,7 +106,7 @@ impl Database {
         }
     }
 
-    pub async fn get_user_via_email(&self, email: String) -> Result<User, DatabaseResponse> {
+    async fn get_user_via_email(&self, email: String) -> Result<User, DatabaseResponse> {
         let result = self.users.find_one(doc! {"email.email": email}).await;
 
         match result {
@@ -111,7 +116,7 @@ impl Database {
         }
     }
 
-    pub async fn get_user_via_address(&self, address: String) -> Result<User, DatabaseResponse> {
+    async fn get_user_via_address(&self, address: String) -> Result<User, DatabaseResponse> {
         let filter = doc! {
             "wallet.wallet_address": {
                 "$regex": format!("^{}$", address),
@@ -137,7 +142,7 @@ impl Database {
         }
     }
 
-    pub async fn get_user_via_uuid(&self, uuid: String) -> Result<User, DatabaseResponse> {
+    async fn get_user_via_uuid(&self, uuid: String) -> Result<User, DatabaseResponse> {
         let result = self.users.find_one(doc! {"user_uuid": uuid}).await;
 
         match result {
@@ -147,14 +152,14 @@ impl Database {
         }
     }
 
-    pub async fn update_user_wallet(
+    async fn update_user_wallet(
         &self,
         uuid: String,
         wallet_struct: Wallet,
-    ) -> Result<UpdateResult, DatabaseResponse> {
+    ) -> Result<(), DatabaseResponse> {
         let wallet_bson =
             to_bson(&wallet_struct).map_err(|e| DatabaseResponse::new(500, e.to_string()))?;
-        let result = try_or_return_string!(
+        let _result = try_or_return_string!(
             self.users
                 .update_one(
                     doc! {"user_uuid": uuid},
@@ -162,10 +167,52 @@ impl Database {
                 )
                 .await
         );
-        Ok(result)
+        Ok(())
     }
 
-    pub async fn get_protocol_via_name(&self, name: String) -> Result<Protocol, DatabaseResponse> {
+    async fn create_wallet_challenge(
+        &self,
+        challenge: WalletChallenge,
+    ) -> Result<(), DatabaseResponse> {
+        match self.wallet_challenges.insert_one(challenge).await {
+            Ok(_result) => Ok(()),
+            Err(e) => Err(DatabaseResponse::new(
+                500,
+                format!("{}, {}", "Error saving wallet challenge", e),
+            )),
+        }
+    }
+
+    async fn consume_wallet_challenge(
+        &self,
+        nonce: String,
+        purpose: ChallengePurpose,
+        user_uuid: Option<String>,
+    ) -> Result<WalletChallenge, DatabaseResponse> {
+        let result = self
+            .wallet_challenges
+            .find_one_and_update(
+                doc! {
+                    "nonce": nonce,
+                    "purpose": purpose.to_string(),
+                    "user_uuid": user_uuid,
+                    "used": false,
+                },
+                doc! {"$set": {"used": true}},
+            )
+            .return_document(ReturnDocument::After)
+            .await;
+        match result {
+            Ok(Some(challenge)) => Ok(challenge),
+            Ok(None) => Err(DatabaseResponse::new(
+                404,
+                "Wallet challenge not found".to_string(),
+            )),
+            Err(e) => Err(DatabaseResponse::new(500, format!("Database error: {}", e))),
+        }
+    }
+
+    async fn get_protocol_via_name(&self, name: String) -> Result<Protocol, DatabaseResponse> {
         let result = self.protocols.find_one(doc! {"name": name}).await;
 
         match result {
@@ -175,7 +222,7 @@ impl Database {
         }
     }
 
-    pub async fn get_protocol_via_id(&self, id: String) -> Result<Protocol, DatabaseResponse> {
+    async fn get_protocol_via_id(&self, id: String) -> Result<Protocol, DatabaseResponse> {
         let filter = doc! {
             "protocol_uuid": {
                 "$regex": format!("^{}$", id),
@@ -201,12 +248,9 @@ impl Database {
         }
     }
 
-    pub async fn create_protocol(
-        &self,
-        protocol: Protocol,
-    ) -> Result<InsertOneResult, DatabaseResponse> {
+    async fn create_protocol(&self, protocol: Protocol) -> Result<(), DatabaseResponse> {
         match self.protocols.insert_one(protocol).await {
-            Ok(result) => Ok(result),
+            Ok(_result) => Ok(()),
             Err(e) => Err(DatabaseResponse::new(
                 500,
                 format!("{}, {}", "Error creating protocol", e),
@@ -214,13 +258,10 @@ impl Database {
         }
     }
 
-    pub async fn update_protocol_team(
-        &self,
-        protocol: Protocol,
-    ) -> Result<UpdateResult, DatabaseResponse> {
+    async fn update_protocol_team(&self, protocol: Protocol) -> Result<(), DatabaseResponse> {
         let staffs_bson =
             to_bson(&protocol.staffs).map_err(|e| DatabaseResponse::new(500, e.to_string()))?;
-        let result = try_or_return_string!(
+        let _result = try_or_return_string!(
             self.protocols
                 .update_one(
                     doc! {"name": protocol.name},
@@ -228,10 +269,10 @@ impl Database {
                 )
                 .await
         );
-        Ok(result)
+        Ok(())
     }
 
-    pub async fn get_all_protocols(&self) -> Result<Vec<Protocol>, DatabaseResponse> {
+    async fn get_all_protocols(&self) -> Result<Vec<Protocol>, DatabaseResponse> {
         let result = self.protocols.find(doc! {}).await;
 
         match result {
@@ -255,7 +296,7 @@ impl Database {
         }
     }
 
-    pub async fn update_protocol(&self, protocol: Protocol) -> Result<Protocol, DatabaseResponse> {
+    async fn update_protocol(&self, protocol: Protocol) -> Result<Protocol, DatabaseResponse> {
         let result = self
             .protocols
             .replace_one(doc! {"name": protocol.name.clone()}, protocol.clone())
@@ -272,9 +313,9 @@ impl Database {
         }
     }
 
-    pub async fn add_quiz(&self, quiz: Quiz) -> Result<InsertOneResult, DatabaseResponse> {
+    async fn add_quiz(&self, quiz: Quiz) -> Result<(), DatabaseResponse> {
         match self.quizes.insert_one(quiz).await {
-            Ok(result) => Ok(result),
+            Ok(_result) => Ok(()),
             Err(e) => Err(DatabaseResponse::new(
                 500,
                 format!("{}, {}", "Error creating quiz", e),
@@ -282,7 +323,7 @@ impl Database {
         }
     }
 
-    pub async fn get_quiz_via_uuid(&self, uuid: String) -> Result<Quiz, DatabaseResponse> {
+    async fn get_quiz_via_uuid(&self, uuid: String) -> Result<Quiz, DatabaseResponse> {
         let filter = doc! {
             "uuid": {
                 "$regex": format!("^{}$", uuid),
@@ -308,7 +349,7 @@ impl Database {
         }
     }
 
-    pub async fn get_quiz_participant_via_uuid(
+    async fn get_quiz_participant_via_uuid(
         &self,
         quiz_uuid: String,
         participant_uuid: String,
@@ -338,7 +379,7 @@ impl Database {
         }
     }
 
-    pub async fn update_quiz(&self, quiz: Quiz) -> Result<Quiz, DatabaseResponse> {
+    async fn update_quiz(&self, quiz: Quiz) -> Result<Quiz, DatabaseResponse> {
         let result = self
             .quizes
             .replace_one(doc! {"uuid": quiz.uuid.clone()}, quiz.clone())
@@ -355,7 +396,7 @@ impl Database {
         }
     }
 
-    pub async fn update_user(&self, user: User) -> Result<User, DatabaseResponse> {
+    async fn update_user(&self, user: User) -> Result<User, DatabaseResponse> {
         let result = self
             .users
             .replace_one(doc! {"user_uuid": user.user_uuid.clone()}, user.clone())
@@ -372,7 +413,7 @@ impl Database {
         }
     }
 
-    pub async fn get_all_quizes(&self) -> Result<Vec<Quiz>, DatabaseResponse> {
+    async fn get_all_quizes(&self) -> Result<Vec<Quiz>, DatabaseResponse> {
         let result = self.quizes.find(doc! {}).await;
 
         match result {
@@ -392,4 +433,385 @@ impl Database {
             )),
         }
     }
+
+    async fn create_hackathon(&self, hackathon: Hackathon) -> Result<(), DatabaseResponse> {
+        match self.hackathons.insert_one(hackathon).await {
+            Ok(_result) => Ok(()),
+            Err(e) => Err(DatabaseResponse::new(
+                500,
+                format!("{}, {}", "Error creating hackathon", e),
+            )),
+        }
+    }
+
+    async fn get_hackathon_via_uuid(&self, uuid: String) -> Result<Hackathon, DatabaseResponse> {
+        let filter = doc! {
+            "uuid": {
+                "$regex": format!("^{}$", uuid),
+                "$options": "i"  // Case-insensitive option
+            }
+        };
+
+        let mut cursor = match self.hackathons.find(filter).await {
+            Ok(cursor) => cursor,
+            Err(e) => return Err(DatabaseResponse::new(500, format!("Database error: {}", e))),
+        };
+
+        if let Some(result) = cursor.next().await {
+            match result {
+                Ok(hackathon) => Ok(hackathon),
+                Err(e) => Err(DatabaseResponse::new(
+                    500,
+                    format!("Error parsing hackathon data: {}", e),
+                )),
+            }
+        } else {
+            Err(DatabaseResponse::new(
+                404,
+                "Hackathon not found".to_string(),
+            ))
+        }
+    }
+
+    async fn update_hackathon(&self, hackathon: Hackathon) -> Result<Hackathon, DatabaseResponse> {
+        let result = self
+            .hackathons
+            .replace_one(doc! {"uuid": hackathon.uuid.clone()}, hackathon.clone())
+            .await;
+        match result {
+            Ok(update_result) => {
+                if update_result.matched_count == 0 {
+                    Err(DatabaseResponse::new(
+                        404,
+                        "Hackathon not found".to_string(),
+                    ))
+                } else {
+                    Ok(hackathon)
+                }
+            }
+            Err(e) => Err(DatabaseResponse::new(500, format!("{}", e))),
+        }
+    }
+
+    async fn get_all_hackathons(&self) -> Result<Vec<Hackathon>, DatabaseResponse> {
+        let result = self.hackathons.find(doc! {}).await;
+
+        match result {
+            Ok(mut cursor) => {
+                let mut hackathons: Vec<Hackathon> = Vec::new();
+                while let Ok(Some(hackathon)) = cursor.try_next().await {
+                    hackathons.push(hackathon);
+                }
+                if hackathons.is_empty() {
+                    return Err(DatabaseResponse::new(
+                        404,
+                        "No hackathons were found".to_string(),
+                    ));
+                }
+                Ok(hackathons)
+            }
+            Err(e) => Err(DatabaseResponse::new(
+                500,
+                format!("{}, {}", "Error retrieving hackathons", e),
+            )),
+        }
+    }
+
+    async fn create_settlement_job(&self, job: SettlementJob) -> Result<(), DatabaseResponse> {
+        match self.settlement_jobs.insert_one(job).await {
+            Ok(_result) => Ok(()),
+            Err(e) => Err(DatabaseResponse::new(
+                500,
+                format!("{}, {}", "Error creating settlement job", e),
+            )),
+        }
+    }
+
+    async fn get_settlement_job_via_target(
+        &self,
+        target_uuid: String,
+    ) -> Result<SettlementJob, DatabaseResponse> {
+        match self
+            .settlement_jobs
+            .find_one(doc! {"target_uuid": target_uuid})
+            .await
+        {
+            Ok(Some(job)) => Ok(job),
+            Ok(None) => Err(DatabaseResponse::new(
+                404,
+                "Settlement job not found".to_string(),
+            )),
+            Err(e) => Err(DatabaseResponse::new(500, format!("Database error: {}", e))),
+        }
+    }
+
+    async fn get_due_settlement_jobs(
+        &self,
+        now: i64,
+    ) -> Result<Vec<SettlementJob>, DatabaseResponse> {
+        let pending: Vec<&str> = [
+            SettlementStage::Queued,
+            SettlementStage::SubmittedOnchain,
+            SettlementStage::AwaitingResult,
+            SettlementStage::ApplyingResults,
+        ]
+        .iter()
+        .map(|stage| stage.to_string())
+        .collect();
+        let filter = doc! {
+            "stage": {"$in": pending},
+            "next_attempt_at": {"$lte": now},
+        };
+
+        let mut cursor = match self
+            .settlement_jobs
+            .find(filter)
+            .sort(doc! {"next_attempt_at": 1})
+            .await
+        {
+            Ok(cursor) => cursor,
+            Err(e) => return Err(DatabaseResponse::new(500, format!("Database error: {}", e))),
+        };
+        let mut jobs: Vec<SettlementJob> = Vec::new();
+        while let Some(result) = cursor.next().await {
+            match result {
+                Ok(job) => jobs.push(job),
+                Err(e) => {
+                    return Err(DatabaseResponse::new(
+                        500,
+                        format!("Error parsing settlement job: {}", e),
+                    ))
+                }
+            }
+        }
+        Ok(jobs)
+    }
+
+    async fn update_settlement_job(
+        &self,
+        job: SettlementJob,
+    ) -> Result<SettlementJob, DatabaseResponse> {
+        let result = self
+            .settlement_jobs
+            .replace_one(doc! {"uuid": job.uuid.clone()}, job.clone())
+            .await;
+        match result {
+            Ok(update_result) => {
+                if update_result.matched_count == 0 {
+                    Err(DatabaseResponse::new(
+                        404,
+                        "Settlement job not found".to_string(),
+                    ))
+                } else {
+                    Ok(job)
+                }
+            }
+            Err(e) => Err(DatabaseResponse::new(500, format!("{}", e))),
+        }
+    }
+
+    async fn save_contract_event(&self, event: ContractEvent) -> Result<(), DatabaseResponse> {
+        match self
+            .events
+            .replace_one(doc! {"id": event.id.clone()}, event)
+            .upsert(true)
+            .await
+        {
+            Ok(_result) => Ok(()),
+            Err(e) => Err(DatabaseResponse::new(
+                500,
+                format!("{}, {}", "Error saving contract event", e),
+            )),
+        }
+    }
+
+    async fn get_contract_events(
+        &self,
+        contract_address: String,
+    ) -> Result<Vec<ContractEvent>, DatabaseResponse> {
+        let mut cursor = match self
+            .events
+            .find(doc! {"contract_address": contract_address.to_lowercase()})
+            .sort(doc! {"block_number": 1, "log_index": 1})
+            .await
+        {
+            Ok(cursor) => cursor,
+            Err(e) => return Err(DatabaseResponse::new(500, format!("Database error: {}", e))),
+        };
+        let mut events: Vec<ContractEvent> = Vec::new();
+        while let Ok(Some(event)) = cursor.try_next().await {
+            events.push(event);
+        }
+        if events.is_empty() {
+            return Err(DatabaseResponse::new(
+                404,
+                "No events were found".to_string(),
+            ));
+        }
+        Ok(events)
+    }
+
+    async fn get_indexer_cursor(
+        &self,
+        chain_id: u64,
+        contract_address: String,
+    ) -> Result<IndexerCursor, DatabaseResponse> {
+        match self
+            .indexer_cursors
+            .find_one(doc! {
+                "chain_id": chain_id as i64,
+                "contract_address": contract_address.to_lowercase(),
+            })
+            .await
+        {
+            Ok(Some(cursor)) => Ok(cursor),
+            Ok(None) => Err(DatabaseResponse::new(
+                404,
+                "Indexer cursor not found".to_string(),
+            )),
+            Err(e) => Err(DatabaseResponse::new(500, format!("Database error: {}", e))),
+        }
+    }
+
+    async fn save_indexer_cursor(&self, cursor: IndexerCursor) -> Result<(), DatabaseResponse> {
+        match self
+            .indexer_cursors
+            .replace_one(
+                doc! {
+                    "chain_id": cursor.chain_id.map(|chain_id| chain_id as i64),
+                    "contract_address": cursor.contract_address.clone(),
+                },
+                cursor,
+            )
+            .upsert(true)
+            .await
+        {
+            Ok(_result) => Ok(()),
+            Err(e) => Err(DatabaseResponse::new(
+                500,
+                format!("{}, {}", "Error saving indexer cursor", e),
+            )),
+        }
+    }
+
+    async fn create_transaction(&self, tx: ChainTransaction) -> Result<(), DatabaseResponse> {
+        match self.transactions.insert_one(tx).await {
+            Ok(_result) => Ok(()),
+            Err(e) => Err(DatabaseResponse::new(
+                500,
+                format!("{}, {}", "Error saving transaction", e),
+            )),
+        }
+    }
+
+    async fn update_transaction(
+        &self,
+        tx: ChainTransaction,
+    ) -> Result<ChainTransaction, DatabaseResponse> {
+        let result = self
+            .transactions
+            .replace_one(doc! {"uuid": tx.uuid.clone()}, tx.clone())
+            .await;
+        match result {
+            Ok(update_result) => {
+                if update_result.matched_count == 0 {
+                    Err(DatabaseResponse::new(
+                        404,
+                        "Transaction not found".to_string(),
+                    ))
+                } else {
+                    Ok(tx)
+                }
+            }
+            Err(e) => Err(DatabaseResponse::new(500, format!("{}", e))),
+        }
+    }
+
+    async fn get_transaction_via_hash(
+        &self,
+        tx_hash: String,
+    ) -> Result<ChainTransaction, DatabaseResponse> {
+        let filter = doc! {
+            "$or": [
+                {"tx_hash": {"$regex": format!("^{}$", tx_hash), "$options": "i"}},
+                {"replaced_hashes": {"$regex": format!("^{}$", tx_hash), "$options": "i"}},
+            ]
+        };
+        match self.transactions.find_one(filter).await {
+            Ok(Some(tx)) => Ok(tx),
+            Ok(None) => Err(DatabaseResponse::new(
+                404,
+                "Transaction not found".to_string(),
+            )),
+            Err(e) => Err(DatabaseResponse::new(500, format!("Database error: {}", e))),
+        }
+    }
+
+    async fn get_transactions_via_target(
+        &self,
+        target_uuid: String,
+    ) -> Result<Vec<ChainTransaction>, DatabaseResponse> {
+        self.find_transactions(doc! {"target_uuid": target_uuid}, doc! {"created_at": 1})
+            .await
+    }
+
+    async fn get_transactions_via_protocol(
+        &self,
+        protocol: String,
+    ) -> Result<Vec<ChainTransaction>, DatabaseResponse> {
+        self.find_transactions(doc! {"protocol": protocol}, doc! {"created_at": 1})
+            .await
+    }
+
+    async fn get_transactions_via_purpose(
+        &self,
+        purpose: TransactionPurpose,
+    ) -> Result<Vec<ChainTransaction>, DatabaseResponse> {
+        self.find_transactions(
+            doc! {"purpose": purpose.to_string()},
+            doc! {"created_at": 1},
+        )
+        .await
+    }
+
+    async fn get_open_transactions(
+        &self,
+        chain_id: u64,
+    ) -> Result<Vec<ChainTransaction>, DatabaseResponse> {
+        let open: Vec<&str> = [TransactionStatus::Pending, TransactionStatus::Mined]
+            .iter()
+            .map(|status| status.to_string())
+            .collect();
+        self.find_transactions(
+            doc! {"chain_id": chain_id as i64, "status": {"$in": open}},
+            doc! {"nonce": 1},
+        )
+        .await
+    }
+}
+
+impl Database {
+    async fn find_transactions(
+        &self,
+        filter: mongodb::bson::Document,
+        sort: mongodb::bson::Document,
+    ) -> Result<Vec<ChainTransaction>, DatabaseResponse> {
+        let mut cursor = match self.transactions.find(filter).sort(sort).await {
+            Ok(cursor) => cursor,
+            Err(e) => return Err(DatabaseResponse::new(500, format!("Database error: {}", e))),
+        };
+        let mut transactions: Vec<ChainTransaction> = Vec::new();
+        while let Some(result) = cursor.next().await {
+            match result {
+                Ok(tx) => transactions.push(tx),
+                Err(e) => {
+                    return Err(DatabaseResponse::new(
+                        500,
+                        format!("Error parsing transaction: {}", e),
+                    ))
+                }
+            }
+        }
+        Ok(transactions)
+    }
 }
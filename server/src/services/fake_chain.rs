@@ -0,0 +1,318 @@
+use super::chain::{
+    ChainClient, DeployedProtocol, GradingSubmission,
+    OpenQuestEvents::{PtotocolCreated, ResultReceived},
+};
+use super::quiz_services::decode_quiz_response;
+use alloy::{
+    primitives::{keccak256, Address, Bytes, B256, U256},
+    rpc::types::Log,
+    sol_types::SolEvent,
+};
+use async_trait::async_trait;
+use std::collections::{HashMap, VecDeque};
+use std::str::FromStr;
+use std::sync::RwLock;
+
+/// A protocol deployed through `FakeChainClient::create_protocol`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FakeProtocol {
+    pub name: String,
+    pub protocol_id: String,
+    pub contract_address: String,
+    pub admin: String,
+}
+
+fn lock_error() -> String {
+    "Fake chain lock poisoned".to_string()
+}
+
+#[derive(Default)]
+struct FakeChainState {
+    block_number: u64,
+    protocols: Vec<FakeProtocol>,
+    submissions: Vec<GradingSubmission>,
+    /// Coprocessor notices keyed by lowercased payload hex.
+    responses: HashMap<String, Vec<u8>>,
+    /// Results roots of settled quizzes, keyed by lowercased contract address and uuid.
+    settled_roots: HashMap<(String, String), String>,
+    logs: Vec<Log>,
+    failures: VecDeque<String>,
+}
+
+/// In-memory `ChainClient` for tests. Every write mines one block and emits the same
+/// logs the real contracts would, and failures can be queued with `fail_next`.
+pub struct FakeChainClient {
+    factory_address: Address,
+    signer: Address,
+    state: RwLock<FakeChainState>,
+}
+
+impl FakeChainClient {
+    pub fn new() -> Self {
+        FakeChainClient {
+            factory_address: Address::from_word(keccak256("openquest-factory")),
+            signer: Address::from_word(keccak256("openquest-signer")),
+            state: RwLock::new(FakeChainState::default()),
+        }
+    }
+
+    pub fn factory_address(&self) -> String {
+        self.factory_address.to_string()
+    }
+
+    /// Calls `createProtocol` from `admin` instead of the server's signer, the way anyone
+    /// can, overwriting the factory's records for `name` and `protocol_id`.
+    pub fn create_protocol_as(
+        &self,
+        admin: &str,
+        name: &str,
+        protocol_id: &str,
+    ) -> Result<String, String> {
+        let admin = Address::from_str(admin).map_err(|e| e.to_string())?;
+        let mut state = self.state.write().map_err(|_| lock_error())?;
+        Ok(self.deploy(&mut state, admin, name, protocol_id))
+    }
+
+    /// Makes the next call fail with `error`; queued failures are used in order.
+    pub fn fail_next(&self, error: &str) {
+        if let Ok(mut state) = self.state.write() {
+            state.failures.push_back(error.to_string());
+        }
+    }
+
+    /// Plays the coprocessor: stores `notice` as the response to `payload`, settles the
+    /// quiz it is for unless an earlier notice already did, and emits `ResultReceived`
+    /// from the contract that was asked to grade it.
+    pub fn respond(&self, payload: &str, notice: Vec<u8>) -> Result<(), String> {
+        let data = decode_quiz_response(&notice)?;
+        let mut state = self.state.write().map_err(|_| lock_error())?;
+        let contract_address = Self::graded_by(&state, payload)?;
+        state
+            .settled_roots
+            .entry((contract_address.to_lowercase(), data.uuid))
+            .or_insert(data.results_root.unwrap_or(B256::ZERO.to_string()));
+        let contract_address = Address::from_str(&contract_address).map_err(|e| e.to_string())?;
+        state
+            .responses
+            .insert(payload.to_lowercase(), notice.clone());
+        let event = ResultReceived {
+            data: Bytes::from(notice),
+        };
+        Self::mine(&mut state, contract_address, &event);
+        Ok(())
+    }
+
+    /// Overwrites the stored response to `payload` without settling anything, the way
+    /// anyone could through the `demoHandleNotice` of contracts deployed before it was removed.
+    pub fn plant_response(&self, payload: &str, notice: Vec<u8>) -> Result<(), String> {
+        let mut state = self.state.write().map_err(|_| lock_error())?;
+        Self::graded_by(&state, payload)?;
+        state.responses.insert(payload.to_lowercase(), notice);
+        Ok(())
+    }
+
+    /// Contract that was asked to grade `payload`.
+    fn graded_by(state: &FakeChainState, payload: &str) -> Result<String, String> {
+        state
+            .submissions
+            .iter()
+            .find(|submission| submission.payload.eq_ignore_ascii_case(payload))
+            .map(|submission| submission.contract_address.clone())
+            .ok_or_else(|| "No grading submission for this payload".to_string())
+    }
+
+    /// Emits an arbitrary event from `contract_address` in a new block.
+    pub fn emit<E: SolEvent>(&self, contract_address: &str, event: &E) -> Result<(), String> {
+        let address = Address::from_str(contract_address).map_err(|e| e.to_string())?;
+        let mut state = self.state.write().map_err(|_| lock_error())?;
+        Self::mine(&mut state, address, event);
+        Ok(())
+    }
+
+    pub fn protocols(&self) -> Vec<FakeProtocol> {
+        self.state
+            .read()
+            .map(|state| state.protocols.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn submissions(&self) -> Vec<GradingSubmission> {
+        self.state
+            .read()
+            .map(|state| state.submissions.clone())
+            .unwrap_or_default()
+    }
+
+    fn take_failure(&self) -> Result<(), String> {
+        let mut state = self.state.write().map_err(|_| lock_error())?;
+        match state.failures.pop_front() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    /// Deploys a protocol contract from `admin`, returning the transaction hash. Each
+    /// deployment gets a new address, as it would on-chain.
+    fn deploy(
+        &self,
+        state: &mut FakeChainState,
+        admin: Address,
+        name: &str,
+        protocol_id: &str,
+    ) -> String {
+        let contract_address = Address::from_word(keccak256(format!(
+            "protocol:{}:{}",
+            protocol_id,
+            state.protocols.len()
+        )));
+        let event = PtotocolCreated {
+            admin,
+            time: U256::from(chrono::Utc::now().timestamp()),
+            protocol_id: keccak256(protocol_id),
+            protocolContract: contract_address,
+        };
+        let tx_hash = Self::mine(state, self.factory_address, &event);
+        state.protocols.push(FakeProtocol {
+            name: name.to_string(),
+            protocol_id: protocol_id.to_string(),
+            contract_address: contract_address.to_string(),
+            admin: admin.to_string(),
+        });
+        tx_hash.to_string()
+    }
+
+    /// Mines a block holding a single transaction that emits `event`, returning its hash.
+    fn mine<E: SolEvent>(state: &mut FakeChainState, address: Address, event: &E) -> B256 {
+        state.block_number += 1;
+        let tx_hash = keccak256(state.block_number.to_be_bytes());
+        state.logs.push(Log {
+            inner: alloy::primitives::Log {
+                address,
+                data: event.encode_log_data(),
+            },
+            block_number: Some(state.block_number),
+            transaction_hash: Some(tx_hash),
+            transaction_index: Some(0),
+            log_index: Some(0),
+            ..Default::default()
+        });
+        tx_hash
+    }
+}
+
+impl Default for FakeChainClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl ChainClient for FakeChainClient {
+    fn signer_address(&self) -> Option<String> {
+        Some(self.signer.to_string())
+    }
+
+    async fn create_protocol(&self, name: &str, protocol_id: &str) -> Result<String, String> {
+        self.take_failure()?;
+        let mut state = self.state.write().map_err(|_| lock_error())?;
+        Ok(self.deploy(&mut state, self.signer, name, protocol_id))
+    }
+
+    async fn deployed_protocol(
+        &self,
+        protocol_id: &str,
+    ) -> Result<Option<DeployedProtocol>, String> {
+        self.take_failure()?;
+        let state = self.state.read().map_err(|_| lock_error())?;
+        Ok(state
+            .protocols
+            .iter()
+            .rev()
+            .find(|protocol| protocol.protocol_id == protocol_id)
+            .map(|protocol| DeployedProtocol {
+                name: protocol.name.clone(),
+                protocol_id: protocol.protocol_id.clone(),
+                contract_address: protocol.contract_address.clone(),
+                admin: protocol.admin.clone(),
+            }))
+    }
+
+    async fn protocol_address_via_name(&self, name: &str) -> Result<Option<String>, String> {
+        self.take_failure()?;
+        let state = self.state.read().map_err(|_| lock_error())?;
+        Ok(state
+            .protocols
+            .iter()
+            .rev()
+            .find(|protocol| protocol.name == name)
+            .map(|protocol| protocol.contract_address.clone()))
+    }
+
+    async fn submit_grading(&self, submission: GradingSubmission) -> Result<String, String> {
+        self.take_failure()?;
+        let mut state = self.state.write().map_err(|_| lock_error())?;
+        if !state.protocols.iter().any(|protocol| {
+            protocol
+                .contract_address
+                .eq_ignore_ascii_case(&submission.contract_address)
+        }) {
+            return Err(format!(
+                "No protocol contract at {}",
+                submission.contract_address
+            ));
+        }
+        state.block_number += 1;
+        let tx_hash = keccak256(state.block_number.to_be_bytes()).to_string();
+        state.submissions.push(submission);
+        Ok(tx_hash)
+    }
+
+    async fn quiz_response(
+        &self,
+        _contract_address: &str,
+        payload: &str,
+    ) -> Result<Option<Vec<u8>>, String> {
+        self.take_failure()?;
+        let state = self.state.read().map_err(|_| lock_error())?;
+        Ok(state.responses.get(&payload.to_lowercase()).cloned())
+    }
+
+    async fn settled_results_root(
+        &self,
+        contract_address: &str,
+        uuid: &str,
+    ) -> Result<Option<String>, String> {
+        self.take_failure()?;
+        let state = self.state.read().map_err(|_| lock_error())?;
+        Ok(state
+            .settled_roots
+            .get(&(contract_address.to_lowercase(), uuid.to_string()))
+            .cloned())
+    }
+
+    async fn block_number(&self) -> Result<u64, String> {
+        self.take_failure()?;
+        let state = self.state.read().map_err(|_| lock_error())?;
+        Ok(state.block_number)
+    }
+
+    async fn logs(
+        &self,
+        contract_address: &str,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<Vec<Log>, String> {
+        self.take_failure()?;
+        let address = Address::from_str(contract_address).map_err(|e| e.to_string())?;
+        let state = self.state.read().map_err(|_| lock_error())?;
+        Ok(state
+            .logs
+            .iter()
+            .filter(|log| {
+                let block = log.block_number.unwrap_or(0);
+                log.inner.address == address && block >= from_block && block <= to_block
+            })
+            .cloned()
+            .collect())
+    }
+}
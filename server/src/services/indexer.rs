@@ -0,0 +1,313 @@
+use super::chain::OpenQuestEvents;
+use super::chain_registry::{ChainRegistry, RegisteredChain};
+use super::quiz_services::decode_quiz_response;
+use super::settlement_queue::record_settlement_result;
+use super::storage::Storage;
+use crate::models::event_model::{ContractEvent, ContractEventData, IndexerCursor};
+use alloy::{
+    primitives::{keccak256, Address},
+    rpc::types::Log,
+    sol_types::SolEvent,
+};
+use std::env;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::sleep;
+
+use OpenQuestEvents::{
+    PtotocolCreated, QuizCreated, ResultReceived, RewardsDistributed, StaffAdded, StaffRemoved,
+    TriviaCanceled,
+};
+
+/// Settings read from the environment once at startup.
+#[derive(Debug, Clone)]
+pub struct IndexerConfig {
+    pub start_block: u64,
+    pub replay_from: Option<u64>,
+    pub confirmations: u64,
+    pub batch_blocks: u64,
+    pub poll_interval: u64,
+}
+
+impl IndexerConfig {
+    pub fn from_env() -> Self {
+        fn read<T: FromStr>(name: &str) -> Option<T> {
+            env::var(name)
+                .ok()
+                .and_then(|value| value.parse::<T>().ok())
+        }
+        IndexerConfig {
+            start_block: read("INDEXER_START_BLOCK").unwrap_or(0),
+            replay_from: read("INDEXER_REPLAY_FROM"),
+            confirmations: read("INDEXER_CONFIRMATIONS").unwrap_or(2),
+            batch_blocks: read::<u64>("INDEXER_BATCH_BLOCKS").unwrap_or(2000).max(1),
+            poll_interval: read("INDEXER_POLL_SECS").unwrap_or(15),
+        }
+    }
+}
+
+/// Follows the logs of each enabled chain's factory and every protocol contract deployed
+/// on it, saving each decoded event and a per-contract block cursor so indexing resumes
+/// where it stopped.
+///
+/// Setting `INDEXER_REPLAY_FROM` rewinds every cursor to that block on startup; saved
+/// events are keyed by transaction and log index, so replaying history is safe.
+pub async fn run_indexer(db: Arc<dyn Storage>, registry: Arc<ChainRegistry>) {
+    let config = IndexerConfig::from_env();
+    let chain_ids: Vec<u64> = registry
+        .chains()
+        .iter()
+        .filter(|chain| chain.enabled)
+        .map(|chain| chain.config.chain_id)
+        .collect();
+    // Chains that have not yet completed their replay pass.
+    let mut replaying: Vec<u64> = match config.replay_from {
+        Some(_) => chain_ids.clone(),
+        None => Vec::new(),
+    };
+
+    loop {
+        for chain_id in chain_ids.iter() {
+            let replay_from = config.replay_from.filter(|_| replaying.contains(chain_id));
+            match index_new_blocks(&db, &registry, *chain_id, &config, replay_from).await {
+                Ok(()) => replaying.retain(|id| id != chain_id),
+                Err(err) => println!("Indexer could not read chain {}: {}", chain_id, err),
+            }
+        }
+        sleep(Duration::from_secs(config.poll_interval)).await;
+    }
+}
+
+/// Indexes every watched contract on `chain_id` up to the last confirmed block. Errors
+/// on a single contract are logged and left for the next pass.
+pub async fn index_new_blocks(
+    db: &Arc<dyn Storage>,
+    registry: &ChainRegistry,
+    chain_id: u64,
+    config: &IndexerConfig,
+    replay_from: Option<u64>,
+) -> Result<(), String> {
+    let chain = registry.resolve(Some(chain_id))?;
+    let head = chain.client.block_number().await?;
+    let safe_head = head.saturating_sub(config.confirmations);
+    for contract in watched_contracts(db, registry, chain).await {
+        if let Err(err) = index_contract(
+            db,
+            registry,
+            chain,
+            config,
+            &contract,
+            safe_head,
+            replay_from,
+        )
+        .await
+        {
+            println!("Error indexing {} on chain {}: {}", contract, chain_id, err);
+        }
+    }
+    Ok(())
+}
+
+/// The chain's factory plus every protocol deployed on it, lowercased.
+async fn watched_contracts(
+    db: &Arc<dyn Storage>,
+    registry: &ChainRegistry,
+    chain: &RegisteredChain,
+) -> Vec<String> {
+    let mut contracts: Vec<String> = Vec::new();
+    if let Some(factory) = chain.config.factory_address.as_ref() {
+        contracts.push(factory.to_lowercase());
+    }
+    for protocol in db.get_all_protocols().await.unwrap_or(Vec::new()) {
+        if !on_chain(registry, protocol.chain_id, chain.config.chain_id) {
+            continue;
+        }
+        let address = protocol.contract_address.trim().to_lowercase();
+        if Address::from_str(&address).is_ok() && !contracts.contains(&address) {
+            contracts.push(address);
+        }
+    }
+    contracts
+}
+
+/// Whether a protocol recorded with `protocol_chain_id` lives on `chain_id`.
+fn on_chain(registry: &ChainRegistry, protocol_chain_id: Option<u64>, chain_id: u64) -> bool {
+    protocol_chain_id.or(registry.default_chain_id()) == Some(chain_id)
+}
+
+async fn index_contract(
+    db: &Arc<dyn Storage>,
+    registry: &ChainRegistry,
+    chain: &RegisteredChain,
+    config: &IndexerConfig,
+    contract: &str,
+    safe_head: u64,
+    replay_from: Option<u64>,
+) -> Result<(), String> {
+    let chain_id = chain.config.chain_id;
+    let mut next_block = match db.get_indexer_cursor(chain_id, contract.to_string()).await {
+        Ok(cursor) => cursor.next_block.max(0) as u64,
+        Err(err) if err.error_code == 404 => config.start_block,
+        Err(err) => return Err(err.message),
+    };
+    if let Some(replay_from) = replay_from {
+        next_block = next_block.min(replay_from);
+    }
+
+    while next_block <= safe_head {
+        let to_block = (next_block + config.batch_blocks - 1).min(safe_head);
+        let logs = chain.client.logs(contract, next_block, to_block).await?;
+
+        for log in logs.iter() {
+            let Some(event) = decode_event(chain_id, contract, log) else {
+                continue;
+            };
+            react_to_event(db, registry, &event).await;
+            db.save_contract_event(event).await.map_err(|e| e.message)?;
+        }
+
+        // The cursor only moves once every log in the batch is saved, so a crash mid-batch
+        // replays that batch instead of skipping it.
+        next_block = to_block + 1;
+        db.save_indexer_cursor(IndexerCursor {
+            chain_id: Some(chain_id),
+            contract_address: contract.to_string(),
+            next_block: next_block as i64,
+            updated_at: chrono::Utc::now().timestamp(),
+        })
+        .await
+        .map_err(|e| e.message)?;
+    }
+    Ok(())
+}
+
+fn decode_event(chain_id: u64, contract: &str, log: &Log) -> Option<ContractEvent> {
+    let topic = *log.topic0()?;
+    let data = if topic == ResultReceived::SIGNATURE_HASH {
+        let event = log.log_decode::<ResultReceived>().ok()?.inner.data;
+        let target_uuid = decode_quiz_response(&event.data)
+            .ok()
+            .map(|response| response.uuid);
+        ContractEventData::ResultReceived {
+            target_uuid,
+            data: event.data.to_string(),
+        }
+    } else if topic == QuizCreated::SIGNATURE_HASH {
+        let event = log.log_decode::<QuizCreated>().ok()?.inner.data;
+        ContractEventData::QuizCreated {
+            by: event.by.to_string(),
+            quiz_id_hash: event.quiz_id.to_string(),
+        }
+    } else if topic == RewardsDistributed::SIGNATURE_HASH {
+        let event = log.log_decode::<RewardsDistributed>().ok()?.inner.data;
+        ContractEventData::RewardsDistributed {
+            winners: event.winners.iter().map(|w| w.to_string()).collect(),
+            reward_per_winner: event.rewardPerWinner.to_string(),
+            time: event.time.to_string(),
+        }
+    } else if topic == TriviaCanceled::SIGNATURE_HASH {
+        let event = log.log_decode::<TriviaCanceled>().ok()?.inner.data;
+        ContractEventData::TriviaCanceled {
+            admin: event.admin.to_string(),
+            reason: event.reason,
+            time: event.time.to_string(),
+        }
+    } else if topic == StaffAdded::SIGNATURE_HASH {
+        let event = log.log_decode::<StaffAdded>().ok()?.inner.data;
+        ContractEventData::StaffAdded {
+            admin: event.admin.to_string(),
+            staff: event.staff.to_string(),
+            time: event.time.to_string(),
+        }
+    } else if topic == StaffRemoved::SIGNATURE_HASH {
+        let event = log.log_decode::<StaffRemoved>().ok()?.inner.data;
+        ContractEventData::StaffRemoved {
+            admin: event.admin.to_string(),
+            staff: event.staff.to_string(),
+            time: event.time.to_string(),
+        }
+    } else if topic == PtotocolCreated::SIGNATURE_HASH {
+        let event = log.log_decode::<PtotocolCreated>().ok()?.inner.data;
+        ContractEventData::ProtocolCreated {
+            admin: event.admin.to_string(),
+            time: event.time.to_string(),
+            protocol_id_hash: event.protocol_id.to_string(),
+            protocol_contract: event.protocolContract.to_string(),
+        }
+    } else {
+        return None;
+    };
+
+    let tx_hash = log.transaction_hash?.to_string();
+    let log_index = log.log_index? as i64;
+    Some(ContractEvent {
+        id: ContractEvent::event_id(&tx_hash, log_index),
+        chain_id: Some(chain_id),
+        contract_address: contract.to_string(),
+        block_number: log.block_number? as i64,
+        tx_hash,
+        log_index,
+        data,
+        indexed_at: chrono::Utc::now().timestamp(),
+    })
+}
+
+/// Side effects of an event on the rest of the system. Each one is idempotent so a
+/// replayed event changes nothing the first pass did not.
+async fn react_to_event(db: &Arc<dyn Storage>, registry: &ChainRegistry, event: &ContractEvent) {
+    match &event.data {
+        ContractEventData::ResultReceived { data, .. } => {
+            let decoded = hex::decode(data.trim_start_matches("0x"))
+                .map_err(|e| e.to_string())
+                .and_then(|bytes| decode_quiz_response(&bytes));
+            match decoded {
+                Ok(response) => {
+                    let recorded = record_settlement_result(db, registry, event, response).await;
+                    if let Err(err) = recorded {
+                        println!("Error recording result from {}: {}", event.id, err);
+                    }
+                }
+                Err(err) => println!("Undecodable result in {}: {}", event.id, err),
+            }
+        }
+        ContractEventData::ProtocolCreated {
+            admin,
+            protocol_id_hash,
+            protocol_contract,
+            ..
+        } => {
+            // Protocol contracts never emit this event, but only the factory is trusted with
+            // it, and only for deployments the server sent: anyone can reuse an id.
+            let Some(chain) = event.chain_id.and_then(|chain_id| registry.get(chain_id)) else {
+                return;
+            };
+            let from_factory = chain
+                .config
+                .factory_address
+                .as_ref()
+                .is_some_and(|factory| factory.eq_ignore_ascii_case(&event.contract_address));
+            if !from_factory || !chain.client.deployed_by_server(admin) {
+                return;
+            }
+            // `protocol_id` is an indexed string, so only its hash is in the log.
+            let protocols = db.get_all_protocols().await.unwrap_or(Vec::new());
+            let Some(mut protocol) = protocols.into_iter().find(|protocol| {
+                keccak256(protocol.protocol_uuid.as_bytes()).to_string() == *protocol_id_hash
+                    && event
+                        .chain_id
+                        .is_some_and(|chain_id| on_chain(registry, protocol.chain_id, chain_id))
+            }) else {
+                return;
+            };
+            if Address::from_str(protocol.contract_address.trim()).is_ok() {
+                return;
+            }
+            protocol.contract_address = protocol_contract.clone();
+            if let Err(err) = db.update_protocol(protocol).await {
+                println!("Error saving protocol contract address: {}", err.message);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,81 @@
+use super::quiz_services::decode_quiz_response;
+use alloy::transports::http::reqwest;
+use openquest_types::{encode_payload, GradingPayload, QuizResponse, RejectionReport};
+use serde::Deserialize;
+use std::env;
+
+/// Sends inspect requests to the Coprocessor program at `COPROCESSOR_INSPECT_URL`, the
+/// `/inspect` endpoint of the node running it.
+#[derive(Debug, Clone)]
+pub struct InspectClient {
+    url: Option<String>,
+    http: reqwest::Client,
+}
+
+#[derive(Debug, Deserialize)]
+struct InspectResponse {
+    status: String,
+    #[serde(default)]
+    reports: Vec<InspectReport>,
+}
+
+#[derive(Debug, Deserialize)]
+struct InspectReport {
+    payload: String,
+}
+
+impl InspectClient {
+    pub fn new(url: Option<String>) -> Self {
+        InspectClient {
+            url,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        InspectClient::new(env::var("COPROCESSOR_INSPECT_URL").ok())
+    }
+
+    pub fn is_configured(&self) -> bool {
+        self.url.is_some()
+    }
+
+    /// Has the program grade `payload` without settling it, and returns the response it
+    /// would publish as its notice for a `gradeQuiz` call with the same payload.
+    pub async fn preview_grading(&self, payload: &GradingPayload) -> Result<QuizResponse, String> {
+        let url = self
+            .url
+            .as_ref()
+            .ok_or("COPROCESSOR_INSPECT_URL is not set")?;
+        let body = encode_payload(payload).map_err(|e| e.to_string())?;
+        let response = self
+            .http
+            .post(url)
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| format!("Inspect request failed: {}", e))?;
+        if !response.status().is_success() {
+            return Err(format!("Inspect request answered {}", response.status()));
+        }
+        let bytes = response.bytes().await.map_err(|e| e.to_string())?;
+        let inspected: InspectResponse = serde_json::from_slice(&bytes)
+            .map_err(|e| format!("Unexpected inspect response: {}", e))?;
+
+        let report = inspected
+            .reports
+            .first()
+            .ok_or_else(|| format!("Inspect returned no report ({})", inspected.status))?;
+        let report = hex::decode(report.payload.trim_start_matches("0x"))
+            .map_err(|e| format!("Inspect report is not hex: {}", e))?;
+        if inspected.status != "Accepted" {
+            // A rejected inspect reports why.
+            let reason = match RejectionReport::decode(&report) {
+                Ok(rejection) => rejection.to_string(),
+                Err(_) => String::from_utf8_lossy(&report).to_string(),
+            };
+            return Err(format!("Coprocessor rejected the payload: {}", reason));
+        }
+        decode_quiz_response(&report)
+    }
+}
@@ -0,0 +1,508 @@
+use crate::models::event_model::{ContractEvent, IndexerCursor};
+use crate::models::hackathon_model::Hackathon;
+use crate::models::protocol_model::Protocol;
+use crate::models::quiz_model::{Participant, Quiz};
+use crate::models::settlement_model::SettlementJob;
+use crate::models::transaction_model::{ChainTransaction, TransactionPurpose};
+use crate::models::user_model::{SimpleUserStruct, User, Wallet};
+use crate::models::wallet_challenge_model::{ChallengePurpose, WalletChallenge};
+use crate::services::storage::{DatabaseResponse, Storage};
+use async_trait::async_trait;
+use std::sync::RwLock;
+
+/// A `Storage` backend that lives entirely in process memory.
+///
+/// It mirrors the lookups `Database` performs against MongoDB (including the
+/// case-insensitive ones) so handlers behave the same in tests and local demos.
+#[derive(Debug, Default)]
+pub struct MemoryDatabase {
+    users: RwLock<Vec<User>>,
+    protocols: RwLock<Vec<Protocol>>,
+    quizes: RwLock<Vec<Quiz>>,
+    hackathons: RwLock<Vec<Hackathon>>,
+    settlement_jobs: RwLock<Vec<SettlementJob>>,
+    events: RwLock<Vec<ContractEvent>>,
+    indexer_cursors: RwLock<Vec<IndexerCursor>>,
+    transactions: RwLock<Vec<ChainTransaction>>,
+    wallet_challenges: RwLock<Vec<WalletChallenge>>,
+}
+
+impl MemoryDatabase {
+    pub fn new() -> Self {
+        MemoryDatabase::default()
+    }
+}
+
+fn lock_error() -> DatabaseResponse {
+    DatabaseResponse::new(500, "In-memory store lock poisoned".to_string())
+}
+
+#[async_trait]
+impl Storage for MemoryDatabase {
+    async fn create_user(&self, user: User) -> Result<(), DatabaseResponse> {
+        let mut users = self.users.write().map_err(|_| lock_error())?;
+        users.push(user);
+        Ok(())
+    }
+
+    async fn get_all_users(&self) -> Result<Vec<SimpleUserStruct>, DatabaseResponse> {
+        let users = self.users.read().map_err(|_| lock_error())?;
+        if users.is_empty() {
+            return Err(DatabaseResponse::new(
+                404,
+                "No users were found".to_string(),
+            ));
+        }
+        Ok(users.iter().map(|user| user.display()).collect())
+    }
+
+    async fn get_user_via_email(&self, email: String) -> Result<User, DatabaseResponse> {
+        let users = self.users.read().map_err(|_| lock_error())?;
+        users
+            .iter()
+            .find(|user| {
+                user.email
+                    .as_ref()
+                    .is_some_and(|stored| stored.email == email)
+            })
+            .cloned()
+            .ok_or_else(|| DatabaseResponse::new(404, "User not found".to_string()))
+    }
+
+    async fn get_user_via_address(&self, address: String) -> Result<User, DatabaseResponse> {
+        let users = self.users.read().map_err(|_| lock_error())?;
+        users
+            .iter()
+            .find(|user| {
+                user.wallet
+                    .wallet_address
+                    .as_ref()
+                    .is_some_and(|wallet| wallet.eq_ignore_ascii_case(&address))
+            })
+            .cloned()
+            .ok_or_else(|| DatabaseResponse::new(404, "User not found".to_string()))
+    }
+
+    async fn get_user_via_uuid(&self, uuid: String) -> Result<User, DatabaseResponse> {
+        let users = self.users.read().map_err(|_| lock_error())?;
+        users
+            .iter()
+            .find(|user| user.user_uuid == uuid)
+            .cloned()
+            .ok_or_else(|| DatabaseResponse::new(404, "User not found".to_string()))
+    }
+
+    async fn update_user_wallet(
+        &self,
+        uuid: String,
+        wallet_struct: Wallet,
+    ) -> Result<(), DatabaseResponse> {
+        let mut users = self.users.write().map_err(|_| lock_error())?;
+        if let Some(user) = users.iter_mut().find(|user| user.user_uuid == uuid) {
+            user.wallet = wallet_struct;
+        }
+        Ok(())
+    }
+
+    async fn update_user(&self, user: User) -> Result<User, DatabaseResponse> {
+        let mut users = self.users.write().map_err(|_| lock_error())?;
+        match users.iter_mut().find(|u| u.user_uuid == user.user_uuid) {
+            Some(existing) => {
+                *existing = user.clone();
+                Ok(user)
+            }
+            None => Err(DatabaseResponse::new(404, "User not found".to_string())),
+        }
+    }
+
+    async fn create_wallet_challenge(
+        &self,
+        challenge: WalletChallenge,
+    ) -> Result<(), DatabaseResponse> {
+        let mut challenges = self.wallet_challenges.write().map_err(|_| lock_error())?;
+        challenges.push(challenge);
+        Ok(())
+    }
+
+    async fn consume_wallet_challenge(
+        &self,
+        nonce: String,
+        purpose: ChallengePurpose,
+        user_uuid: Option<String>,
+    ) -> Result<WalletChallenge, DatabaseResponse> {
+        let mut challenges = self.wallet_challenges.write().map_err(|_| lock_error())?;
+        match challenges.iter_mut().find(|challenge| {
+            challenge.nonce == nonce
+                && challenge.purpose == purpose
+                && challenge.user_uuid == user_uuid
+                && !challenge.used
+        }) {
+            Some(challenge) => {
+                challenge.used = true;
+                Ok(challenge.clone())
+            }
+            None => Err(DatabaseResponse::new(
+                404,
+                "Wallet challenge not found".to_string(),
+            )),
+        }
+    }
+
+    async fn get_protocol_via_name(&self, name: String) -> Result<Protocol, DatabaseResponse> {
+        let protocols = self.protocols.read().map_err(|_| lock_error())?;
+        protocols
+            .iter()
+            .find(|protocol| protocol.name == name)
+            .cloned()
+            .ok_or_else(|| DatabaseResponse::new(404, "Protocol not found".to_string()))
+    }
+
+    async fn get_protocol_via_id(&self, id: String) -> Result<Protocol, DatabaseResponse> {
+        let protocols = self.protocols.read().map_err(|_| lock_error())?;
+        protocols
+            .iter()
+            .find(|protocol| protocol.protocol_uuid.eq_ignore_ascii_case(&id))
+            .cloned()
+            .ok_or_else(|| DatabaseResponse::new(404, "Protocol not found".to_string()))
+    }
+
+    async fn create_protocol(&self, protocol: Protocol) -> Result<(), DatabaseResponse> {
+        let mut protocols = self.protocols.write().map_err(|_| lock_error())?;
+        protocols.push(protocol);
+        Ok(())
+    }
+
+    async fn update_protocol_team(&self, protocol: Protocol) -> Result<(), DatabaseResponse> {
+        let mut protocols = self.protocols.write().map_err(|_| lock_error())?;
+        if let Some(existing) = protocols.iter_mut().find(|p| p.name == protocol.name) {
+            existing.staffs = protocol.staffs;
+        }
+        Ok(())
+    }
+
+    async fn get_all_protocols(&self) -> Result<Vec<Protocol>, DatabaseResponse> {
+        let protocols = self.protocols.read().map_err(|_| lock_error())?;
+        if protocols.is_empty() {
+            return Err(DatabaseResponse::new(
+                404,
+                "No protocols were found".to_string(),
+            ));
+        }
+        Ok(protocols.clone())
+    }
+
+    async fn update_protocol(&self, protocol: Protocol) -> Result<Protocol, DatabaseResponse> {
+        let mut protocols = self.protocols.write().map_err(|_| lock_error())?;
+        match protocols.iter_mut().find(|p| p.name == protocol.name) {
+            Some(existing) => {
+                *existing = protocol.clone();
+                Ok(protocol)
+            }
+            None => Err(DatabaseResponse::new(404, "Protocol not found".to_string())),
+        }
+    }
+
+    async fn add_quiz(&self, quiz: Quiz) -> Result<(), DatabaseResponse> {
+        let mut quizes = self.quizes.write().map_err(|_| lock_error())?;
+        quizes.push(quiz);
+        Ok(())
+    }
+
+    async fn get_quiz_via_uuid(&self, uuid: String) -> Result<Quiz, DatabaseResponse> {
+        let quizes = self.quizes.read().map_err(|_| lock_error())?;
+        quizes
+            .iter()
+            .find(|quiz| quiz.uuid.eq_ignore_ascii_case(&uuid))
+            .cloned()
+            .ok_or_else(|| DatabaseResponse::new(404, "Quiz not found".to_string()))
+    }
+
+    async fn get_quiz_participant_via_uuid(
+        &self,
+        quiz_uuid: String,
+        participant_uuid: String,
+    ) -> Result<Participant, DatabaseResponse> {
+        let quizes = self.quizes.read().map_err(|_| lock_error())?;
+        let quiz = quizes
+            .iter()
+            .find(|quiz| quiz.uuid == quiz_uuid)
+            .ok_or_else(|| DatabaseResponse::new(404, "Quiz not found".to_string()))?;
+        quiz.participants
+            .iter()
+            .find(|p| p.user_uuid == participant_uuid)
+            .cloned()
+            .ok_or_else(|| {
+                DatabaseResponse::new(
+                    404,
+                    format!(
+                        "Participant not found in quiz with UUID: {}",
+                        participant_uuid
+                    ),
+                )
+            })
+    }
+
+    async fn update_quiz(&self, quiz: Quiz) -> Result<Quiz, DatabaseResponse> {
+        let mut quizes = self.quizes.write().map_err(|_| lock_error())?;
+        match quizes.iter_mut().find(|q| q.uuid == quiz.uuid) {
+            Some(existing) => {
+                *existing = quiz.clone();
+                Ok(quiz)
+            }
+            None => Err(DatabaseResponse::new(404, "Quiz not found".to_string())),
+        }
+    }
+
+    async fn get_all_quizes(&self) -> Result<Vec<Quiz>, DatabaseResponse> {
+        let quizes = self.quizes.read().map_err(|_| lock_error())?;
+        if quizes.is_empty() {
+            return Err(DatabaseResponse::new(404, "No Quiz were found".to_string()));
+        }
+        Ok(quizes.clone())
+    }
+
+    async fn create_hackathon(&self, hackathon: Hackathon) -> Result<(), DatabaseResponse> {
+        let mut hackathons = self.hackathons.write().map_err(|_| lock_error())?;
+        hackathons.push(hackathon);
+        Ok(())
+    }
+
+    async fn get_hackathon_via_uuid(&self, uuid: String) -> Result<Hackathon, DatabaseResponse> {
+        let hackathons = self.hackathons.read().map_err(|_| lock_error())?;
+        hackathons
+            .iter()
+            .find(|hackathon| hackathon.uuid.eq_ignore_ascii_case(&uuid))
+            .cloned()
+            .ok_or_else(|| DatabaseResponse::new(404, "Hackathon not found".to_string()))
+    }
+
+    async fn update_hackathon(&self, hackathon: Hackathon) -> Result<Hackathon, DatabaseResponse> {
+        let mut hackathons = self.hackathons.write().map_err(|_| lock_error())?;
+        match hackathons.iter_mut().find(|h| h.uuid == hackathon.uuid) {
+            Some(existing) => {
+                *existing = hackathon.clone();
+                Ok(hackathon)
+            }
+            None => Err(DatabaseResponse::new(
+                404,
+                "Hackathon not found".to_string(),
+            )),
+        }
+    }
+
+    async fn get_all_hackathons(&self) -> Result<Vec<Hackathon>, DatabaseResponse> {
+        let hackathons = self.hackathons.read().map_err(|_| lock_error())?;
+        if hackathons.is_empty() {
+            return Err(DatabaseResponse::new(
+                404,
+                "No hackathons were found".to_string(),
+            ));
+        }
+        Ok(hackathons.clone())
+    }
+
+    async fn create_settlement_job(&self, job: SettlementJob) -> Result<(), DatabaseResponse> {
+        let mut jobs = self.settlement_jobs.write().map_err(|_| lock_error())?;
+        jobs.push(job);
+        Ok(())
+    }
+
+    async fn get_settlement_job_via_target(
+        &self,
+        target_uuid: String,
+    ) -> Result<SettlementJob, DatabaseResponse> {
+        let jobs = self.settlement_jobs.read().map_err(|_| lock_error())?;
+        jobs.iter()
+            .find(|job| job.target_uuid == target_uuid)
+            .cloned()
+            .ok_or_else(|| DatabaseResponse::new(404, "Settlement job not found".to_string()))
+    }
+
+    async fn get_due_settlement_jobs(
+        &self,
+        now: i64,
+    ) -> Result<Vec<SettlementJob>, DatabaseResponse> {
+        let jobs = self.settlement_jobs.read().map_err(|_| lock_error())?;
+        let mut due: Vec<SettlementJob> = jobs
+            .iter()
+            .filter(|job| !job.stage.is_terminal() && job.next_attempt_at <= now)
+            .cloned()
+            .collect();
+        due.sort_by_key(|job| job.next_attempt_at);
+        Ok(due)
+    }
+
+    async fn update_settlement_job(
+        &self,
+        job: SettlementJob,
+    ) -> Result<SettlementJob, DatabaseResponse> {
+        let mut jobs = self.settlement_jobs.write().map_err(|_| lock_error())?;
+        match jobs.iter_mut().find(|j| j.uuid == job.uuid) {
+            Some(existing) => {
+                *existing = job.clone();
+                Ok(job)
+            }
+            None => Err(DatabaseResponse::new(
+                404,
+                "Settlement job not found".to_string(),
+            )),
+        }
+    }
+
+    async fn save_contract_event(&self, event: ContractEvent) -> Result<(), DatabaseResponse> {
+        let mut events = self.events.write().map_err(|_| lock_error())?;
+        match events.iter_mut().find(|e| e.id == event.id) {
+            Some(existing) => *existing = event,
+            None => events.push(event),
+        }
+        Ok(())
+    }
+
+    async fn get_contract_events(
+        &self,
+        contract_address: String,
+    ) -> Result<Vec<ContractEvent>, DatabaseResponse> {
+        let events = self.events.read().map_err(|_| lock_error())?;
+        let mut found: Vec<ContractEvent> = events
+            .iter()
+            .filter(|event| {
+                event
+                    .contract_address
+                    .eq_ignore_ascii_case(&contract_address)
+            })
+            .cloned()
+            .collect();
+        if found.is_empty() {
+            return Err(DatabaseResponse::new(
+                404,
+                "No events were found".to_string(),
+            ));
+        }
+        found.sort_by_key(|event| (event.block_number, event.log_index));
+        Ok(found)
+    }
+
+    async fn get_indexer_cursor(
+        &self,
+        chain_id: u64,
+        contract_address: String,
+    ) -> Result<IndexerCursor, DatabaseResponse> {
+        let cursors = self.indexer_cursors.read().map_err(|_| lock_error())?;
+        cursors
+            .iter()
+            .find(|cursor| {
+                cursor.chain_id == Some(chain_id)
+                    && cursor
+                        .contract_address
+                        .eq_ignore_ascii_case(&contract_address)
+            })
+            .cloned()
+            .ok_or_else(|| DatabaseResponse::new(404, "Indexer cursor not found".to_string()))
+    }
+
+    async fn save_indexer_cursor(&self, cursor: IndexerCursor) -> Result<(), DatabaseResponse> {
+        let mut cursors = self.indexer_cursors.write().map_err(|_| lock_error())?;
+        match cursors.iter_mut().find(|c| {
+            c.chain_id == cursor.chain_id && c.contract_address == cursor.contract_address
+        }) {
+            Some(existing) => *existing = cursor,
+            None => cursors.push(cursor),
+        }
+        Ok(())
+    }
+
+    async fn create_transaction(&self, tx: ChainTransaction) -> Result<(), DatabaseResponse> {
+        let mut transactions = self.transactions.write().map_err(|_| lock_error())?;
+        transactions.push(tx);
+        Ok(())
+    }
+
+    async fn update_transaction(
+        &self,
+        tx: ChainTransaction,
+    ) -> Result<ChainTransaction, DatabaseResponse> {
+        let mut transactions = self.transactions.write().map_err(|_| lock_error())?;
+        match transactions.iter_mut().find(|t| t.uuid == tx.uuid) {
+            Some(existing) => {
+                *existing = tx.clone();
+                Ok(tx)
+            }
+            None => Err(DatabaseResponse::new(
+                404,
+                "Transaction not found".to_string(),
+            )),
+        }
+    }
+
+    async fn get_transaction_via_hash(
+        &self,
+        tx_hash: String,
+    ) -> Result<ChainTransaction, DatabaseResponse> {
+        let transactions = self.transactions.read().map_err(|_| lock_error())?;
+        transactions
+            .iter()
+            .find(|tx| {
+                tx.hashes()
+                    .iter()
+                    .any(|hash| hash.eq_ignore_ascii_case(&tx_hash))
+            })
+            .cloned()
+            .ok_or_else(|| DatabaseResponse::new(404, "Transaction not found".to_string()))
+    }
+
+    async fn get_transactions_via_target(
+        &self,
+        target_uuid: String,
+    ) -> Result<Vec<ChainTransaction>, DatabaseResponse> {
+        let transactions = self.transactions.read().map_err(|_| lock_error())?;
+        let mut found: Vec<ChainTransaction> = transactions
+            .iter()
+            .filter(|tx| tx.target_uuid == target_uuid)
+            .cloned()
+            .collect();
+        found.sort_by_key(|tx| tx.created_at);
+        Ok(found)
+    }
+
+    async fn get_transactions_via_protocol(
+        &self,
+        protocol: String,
+    ) -> Result<Vec<ChainTransaction>, DatabaseResponse> {
+        let transactions = self.transactions.read().map_err(|_| lock_error())?;
+        let mut found: Vec<ChainTransaction> = transactions
+            .iter()
+            .filter(|tx| tx.protocol == protocol)
+            .cloned()
+            .collect();
+        found.sort_by_key(|tx| tx.created_at);
+        Ok(found)
+    }
+
+    async fn get_transactions_via_purpose(
+        &self,
+        purpose: TransactionPurpose,
+    ) -> Result<Vec<ChainTransaction>, DatabaseResponse> {
+        let transactions = self.transactions.read().map_err(|_| lock_error())?;
+        let mut found: Vec<ChainTransaction> = transactions
+            .iter()
+            .filter(|tx| tx.purpose == purpose)
+            .cloned()
+            .collect();
+        found.sort_by_key(|tx| tx.created_at);
+        Ok(found)
+    }
+
+    async fn get_open_transactions(
+        &self,
+        chain_id: u64,
+    ) -> Result<Vec<ChainTransaction>, DatabaseResponse> {
+        let transactions = self.transactions.read().map_err(|_| lock_error())?;
+        let mut open: Vec<ChainTransaction> = transactions
+            .iter()
+            .filter(|tx| tx.chain_id == Some(chain_id) && !tx.status.is_final())
+            .cloned()
+            .collect();
+        open.sort_by_key(|tx| tx.nonce);
+        Ok(open)
+    }
+}
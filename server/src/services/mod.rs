@@ -1,2 +1,16 @@
+pub mod alloy_chain;
+pub mod blob_store;
+pub mod chain;
+pub mod chain_registry;
 pub mod db;
+pub mod fake_chain;
+pub mod indexer;
+pub mod inspect;
+pub mod memory_db;
+pub mod protocol_reconciler;
 pub mod quiz_services;
+pub mod settlement_queue;
+pub mod signer;
+pub mod siwe;
+pub mod storage;
+pub mod tx_manager;
@@ -0,0 +1,274 @@
+use super::chain::DeployedProtocol;
+use super::chain_registry::ChainRegistry;
+use super::storage::Storage;
+use crate::models::{protocol_model::Protocol, transaction_model::TransactionPurpose};
+use serde::Serialize;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Default)]
+pub struct ReconcileOptions {
+    /// Report what would change without saving anything.
+    pub dry_run: bool,
+    /// User who becomes the owner of backfilled protocols. Without one, protocols that
+    /// are deployed but missing from the database are only reported.
+    pub owner_uuid: Option<String>,
+    /// Protocol ids to look up on the factory besides those found in sent transactions.
+    pub protocol_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "action")]
+pub enum ReconcileAction {
+    /// The stored address matches the factory.
+    Unchanged,
+    /// The stored address was missing or wrong and now matches the factory.
+    Repaired {
+        previous: String,
+    },
+    /// Deployed but never saved; a protocol record was created for it.
+    Backfilled,
+    /// Deployed but never saved, and no owner was given to backfill it with.
+    Orphaned,
+    /// The factory has no contract for this protocol.
+    NotDeployed,
+    /// The factory has a contract for this protocol that the server cannot tell it
+    /// deployed; it is reported but never saved.
+    Unverified {
+        reason: String,
+    },
+    Failed {
+        error: String,
+    },
+}
+
+/// What reconciliation found for one protocol.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReconcileOutcome {
+    pub protocol_id: String,
+    pub name: Option<String>,
+    pub chain_id: Option<u64>,
+    pub contract_address: Option<String>,
+    #[serde(flatten)]
+    pub action: ReconcileAction,
+}
+
+/// Compares every protocol's `contract_address` with the factory on its chain and repairs
+/// the ones that differ, then backfills protocols that were deployed by a
+/// `createProtocol` transaction (or listed in `options.protocol_ids`) but never saved.
+pub async fn reconcile_protocols(
+    db: &Arc<dyn Storage>,
+    registry: &ChainRegistry,
+    options: &ReconcileOptions,
+) -> Result<Vec<ReconcileOutcome>, String> {
+    if let Some(owner_uuid) = options.owner_uuid.as_ref() {
+        db.get_user_via_uuid(owner_uuid.clone())
+            .await
+            .map_err(|e| format!("Owner {}: {}", owner_uuid, e.message))?;
+    }
+    let protocols = match db.get_all_protocols().await {
+        Ok(protocols) => protocols,
+        Err(e) if e.error_code == 404 => Vec::new(),
+        Err(e) => return Err(e.message),
+    };
+
+    let mut outcomes = Vec::new();
+    for protocol in protocols.iter() {
+        outcomes.push(reconcile_saved(db, registry, options, protocol.clone()).await);
+    }
+
+    // Ids the server sent `createProtocol` for, with the chain each was sent on.
+    let mut candidates: Vec<(String, Option<u64>)> = Vec::new();
+    for tx in db
+        .get_transactions_via_purpose(TransactionPurpose::CreateProtocol)
+        .await
+        .map_err(|e| e.message)?
+    {
+        candidates.push((tx.target_uuid, tx.chain_id));
+    }
+    for protocol_id in options.protocol_ids.iter() {
+        candidates.push((protocol_id.clone(), None));
+    }
+    let mut seen: Vec<String> = protocols
+        .iter()
+        .map(|protocol| protocol.protocol_uuid.clone())
+        .collect();
+    for (protocol_id, chain_id) in candidates {
+        if seen.contains(&protocol_id) {
+            continue;
+        }
+        seen.push(protocol_id.clone());
+        outcomes.push(backfill(db, registry, options, &protocols, protocol_id, chain_id).await);
+    }
+    Ok(outcomes)
+}
+
+async fn reconcile_saved(
+    db: &Arc<dyn Storage>,
+    registry: &ChainRegistry,
+    options: &ReconcileOptions,
+    mut protocol: Protocol,
+) -> ReconcileOutcome {
+    let mut outcome = ReconcileOutcome {
+        protocol_id: protocol.protocol_uuid.clone(),
+        name: Some(protocol.name.clone()),
+        chain_id: protocol.chain_id,
+        contract_address: None,
+        action: ReconcileAction::Unchanged,
+    };
+    let chain = match registry.resolve(protocol.chain_id) {
+        Ok(chain) => chain,
+        Err(error) => {
+            outcome.action = ReconcileAction::Failed { error };
+            return outcome;
+        }
+    };
+    outcome.chain_id = Some(chain.config.chain_id);
+
+    // Protocols deployed before ids were passed to the factory are only known by name,
+    // which anyone can deploy under too, so a contract found that way is only reported.
+    let deployed = match chain
+        .client
+        .deployed_protocol(&protocol.protocol_uuid)
+        .await
+    {
+        Ok(Some(deployed)) if chain.client.deployed_by_server(&deployed.admin) => {
+            Ok(Some((deployed.contract_address, None)))
+        }
+        Ok(Some(deployed)) => Ok(Some((
+            deployed.contract_address,
+            Some(format!("Deployed by {}, not the server", deployed.admin)),
+        ))),
+        Ok(None) => chain
+            .client
+            .protocol_address_via_name(&protocol.name)
+            .await
+            .map(|found| {
+                found.map(|contract_address| {
+                    let reason = "Only found by name, which does not record who deployed it";
+                    (contract_address, Some(reason.to_string()))
+                })
+            }),
+        Err(error) => Err(error),
+    };
+    let (contract_address, unverified) = match deployed {
+        Ok(Some(found)) => found,
+        Ok(None) => {
+            outcome.action = ReconcileAction::NotDeployed;
+            return outcome;
+        }
+        Err(error) => {
+            outcome.action = ReconcileAction::Failed { error };
+            return outcome;
+        }
+    };
+    outcome.contract_address = Some(contract_address.clone());
+    if protocol
+        .contract_address
+        .trim()
+        .eq_ignore_ascii_case(&contract_address)
+    {
+        return outcome;
+    }
+    if let Some(reason) = unverified {
+        outcome.action = ReconcileAction::Unverified { reason };
+        return outcome;
+    }
+
+    let previous = protocol.contract_address.trim().to_string();
+    protocol.contract_address = contract_address;
+    protocol.chain_id = Some(chain.config.chain_id);
+    if !options.dry_run {
+        if let Err(e) = db.update_protocol(protocol).await {
+            outcome.action = ReconcileAction::Failed { error: e.message };
+            return outcome;
+        }
+    }
+    outcome.action = ReconcileAction::Repaired { previous };
+    outcome
+}
+
+async fn backfill(
+    db: &Arc<dyn Storage>,
+    registry: &ChainRegistry,
+    options: &ReconcileOptions,
+    protocols: &[Protocol],
+    protocol_id: String,
+    chain_id: Option<u64>,
+) -> ReconcileOutcome {
+    let mut outcome = ReconcileOutcome {
+        protocol_id: protocol_id.clone(),
+        name: None,
+        chain_id,
+        contract_address: None,
+        action: ReconcileAction::NotDeployed,
+    };
+    let (chain_id, deployed) = match find_deployment(registry, &protocol_id, chain_id).await {
+        Ok(Some(found)) => found,
+        Ok(None) => return outcome,
+        Err(error) => {
+            outcome.action = ReconcileAction::Failed { error };
+            return outcome;
+        }
+    };
+    outcome.name = Some(deployed.name.clone());
+    outcome.chain_id = Some(chain_id);
+    outcome.contract_address = Some(deployed.contract_address.clone());
+    let by_server = registry
+        .resolve(Some(chain_id))
+        .is_ok_and(|chain| chain.client.deployed_by_server(&deployed.admin));
+    if !by_server {
+        outcome.action = ReconcileAction::Unverified {
+            reason: format!("Deployed by {}, not the server", deployed.admin),
+        };
+        return outcome;
+    }
+
+    let Some(owner_uuid) = options.owner_uuid.clone() else {
+        outcome.action = ReconcileAction::Orphaned;
+        return outcome;
+    };
+    // A later registration under the same name is the protocol users already know.
+    if protocols
+        .iter()
+        .any(|protocol| protocol.name == deployed.name)
+    {
+        outcome.action = ReconcileAction::Failed {
+            error: format!("A protocol named {} already exists", deployed.name),
+        };
+        return outcome;
+    }
+
+    let mut protocol = Protocol::new(deployed.name, owner_uuid, chain_id);
+    protocol.protocol_uuid = protocol_id;
+    protocol.contract_address = deployed.contract_address;
+    if !options.dry_run {
+        if let Err(e) = db.create_protocol(protocol).await {
+            outcome.action = ReconcileAction::Failed { error: e.message };
+            return outcome;
+        }
+    }
+    outcome.action = ReconcileAction::Backfilled;
+    outcome
+}
+
+/// Looks `protocol_id` up on `chain_id`, or on every enabled chain when it is unknown.
+async fn find_deployment(
+    registry: &ChainRegistry,
+    protocol_id: &str,
+    chain_id: Option<u64>,
+) -> Result<Option<(u64, DeployedProtocol)>, String> {
+    let chains: Vec<_> = match chain_id {
+        Some(chain_id) => vec![registry.resolve(Some(chain_id))?],
+        None => registry
+            .chains()
+            .iter()
+            .filter(|chain| chain.enabled)
+            .collect(),
+    };
+    for chain in chains {
+        if let Some(deployed) = chain.client.deployed_protocol(protocol_id).await? {
+            return Ok(Some((chain.config.chain_id, deployed)));
+        }
+    }
+    Ok(None)
+}
@@ -0,0 +1,488 @@
+use super::chain::GradingSubmission;
+use super::chain_registry::ChainRegistry;
+use super::quiz_services::{
+    apply_quiz_results, compress_struct, decode_quiz_response, PayloadConfig,
+};
+use super::storage::Storage;
+use crate::models::{
+    event_model::ContractEvent,
+    hackathon_model::HackathonStatus,
+    quiz_model::{QuizAccess, ResultsTree, Status},
+    settlement_model::{RetryPolicy, SettlementJob, SettlementStage, SettlementTarget},
+    transaction_model::{TransactionPurpose, TransactionStatus},
+    user_model::RewardToken,
+};
+use alloy::primitives::Address;
+use openquest_types::{
+    validate_payload, GradingPayload, QuizResponse, RejectionReport, TokenAmount,
+};
+use std::env;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::sleep;
+
+/// Everything `gradeQuiz` needs, taken from either a quiz or a hackathon.
+struct SettlementRequest {
+    name: String,
+    total_reward: TokenAmount,
+    max_reward_per_user: TokenAmount,
+    created_by: String,
+    protocol: String,
+    access: QuizAccess,
+    end_time: i64,
+    payload: GradingPayload,
+}
+
+/// What loading a job's target turned up.
+enum LoadedTarget {
+    Ready(Box<SettlementRequest>),
+    /// Already paid out before the queue existed.
+    AlreadySettled,
+    Canceled,
+}
+
+/// Works through due settlement jobs forever. Every failure is recorded on the job and
+/// retried with backoff, so one bad quiz can neither stall nor kill the loop.
+pub async fn run_settlement_queue(db: Arc<dyn Storage>, registry: Arc<ChainRegistry>) {
+    let policy = RetryPolicy::from_env();
+    let poll_interval = env::var("SETTLEMENT_POLL_SECS")
+        .ok()
+        .and_then(|value| value.parse::<u64>().ok())
+        .unwrap_or(60);
+
+    enqueue_unsettled(&db).await;
+
+    loop {
+        settle_due_jobs(&db, &registry, &policy, chrono::Utc::now().timestamp()).await;
+        sleep(Duration::from_secs(poll_interval)).await;
+    }
+}
+
+/// Moves every job due at `now` forward by one stage.
+pub async fn settle_due_jobs(
+    db: &Arc<dyn Storage>,
+    registry: &ChainRegistry,
+    policy: &RetryPolicy,
+    now: i64,
+) {
+    match db.get_due_settlement_jobs(now).await {
+        Ok(jobs) => {
+            for job in jobs {
+                process_job(db, registry, job, policy).await;
+            }
+        }
+        Err(err) => println!("Error loading settlement jobs: {}", err.message),
+    }
+}
+
+/// Creates jobs for quizzes and hackathons saved before the queue existed.
+async fn enqueue_unsettled(db: &Arc<dyn Storage>) {
+    let mut targets = Vec::new();
+    for quiz in db.get_all_quizes().await.unwrap_or(Vec::new()) {
+        if !quiz.submited && !matches!(quiz.status, Status::Canceled) {
+            let due_at = quiz.settles_at();
+            targets.push((SettlementTarget::Quiz, quiz.uuid, due_at));
+        }
+    }
+    for hackathon in db.get_all_hackathons().await.unwrap_or(Vec::new()) {
+        if !hackathon.submited && hackathon.status == HackathonStatus::Scheduled {
+            targets.push((
+                SettlementTarget::Hackathon,
+                hackathon.uuid,
+                hackathon.judging_end,
+            ));
+        }
+    }
+
+    for (target, target_uuid, due_at) in targets {
+        match db.get_settlement_job_via_target(target_uuid.clone()).await {
+            Ok(_) => continue,
+            Err(err) if err.error_code == 404 => {
+                let job = SettlementJob::new(target, target_uuid, due_at);
+                if let Err(err) = db.create_settlement_job(job).await {
+                    println!("Error enqueueing settlement job: {}", err.message);
+                }
+            }
+            Err(err) => println!("Error checking settlement job: {}", err.message),
+        }
+    }
+}
+
+async fn process_job(
+    db: &Arc<dyn Storage>,
+    registry: &ChainRegistry,
+    mut job: SettlementJob,
+    policy: &RetryPolicy,
+) {
+    println!(
+        "Settling {:?} {} at stage {}",
+        job.target,
+        job.target_uuid,
+        job.stage.to_string()
+    );
+
+    let outcome = match job.stage {
+        SettlementStage::Queued => submit_job(db, registry, &mut job).await.map(Some),
+        SettlementStage::SubmittedOnchain | SettlementStage::AwaitingResult => {
+            poll_job(db, registry, &mut job).await
+        }
+        SettlementStage::ApplyingResults => apply_job(db, &mut job).await.map(Some),
+        SettlementStage::Settled | SettlementStage::Failed => return,
+    };
+
+    match outcome {
+        Ok(Some(stage)) => job.advance(stage),
+        Ok(None) => job.wait(policy),
+        Err(err) => {
+            println!("Settlement of {} failed: {}", job.target_uuid, err);
+            job.record_failure(err, policy);
+        }
+    }
+    if let Err(err) = db.update_settlement_job(job).await {
+        println!("Error saving settlement job: {}", err.message);
+    }
+}
+
+async fn submit_job(
+    db: &Arc<dyn Storage>,
+    registry: &ChainRegistry,
+    job: &mut SettlementJob,
+) -> Result<SettlementStage, String> {
+    let request = match load_target(db, job).await? {
+        LoadedTarget::Ready(request) => request,
+        LoadedTarget::AlreadySettled => return Ok(SettlementStage::Settled),
+        LoadedTarget::Canceled => {
+            job.stage = SettlementStage::Failed;
+            return Err("Target was canceled".to_string());
+        }
+    };
+    // The coprocessor rejects these too, but its report never reaches the chain, so the
+    // job would only wait for a result that does not come.
+    if let Err(report) = validate_payload(&request.payload) {
+        record_rejection(db, job, &report).await?;
+        job.stage = SettlementStage::Failed;
+        return Err(format!("Payload would be rejected: {}", report));
+    }
+
+    let protocol = db
+        .get_protocol_via_name(request.protocol.clone())
+        .await
+        .map_err(|e| e.message)?;
+    let contract_address = protocol.contract_address;
+    if Address::from_str(contract_address.trim()).is_err() {
+        return Err("Protocol contract is not deployed yet".to_string());
+    }
+    let chain = registry.resolve(protocol.chain_id)?;
+    let creator_wallet = db
+        .get_user_via_uuid(request.created_by.clone())
+        .await
+        .map_err(|e| e.message)?
+        .wallet
+        .wallet_address
+        .ok_or("Creator does not have a linked wallet address")?;
+    let payload = compress_struct(&request.payload, &PayloadConfig::from_env())?;
+
+    // A `gradeQuiz` sent on an earlier attempt may still be live even though that attempt
+    // failed; sending another would pay for the grading twice.
+    let open_tx = db
+        .get_transactions_via_target(job.target_uuid.clone())
+        .await
+        .map_err(|e| e.message)?
+        .into_iter()
+        .find(|tx| {
+            tx.purpose == TransactionPurpose::GradeQuiz
+                && tx.chain_id == Some(chain.config.chain_id)
+                && tx.to.eq_ignore_ascii_case(contract_address.trim())
+                && !tx.status.is_final()
+        });
+    let tx_hash = match open_tx {
+        Some(tx) => {
+            println!(
+                "Reusing open gradeQuiz transaction {} for {}",
+                tx.tx_hash, job.target_uuid
+            );
+            tx.tx_hash
+        }
+        None => {
+            chain
+                .client
+                .submit_grading(GradingSubmission {
+                    contract_address: contract_address.clone(),
+                    uuid: job.target_uuid.clone(),
+                    name: request.name,
+                    total_reward: request.total_reward,
+                    max_reward_per_user: request.max_reward_per_user,
+                    created_by: creator_wallet,
+                    protocol: request.protocol,
+                    access: request.access.to_string().to_string(),
+                    payload: payload.clone(),
+                    end_time: request.end_time,
+                })
+                .await?
+        }
+    };
+
+    job.payload = Some(payload);
+    job.contract_address = Some(contract_address);
+    job.chain_id = Some(chain.config.chain_id);
+    job.tx_hash = Some(tx_hash);
+    Ok(SettlementStage::SubmittedOnchain)
+}
+
+/// Keeps the reason a quiz cannot be graded on the quiz, where its staff can see it.
+async fn record_rejection(
+    db: &Arc<dyn Storage>,
+    job: &SettlementJob,
+    report: &RejectionReport,
+) -> Result<(), String> {
+    if job.target != SettlementTarget::Quiz {
+        return Ok(());
+    }
+    let mut quiz = db
+        .get_quiz_via_uuid(job.target_uuid.clone())
+        .await
+        .map_err(|e| e.message)?;
+    quiz.grading_rejection = Some(report.clone());
+    db.update_quiz(quiz).await.map_err(|e| e.message)?;
+    Ok(())
+}
+
+/// Hands a coprocessor result picked up by the indexer in `event` to its waiting job.
+/// Jobs that are not waiting for a result (or have already applied it) are left alone,
+/// and so are results emitted by any contract other than the one the job graded on or
+/// that do not carry the results root the contract settled the quiz with.
+pub(crate) async fn record_settlement_result(
+    db: &Arc<dyn Storage>,
+    registry: &ChainRegistry,
+    event: &ContractEvent,
+    data: QuizResponse,
+) -> Result<(), String> {
+    let mut job = match db.get_settlement_job_via_target(data.uuid.clone()).await {
+        Ok(job) => job,
+        Err(err) if err.error_code == 404 => return Ok(()),
+        Err(err) => return Err(err.message),
+    };
+    let from_job_contract = job
+        .contract_address
+        .as_deref()
+        .is_some_and(|address| address.eq_ignore_ascii_case(&event.contract_address));
+    let on_job_chain =
+        event.chain_id.is_some() && event.chain_id == job.chain_id.or(registry.default_chain_id());
+    if !from_job_contract || !on_job_chain {
+        return Err(format!(
+            "Result for {} was emitted by {} instead of the contract it was graded on",
+            data.uuid, event.contract_address
+        ));
+    }
+    // A `gradeQuiz` sent by someone else for the same uuid emits its own result, so the
+    // result is bound to the job the same way a polled notice is.
+    let settled_root = registry
+        .resolve(job.chain_id)?
+        .client
+        .settled_results_root(&event.contract_address, &data.uuid)
+        .await?;
+    if !settled_root.is_some_and(|root| carries_root(&data, &root)) {
+        return Err(format!(
+            "Result for {} does not carry the results root its contract settled",
+            data.uuid
+        ));
+    }
+    // A job can run out of attempts while its transaction is already mined; the result
+    // is still the quiz's only payout, so it is taken as long as the transaction was not
+    // lost or reverted.
+    let mined = match job.tx_hash.clone() {
+        Some(tx_hash) => db.get_transaction_via_hash(tx_hash).await.is_ok_and(|tx| {
+            matches!(
+                tx.status,
+                TransactionStatus::Mined | TransactionStatus::Confirmed
+            )
+        }),
+        None => false,
+    };
+    match job.stage {
+        SettlementStage::Failed if !mined => Ok(()),
+        SettlementStage::SubmittedOnchain
+        | SettlementStage::AwaitingResult
+        | SettlementStage::Failed => {
+            job.result = Some(data);
+            job.advance(SettlementStage::ApplyingResults);
+            db.update_settlement_job(job).await.map_err(|e| e.message)?;
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Results normally arrive through the indexer; this reads `checkQuizResponse` directly
+/// as a fallback in case the event was missed. Returns `None` while the result is still
+/// on its way, which is not counted as a failed attempt.
+async fn poll_job(
+    db: &Arc<dyn Storage>,
+    registry: &ChainRegistry,
+    job: &mut SettlementJob,
+) -> Result<Option<SettlementStage>, String> {
+    let (Some(payload), Some(contract_address)) =
+        (job.payload.clone(), job.contract_address.clone())
+    else {
+        job.stage = SettlementStage::Queued;
+        return Err("Job has no submitted payload; resubmitting".to_string());
+    };
+
+    let chain = registry.resolve(job.chain_id)?;
+    let Some(response) = chain
+        .client
+        .quiz_response(&contract_address, &payload)
+        .await?
+    else {
+        if let Some(tx_hash) = job.tx_hash.clone() {
+            let reverted = db
+                .get_transaction_via_hash(tx_hash)
+                .await
+                .is_ok_and(|tx| tx.status == TransactionStatus::Reverted);
+            if reverted {
+                job.stage = SettlementStage::Queued;
+                return Err("gradeQuiz transaction reverted; resubmitting".to_string());
+            }
+        }
+        job.stage = SettlementStage::AwaitingResult;
+        return Ok(None);
+    };
+
+    let data = decode_quiz_response(&response)?;
+    if data.uuid != job.target_uuid {
+        return Err(format!(
+            "Result is for {} instead of {}",
+            data.uuid, job.target_uuid
+        ));
+    }
+    // `checkQuizResponse` can be overwritten by anyone on contracts that still have
+    // `demoHandleNotice`, so the notice is only trusted if it carries the root the
+    // contract recorded when the coprocessor settled the quiz.
+    let Some(settled_root) = chain
+        .client
+        .settled_results_root(&contract_address, &job.target_uuid)
+        .await?
+    else {
+        job.stage = SettlementStage::AwaitingResult;
+        return Ok(None);
+    };
+    if !carries_root(&data, &settled_root) {
+        return Err(format!(
+            "Polled notice does not carry the settled results root {}",
+            settled_root
+        ));
+    }
+    job.result = Some(data);
+    Ok(Some(SettlementStage::ApplyingResults))
+}
+
+fn carries_root(data: &QuizResponse, settled_root: &str) -> bool {
+    data.results_root
+        .as_deref()
+        .is_some_and(|root| root.eq_ignore_ascii_case(settled_root))
+}
+
+async fn apply_job(
+    db: &Arc<dyn Storage>,
+    job: &mut SettlementJob,
+) -> Result<SettlementStage, String> {
+    let data = job.result.clone().ok_or("Job has no decoded result")?;
+
+    let token_decimals = match job.target {
+        SettlementTarget::Quiz => {
+            let mut quiz = db
+                .get_quiz_via_uuid(job.target_uuid.clone())
+                .await
+                .map_err(|e| e.message)?;
+            if !quiz.submited {
+                // Results that do not match their root could never be proven, and retrying
+                // will not change the notice.
+                let tree = match ResultsTree::from_response(&data) {
+                    Ok(tree) => tree,
+                    Err(err) => {
+                        job.stage = SettlementStage::Failed;
+                        return Err(format!("Results do not match their root: {}", err));
+                    }
+                };
+                quiz.submited = true;
+                quiz.status = Status::Completed;
+                quiz.results_tree = Some(tree);
+                db.update_quiz(quiz.clone()).await.map_err(|e| e.message)?;
+            }
+            quiz.token_decimals
+        }
+        SettlementTarget::Hackathon => {
+            let mut hackathon = db
+                .get_hackathon_via_uuid(job.target_uuid.clone())
+                .await
+                .map_err(|e| e.message)?;
+            if !hackathon.submited {
+                hackathon.submited = true;
+                hackathon.status = HackathonStatus::Completed;
+                db.update_hackathon(hackathon.clone())
+                    .await
+                    .map_err(|e| e.message)?;
+            }
+            hackathon.token_decimals
+        }
+    };
+
+    let token = RewardToken {
+        chain_id: job.chain_id,
+        decimals: token_decimals,
+    };
+    apply_quiz_results(&data, token, db).await?;
+    Ok(SettlementStage::Settled)
+}
+
+async fn load_target(db: &Arc<dyn Storage>, job: &SettlementJob) -> Result<LoadedTarget, String> {
+    match job.target {
+        SettlementTarget::Quiz => {
+            let quiz = db
+                .get_quiz_via_uuid(job.target_uuid.clone())
+                .await
+                .map_err(|e| e.message)?;
+            if quiz.submited {
+                return Ok(LoadedTarget::AlreadySettled);
+            }
+            if matches!(quiz.status, Status::Canceled) {
+                return Ok(LoadedTarget::Canceled);
+            }
+            Ok(LoadedTarget::Ready(Box::new(SettlementRequest {
+                name: quiz.name.clone(),
+                total_reward: quiz.total_reward,
+                max_reward_per_user: quiz.max_reward_per_user,
+                created_by: quiz.created_by.clone(),
+                protocol: quiz.protocol.clone(),
+                access: quiz.access.clone(),
+                end_time: quiz.end_time,
+                payload: GradingPayload::Quiz(quiz.into_offchain_quiz_data()),
+            })))
+        }
+        SettlementTarget::Hackathon => {
+            let hackathon = db
+                .get_hackathon_via_uuid(job.target_uuid.clone())
+                .await
+                .map_err(|e| e.message)?;
+            if hackathon.submited {
+                return Ok(LoadedTarget::AlreadySettled);
+            }
+            if hackathon.status == HackathonStatus::Canceled {
+                return Ok(LoadedTarget::Canceled);
+            }
+            // Hackathons go through the same `gradeQuiz` path, using the judging deadline as
+            // the end time and the largest prize as the per-user cap.
+            Ok(LoadedTarget::Ready(Box::new(SettlementRequest {
+                name: hackathon.name.clone(),
+                total_reward: hackathon.total_reward(),
+                max_reward_per_user: hackathon.max_reward_per_user(),
+                created_by: hackathon.created_by.clone(),
+                protocol: hackathon.protocol.clone(),
+                access: QuizAccess::Public,
+                end_time: hackathon.judging_end,
+                payload: GradingPayload::Hackathon(hackathon.into_offchain_hackathon_data()),
+            })))
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use alloy::{
+    consensus::{SignableTransaction, TxEip1559, TxEnvelope},
+    eips::eip2718::{Decodable2718, Encodable2718},
+    network::{TransactionBuilder, TxSignerSync},
+    primitives::{Address, Bytes},
+    rpc::{
+        client::RpcClient,
+        types::{TransactionInput, TransactionRequest},
+    },
+    signers::local::PrivateKeySigner,
+};
+use async_trait::async_trait;
+use std::env;
+use std::fs;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Holds the key the server sends transactions with. `TxManager` fills in every field
+/// and only hands the finished transaction over for signing.
+#[async_trait]
+pub trait TransactionSigner: Send + Sync {
+    fn address(&self) -> Address;
+
+    /// Signs `tx` and returns its EIP-2718 encoding, ready for `eth_sendRawTransaction`.
+    async fn sign_transaction(&self, tx: TxEip1559) -> Result<Bytes, String>;
+}
+
+/// Where the signing key lives, chosen with `SIGNER_BACKEND`.
+pub enum SignerBackend {
+    /// `PRIVATE_KEY` in plain text. For local development only.
+    PrivateKey { private_key: String },
+    /// An encrypted JSON keystore at `KEYSTORE_PATH`, unlocked with `KEYSTORE_PASSWORD`
+    /// or the contents of `KEYSTORE_PASSWORD_FILE`.
+    Keystore { path: String, password: String },
+    /// A web3signer-style JSON-RPC service at `REMOTE_SIGNER_URL` that signs for
+    /// `REMOTE_SIGNER_ADDRESS` through `eth_signTransaction`.
+    Remote { url: String, address: String },
+}
+
+impl SignerBackend {
+    pub fn from_env() -> Result<Self, String> {
+        let required = |name: &str| env::var(name).map_err(|_| format!("{} must be set", name));
+        // No default: a deployment that forgets this must not quietly sign with a raw key.
+        let backend = required("SIGNER_BACKEND")?;
+        match backend.to_lowercase().as_str() {
+            "private_key" => Ok(SignerBackend::PrivateKey {
+                private_key: required("PRIVATE_KEY")?,
+            }),
+            "keystore" => {
+                let password = match env::var("KEYSTORE_PASSWORD_FILE") {
+                    Ok(path) => fs::read_to_string(&path)
+                        .map_err(|e| format!("Reading {}: {}", path, e))?
+                        .trim_end_matches(['\r', '\n'])
+                        .to_string(),
+                    Err(_) => required("KEYSTORE_PASSWORD")?,
+                };
+                Ok(SignerBackend::Keystore {
+                    path: required("KEYSTORE_PATH")?,
+                    password,
+                })
+            }
+            "remote" => Ok(SignerBackend::Remote {
+                url: required("REMOTE_SIGNER_URL")?,
+                address: required("REMOTE_SIGNER_ADDRESS")?,
+            }),
+            other => Err(format!(
+                "Unknown SIGNER_BACKEND {}, expected private_key, keystore or remote",
+                other
+            )),
+        }
+    }
+
+    pub fn connect(self) -> Result<Arc<dyn TransactionSigner>, String> {
+        match self {
+            SignerBackend::PrivateKey { private_key } => {
+                println!("Signing with a plain PRIVATE_KEY; use a keystore or remote signer outside development");
+                Ok(Arc::new(LocalSigner::from_private_key(&private_key)?))
+            }
+            SignerBackend::Keystore { path, password } => {
+                Ok(Arc::new(LocalSigner::from_keystore(&path, &password)?))
+            }
+            SignerBackend::Remote { url, address } => {
+                Ok(Arc::new(RemoteSigner::new(&url, &address)?))
+            }
+        }
+    }
+}
+
+/// Builds the signer configured by the `SIGNER_*` variables.
+pub fn signer_from_env() -> Result<Arc<dyn TransactionSigner>, String> {
+    SignerBackend::from_env()?.connect()
+}
+
+/// Signs in-process with a key held in memory.
+pub struct LocalSigner {
+    signer: PrivateKeySigner,
+}
+
+impl LocalSigner {
+    pub fn from_private_key(private_key: &str) -> Result<Self, String> {
+        let signer = PrivateKeySigner::from_str(private_key).map_err(|e| e.to_string())?;
+        Ok(LocalSigner { signer })
+    }
+
+    pub fn from_keystore(path: &str, password: &str) -> Result<Self, String> {
+        let signer = PrivateKeySigner::decrypt_keystore(path, password)
+            .map_err(|e| format!("Unlocking keystore {}: {}", path, e))?;
+        Ok(LocalSigner { signer })
+    }
+}
+
+#[async_trait]
+impl TransactionSigner for LocalSigner {
+    fn address(&self) -> Address {
+        self.signer.address()
+    }
+
+    async fn sign_transaction(&self, mut tx: TxEip1559) -> Result<Bytes, String> {
+        let signature = self
+            .signer
+            .sign_transaction_sync(&mut tx)
+            .map_err(|e| e.to_string())?;
+        let envelope = TxEnvelope::from(tx.into_signed(signature));
+        Ok(envelope.encoded_2718().into())
+    }
+}
+
+/// Asks a signing service over JSON-RPC. The key never enters this process.
+pub struct RemoteSigner {
+    client: RpcClient,
+    address: Address,
+}
+
+impl RemoteSigner {
+    pub fn new(url: &str, address: &str) -> Result<Self, String> {
+        let url = url
+            .parse()
+            .map_err(|_| "REMOTE_SIGNER_URL is not a valid URL")?;
+        let address = Address::from_str(address).map_err(|e| e.to_string())?;
+        Ok(RemoteSigner {
+            client: RpcClient::new_http(url),
+            address,
+        })
+    }
+}
+
+#[async_trait]
+impl TransactionSigner for RemoteSigner {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    async fn sign_transaction(&self, tx: TxEip1559) -> Result<Bytes, String> {
+        let mut request = TransactionRequest::default()
+            .with_from(self.address)
+            .with_chain_id(tx.chain_id)
+            .with_nonce(tx.nonce)
+            .with_gas_limit(tx.gas_limit)
+            .with_max_fee_per_gas(tx.max_fee_per_gas)
+            .with_max_priority_fee_per_gas(tx.max_priority_fee_per_gas)
+            .with_value(tx.value)
+            .input(TransactionInput::both(tx.input.clone()));
+        request.to = Some(tx.to);
+        let raw: Bytes = self
+            .client
+            .request("eth_signTransaction", (request,))
+            .await
+            .map_err(|e| format!("Remote signer: {}", e))?;
+
+        // Only broadcast what was asked for, signed by the expected key.
+        let envelope = TxEnvelope::decode_2718(&mut raw.as_ref())
+            .map_err(|e| format!("Remote signer returned an undecodable transaction: {}", e))?;
+        let signed = envelope
+            .as_eip1559()
+            .ok_or("Remote signer returned a transaction of the wrong type")?;
+        if signed.tx() != &tx {
+            return Err("Remote signer changed the transaction it was asked to sign".to_string());
+        }
+        let signer = signed
+            .signature()
+            .recover_address_from_prehash(&signed.signature_hash())
+            .map_err(|e| e.to_string())?;
+        if signer != self.address {
+            return Err(format!(
+                "Remote signer signed with {} instead of {}",
+                signer, self.address
+            ));
+        }
+        Ok(raw)
+    }
+}
@@ -0,0 +1,128 @@
+use crate::models::wallet_challenge_model::{ChallengePurpose, WalletChallenge};
+use alloy::primitives::{Address, PrimitiveSignature};
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::env;
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// How Sign-In with Ethereum messages are worded, read from the `SIWE_*` variables.
+#[derive(Debug, Clone)]
+pub struct SiweConfig {
+    /// Host the user is signing in to, shown by wallets as the requesting site.
+    pub domain: String,
+    pub uri: String,
+    pub challenge_ttl_secs: i64,
+}
+
+impl SiweConfig {
+    pub fn from_env() -> Self {
+        SiweConfig {
+            domain: env::var("SIWE_DOMAIN").unwrap_or_else(|_| String::from("localhost")),
+            uri: env::var("SIWE_URI").unwrap_or_else(|_| String::from("http://localhost")),
+            challenge_ttl_secs: env::var("SIWE_CHALLENGE_TTL_SECS")
+                .ok()
+                .and_then(|value| value.parse::<i64>().ok())
+                .filter(|value| *value > 0)
+                .unwrap_or(300),
+        }
+    }
+}
+
+/// Parses a `0x` address. Mixed-case input has to carry a valid EIP-55 checksum; all
+/// lowercase or all uppercase input has none to check.
+pub fn parse_wallet_address(value: &str) -> Result<Address, String> {
+    let value = value.trim();
+    let digits = value
+        .strip_prefix("0x")
+        .ok_or("Wallet address must start with 0x")?;
+    if digits.len() != 40 || !digits.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return Err("Wallet address must be 20 bytes of hex".to_string());
+    }
+    let address = Address::from_str(value).map_err(|e| e.to_string())?;
+    let mixed_case = digits.bytes().any(|byte| byte.is_ascii_lowercase())
+        && digits.bytes().any(|byte| byte.is_ascii_uppercase());
+    if mixed_case && address.to_checksum(None) != value {
+        return Err("Wallet address has an invalid EIP-55 checksum".to_string());
+    }
+    if address == Address::ZERO {
+        return Err("Wallet address cannot be the zero address".to_string());
+    }
+    Ok(address)
+}
+
+/// A fresh challenge asking for proof of control over `address`, for linking it to
+/// `user_uuid` or for signing in with it.
+pub fn issue_challenge(
+    config: &SiweConfig,
+    purpose: ChallengePurpose,
+    user_uuid: Option<String>,
+    address: Address,
+    chain_id: u64,
+    now: i64,
+) -> WalletChallenge {
+    let nonce = Uuid::new_v4().simple().to_string();
+    let address = address.to_checksum(None);
+    let expires_at = now + config.challenge_ttl_secs;
+    let statement = match purpose {
+        ChallengePurpose::LinkWallet => {
+            "Link this wallet to your OpenQuest account. Rewards will be paid to it."
+        }
+        ChallengePurpose::SignIn => "Sign in to OpenQuest.",
+    };
+    let message = format!(
+        "{domain} wants you to sign in with your Ethereum account:\n\
+         {address}\n\
+         \n\
+         {statement}\n\
+         \n\
+         URI: {uri}\n\
+         Version: 1\n\
+         Chain ID: {chain_id}\n\
+         Nonce: {nonce}\n\
+         Issued At: {issued_at}\n\
+         Expiration Time: {expires}",
+        domain = config.domain,
+        address = address,
+        statement = statement,
+        uri = config.uri,
+        chain_id = chain_id,
+        nonce = nonce,
+        issued_at = rfc3339(now),
+        expires = rfc3339(expires_at),
+    );
+    WalletChallenge {
+        nonce,
+        purpose,
+        user_uuid,
+        address,
+        chain_id,
+        message,
+        issued_at: now,
+        expires_at,
+        used: false,
+    }
+}
+
+/// Checks that `signature`, an EIP-191 `personal_sign` over the challenge message, was
+/// made by the challenged address.
+pub fn verify_challenge_signature(
+    challenge: &WalletChallenge,
+    signature: &str,
+) -> Result<(), String> {
+    let signature =
+        PrimitiveSignature::from_str(signature.trim()).map_err(|_| "Signature is not valid hex")?;
+    let signer = signature
+        .recover_address_from_msg(challenge.message.as_bytes())
+        .map_err(|_| "Signature could not be recovered")?;
+    let expected = Address::from_str(&challenge.address).map_err(|e| e.to_string())?;
+    if signer != expected {
+        return Err("Signature was not made by the challenged address".to_string());
+    }
+    Ok(())
+}
+
+fn rfc3339(timestamp: i64) -> String {
+    DateTime::<Utc>::from_timestamp(timestamp, 0)
+        .unwrap_or_default()
+        .to_rfc3339_opts(SecondsFormat::Secs, true)
+}
@@ -0,0 +1,235 @@
+use super::db::Database;
+use super::memory_db::MemoryDatabase;
+use crate::models::event_model::{ContractEvent, IndexerCursor};
+use crate::models::hackathon_model::Hackathon;
+use crate::models::protocol_model::{Permission, Protocol};
+use crate::models::quiz_model::{Participant, Quiz, QuizAudience, QuizView};
+use crate::models::settlement_model::SettlementJob;
+use crate::models::transaction_model::{ChainTransaction, TransactionPurpose};
+use crate::models::user_model::{SimpleUserStruct, User, Wallet};
+use crate::models::wallet_challenge_model::{ChallengePurpose, WalletChallenge};
+use async_trait::async_trait;
+use std::sync::Arc;
+
+#[derive(Debug)]
+pub struct DatabaseResponse {
+    pub error_code: u16,
+    pub message: String,
+}
+
+impl DatabaseResponse {
+    pub fn new(error_code: u16, message: String) -> Self {
+        DatabaseResponse {
+            error_code,
+            message,
+        }
+    }
+}
+
+/// Every persistence operation the routes and background tasks rely on.
+///
+/// `Database` implements it on top of MongoDB and `MemoryDatabase` keeps everything in
+/// process, so handlers only ever see a `Data<dyn Storage>`.
+#[async_trait]
+pub trait Storage: Send + Sync {
+    async fn create_user(&self, user: User) -> Result<(), DatabaseResponse>;
+
+    async fn get_all_users(&self) -> Result<Vec<SimpleUserStruct>, DatabaseResponse>;
+
+    async fn get_user_via_email(&self, email: String) -> Result<User, DatabaseResponse>;
+
+    async fn get_user_via_address(&self, address: String) -> Result<User, DatabaseResponse>;
+
+    async fn get_user_via_uuid(&self, uuid: String) -> Result<User, DatabaseResponse>;
+
+    async fn update_user_wallet(
+        &self,
+        uuid: String,
+        wallet_struct: Wallet,
+    ) -> Result<(), DatabaseResponse>;
+
+    async fn update_user(&self, user: User) -> Result<User, DatabaseResponse>;
+
+    async fn create_wallet_challenge(
+        &self,
+        challenge: WalletChallenge,
+    ) -> Result<(), DatabaseResponse>;
+
+    /// Marks the unused `purpose` challenge `nonce` issued to `user_uuid` as used and
+    /// returns it, in one step so a nonce can never be redeemed twice. 404 if there is none.
+    async fn consume_wallet_challenge(
+        &self,
+        nonce: String,
+        purpose: ChallengePurpose,
+        user_uuid: Option<String>,
+    ) -> Result<WalletChallenge, DatabaseResponse>;
+
+    async fn get_protocol_via_name(&self, name: String) -> Result<Protocol, DatabaseResponse>;
+
+    async fn get_protocol_via_id(&self, id: String) -> Result<Protocol, DatabaseResponse>;
+
+    async fn create_protocol(&self, protocol: Protocol) -> Result<(), DatabaseResponse>;
+
+    async fn update_protocol_team(&self, protocol: Protocol) -> Result<(), DatabaseResponse>;
+
+    async fn get_all_protocols(&self) -> Result<Vec<Protocol>, DatabaseResponse>;
+
+    async fn update_protocol(&self, protocol: Protocol) -> Result<Protocol, DatabaseResponse>;
+
+    async fn add_quiz(&self, quiz: Quiz) -> Result<(), DatabaseResponse>;
+
+    async fn get_quiz_via_uuid(&self, uuid: String) -> Result<Quiz, DatabaseResponse>;
+
+    async fn get_quiz_participant_via_uuid(
+        &self,
+        quiz_uuid: String,
+        participant_uuid: String,
+    ) -> Result<Participant, DatabaseResponse>;
+
+    async fn update_quiz(&self, quiz: Quiz) -> Result<Quiz, DatabaseResponse>;
+
+    async fn get_all_quizes(&self) -> Result<Vec<Quiz>, DatabaseResponse>;
+
+    async fn create_hackathon(&self, hackathon: Hackathon) -> Result<(), DatabaseResponse>;
+
+    async fn get_hackathon_via_uuid(&self, uuid: String) -> Result<Hackathon, DatabaseResponse>;
+
+    async fn update_hackathon(&self, hackathon: Hackathon) -> Result<Hackathon, DatabaseResponse>;
+
+    async fn get_all_hackathons(&self) -> Result<Vec<Hackathon>, DatabaseResponse>;
+
+    async fn create_settlement_job(&self, job: SettlementJob) -> Result<(), DatabaseResponse>;
+
+    async fn get_settlement_job_via_target(
+        &self,
+        target_uuid: String,
+    ) -> Result<SettlementJob, DatabaseResponse>;
+
+    /// Non-terminal jobs whose `next_attempt_at` has passed, oldest first. Returns an
+    /// empty list rather than a 404 when nothing is due.
+    async fn get_due_settlement_jobs(
+        &self,
+        now: i64,
+    ) -> Result<Vec<SettlementJob>, DatabaseResponse>;
+
+    async fn update_settlement_job(
+        &self,
+        job: SettlementJob,
+    ) -> Result<SettlementJob, DatabaseResponse>;
+
+    /// Inserts the event, or overwrites the copy saved by an earlier pass over the same block.
+    async fn save_contract_event(&self, event: ContractEvent) -> Result<(), DatabaseResponse>;
+
+    async fn get_contract_events(
+        &self,
+        contract_address: String,
+    ) -> Result<Vec<ContractEvent>, DatabaseResponse>;
+
+    async fn get_indexer_cursor(
+        &self,
+        chain_id: u64,
+        contract_address: String,
+    ) -> Result<IndexerCursor, DatabaseResponse>;
+
+    async fn save_indexer_cursor(&self, cursor: IndexerCursor) -> Result<(), DatabaseResponse>;
+
+    async fn create_transaction(&self, tx: ChainTransaction) -> Result<(), DatabaseResponse>;
+
+    async fn update_transaction(
+        &self,
+        tx: ChainTransaction,
+    ) -> Result<ChainTransaction, DatabaseResponse>;
+
+    /// Finds a transaction by its latest hash or any hash it replaced.
+    async fn get_transaction_via_hash(
+        &self,
+        tx_hash: String,
+    ) -> Result<ChainTransaction, DatabaseResponse>;
+
+    /// Transactions sent for a quiz, hackathon or protocol, oldest first. Returns an
+    /// empty list rather than a 404 when there are none.
+    async fn get_transactions_via_target(
+        &self,
+        target_uuid: String,
+    ) -> Result<Vec<ChainTransaction>, DatabaseResponse>;
+
+    /// Every transaction belonging to the named protocol, oldest first. Returns an empty
+    /// list rather than a 404 when there are none.
+    async fn get_transactions_via_protocol(
+        &self,
+        protocol: String,
+    ) -> Result<Vec<ChainTransaction>, DatabaseResponse>;
+
+    /// Every transaction sent for `purpose`, oldest first. Returns an empty list rather
+    /// than a 404 when there are none.
+    async fn get_transactions_via_purpose(
+        &self,
+        purpose: TransactionPurpose,
+    ) -> Result<Vec<ChainTransaction>, DatabaseResponse>;
+
+    /// Transactions on `chain_id` that are not yet confirmed, lowest nonce first.
+    async fn get_open_transactions(
+        &self,
+        chain_id: u64,
+    ) -> Result<Vec<ChainTransaction>, DatabaseResponse>;
+
+    /// Works out how much of `quiz` the user behind `viewer` may see.
+    async fn quiz_audience(
+        &self,
+        quiz: &Quiz,
+        viewer: Option<&str>,
+    ) -> Result<QuizAudience, DatabaseResponse> {
+        let Some(viewer) = viewer else {
+            return Ok(QuizAudience::Public);
+        };
+        match self.get_protocol_via_name(quiz.protocol.clone()).await {
+            Ok(protocol) if protocol.has_permission(viewer, Permission::ViewAnalytics) => {
+                return Ok(QuizAudience::Staff);
+            }
+            Ok(_) => {}
+            Err(e) if e.error_code == 404 => {}
+            Err(e) => return Err(e),
+        }
+        if quiz.is_participant(viewer) {
+            Ok(QuizAudience::Participant(viewer.to_string()))
+        } else {
+            Ok(QuizAudience::Public)
+        }
+    }
+
+    /// The read endpoints only ever receive quizzes through these projections, so the
+    /// answer key and other users' submissions never leave the data layer by accident.
+    async fn get_quiz_view(
+        &self,
+        uuid: String,
+        viewer: Option<String>,
+    ) -> Result<QuizView, DatabaseResponse> {
+        let quiz = self.get_quiz_via_uuid(uuid).await?;
+        let audience = self.quiz_audience(&quiz, viewer.as_deref()).await?;
+        Ok(quiz.view(&audience))
+    }
+
+    async fn get_all_quiz_views(
+        &self,
+        viewer: Option<String>,
+    ) -> Result<Vec<QuizView>, DatabaseResponse> {
+        let quizes = self.get_all_quizes().await?;
+        let mut views = Vec::with_capacity(quizes.len());
+        for quiz in quizes {
+            let audience = self.quiz_audience(&quiz, viewer.as_deref()).await?;
+            views.push(quiz.view(&audience));
+        }
+        Ok(views)
+    }
+}
+
+/// MongoDB, unless `STORAGE_BACKEND` is set to `memory`.
+pub async fn storage_from_env() -> Result<Arc<dyn Storage>, DatabaseResponse> {
+    match std::env::var("STORAGE_BACKEND").as_deref() {
+        Ok("memory") => {
+            println!("Using the in-memory storage backend, data will not be persisted");
+            Ok(Arc::new(MemoryDatabase::new()))
+        }
+        _ => Ok(Arc::new(Database::init().await?)),
+    }
+}
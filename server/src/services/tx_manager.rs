@@ -0,0 +1,356 @@
+use super::signer::TransactionSigner;
+use super::storage::Storage;
+use crate::models::transaction_model::{ChainTransaction, TransactionPurpose, TransactionStatus};
+use alloy::{
+    consensus::TxEip1559,
+    network::TransactionBuilder,
+    primitives::{keccak256, Address, Bytes, TxKind, B256, U256},
+    providers::{DynProvider, Provider},
+    rpc::types::TransactionRequest,
+};
+use std::env;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::sleep;
+
+/// Settings read from the `TX_*` environment variables.
+#[derive(Debug, Clone)]
+pub struct TxManagerConfig {
+    /// Blocks a transaction has to be buried under before it counts as confirmed.
+    pub confirmations: u64,
+    /// How long a broadcast may sit unmined before it is replaced with higher fees.
+    pub stuck_after_secs: i64,
+    /// Percentage each replacement raises both fees by; nodes require at least 10.
+    pub fee_bump_percent: u128,
+    pub max_fee_bumps: u32,
+    pub poll_interval: u64,
+}
+
+impl TxManagerConfig {
+    pub fn from_env() -> Self {
+        fn read<T: FromStr>(name: &str, default: T) -> T {
+            env::var(name)
+                .ok()
+                .and_then(|value| value.parse::<T>().ok())
+                .unwrap_or(default)
+        }
+        TxManagerConfig {
+            confirmations: read("TX_CONFIRMATIONS", 3),
+            stuck_after_secs: read("TX_STUCK_AFTER_SECS", 120).max(1),
+            fee_bump_percent: read("TX_FEE_BUMP_PERCENT", 20).max(10),
+            max_fee_bumps: read("TX_MAX_FEE_BUMPS", 5),
+            poll_interval: read("TX_MONITOR_POLL_SECS", 10).max(1),
+        }
+    }
+}
+
+/// Owns the server's signer. Every outgoing transaction goes through `send`, which hands
+/// out nonces one at a time and saves the transaction before broadcasting it;
+/// `run_tx_monitor` then follows it until it is confirmed.
+pub struct TxManager {
+    provider: DynProvider,
+    signer: Arc<dyn TransactionSigner>,
+    chain_id: u64,
+    db: Arc<dyn Storage>,
+    config: TxManagerConfig,
+    /// Next nonce to use, once the first transaction has been sent.
+    next_nonce: Mutex<Option<u64>>,
+}
+
+impl TxManager {
+    /// `provider` must be connected to the chain `chain_id`.
+    pub fn new(
+        provider: DynProvider,
+        signer: Arc<dyn TransactionSigner>,
+        chain_id: u64,
+        db: Arc<dyn Storage>,
+        config: TxManagerConfig,
+    ) -> Self {
+        TxManager {
+            provider,
+            signer,
+            chain_id,
+            db,
+            config,
+            next_nonce: Mutex::new(None),
+        }
+    }
+
+    pub fn signer_address(&self) -> Address {
+        self.signer.address()
+    }
+
+    /// Signs a call to `to`, saves it as pending and then broadcasts it. Does not wait for
+    /// the transaction to be mined. Once the record is saved, an error means the node
+    /// never took the transaction and it is marked dropped.
+    pub async fn send(
+        &self,
+        purpose: TransactionPurpose,
+        target_uuid: String,
+        protocol: String,
+        to: Address,
+        input: Bytes,
+    ) -> Result<ChainTransaction, String> {
+        // Held until the broadcast is accepted so concurrent sends never share a nonce.
+        let mut next_nonce = self.next_nonce.lock().await;
+        let node_nonce = self
+            .provider
+            .get_transaction_count(self.signer.address())
+            .pending()
+            .await
+            .map_err(|e| e.to_string())?;
+        // Transactions sent from the same key by something else push the node's count ahead.
+        let nonce = next_nonce.map_or(node_nonce, |nonce| nonce.max(node_nonce));
+        let (max_fee, priority_fee) = self.current_fees().await?;
+
+        let (raw, tx_hash) = self
+            .sign(to, input.clone(), nonce, max_fee, priority_fee)
+            .await?;
+        let mut tx = ChainTransaction::new(
+            self.chain_id,
+            purpose,
+            target_uuid,
+            protocol,
+            self.signer.address().to_string(),
+            to.to_string(),
+            input.to_string(),
+            nonce as i64,
+            tx_hash,
+            max_fee,
+            priority_fee,
+        );
+        // Saved first, so a broadcast that goes through is never left untracked.
+        self.db
+            .create_transaction(tx.clone())
+            .await
+            .map_err(|e| e.message)?;
+
+        if let Err(err) = self.broadcast(&raw, &tx.tx_hash).await {
+            tx.record_dropped(err.clone());
+            if let Err(save_err) = self.db.update_transaction(tx).await {
+                println!("Error saving dropped transaction: {}", save_err.message);
+            }
+            return Err(err);
+        }
+        *next_nonce = Some(nonce + 1);
+        drop(next_nonce);
+
+        println!(
+            "Sent {:?} transaction {} with nonce {}",
+            tx.purpose, tx.tx_hash, tx.nonce
+        );
+        tx.record_broadcast();
+        // The transaction is live either way; the monitor still finds it while unmarked.
+        if let Err(err) = self.db.update_transaction(tx.clone()).await {
+            println!("Error marking transaction as broadcast: {}", err.message);
+        }
+        Ok(tx)
+    }
+
+    /// Checks every unconfirmed transaction on this chain once: records inclusion, confirms those that
+    /// are deep enough, spots reorgs, and replaces stuck broadcasts.
+    pub async fn monitor_once(&self) -> Result<(), String> {
+        let head = self
+            .provider
+            .get_block_number()
+            .await
+            .map_err(|e| e.to_string())?;
+        let transactions = self
+            .db
+            .get_open_transactions(self.chain_id)
+            .await
+            .map_err(|e| e.message)?;
+
+        for mut tx in transactions {
+            let changed = match self.check_transaction(&mut tx, head).await {
+                Ok(changed) => changed,
+                Err(err) => {
+                    println!("Error checking transaction {}: {}", tx.tx_hash, err);
+                    tx.last_error = Some(err);
+                    true
+                }
+            };
+            if changed {
+                if let Err(err) = self.db.update_transaction(tx).await {
+                    println!("Error saving transaction: {}", err.message);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns whether `tx` changed and needs saving.
+    async fn check_transaction(
+        &self,
+        tx: &mut ChainTransaction,
+        head: u64,
+    ) -> Result<bool, String> {
+        let Some((tx_hash, receipt)) = self.find_receipt(tx).await? else {
+            if tx.status == TransactionStatus::Mined {
+                println!("Transaction {} was reorged out", tx.tx_hash);
+                tx.record_reorg();
+                return Ok(true);
+            }
+            // A transaction saved but never broadcast is sent again straight away.
+            let now = chrono::Utc::now().timestamp();
+            if (!tx.broadcast || now - tx.sent_at >= self.config.stuck_after_secs)
+                && tx.fee_bumps < self.config.max_fee_bumps
+            {
+                return self.bump_fees(tx).await;
+            }
+            return Ok(false);
+        };
+
+        let block_number = receipt.block_number.ok_or("Receipt has no block number")?;
+        let block_hash = receipt
+            .block_hash
+            .ok_or("Receipt has no block hash")?
+            .to_string();
+        if tx.status == TransactionStatus::Pending || tx.block_hash.as_ref() != Some(&block_hash) {
+            tx.record_inclusion(
+                tx_hash,
+                block_number as i64,
+                block_hash,
+                receipt.gas_used as i64,
+            );
+            return Ok(true);
+        }
+        // The receipt was fetched again just now and still points at the same block, so
+        // reaching the confirmation depth means that block is still canonical.
+        if head >= block_number + self.config.confirmations {
+            tx.finalize(receipt.status());
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// The receipt for whichever of the transaction's broadcasts was mined, if any.
+    async fn find_receipt(
+        &self,
+        tx: &ChainTransaction,
+    ) -> Result<Option<(String, alloy::rpc::types::TransactionReceipt)>, String> {
+        for hash in tx.hashes() {
+            let parsed = B256::from_str(&hash).map_err(|e| e.to_string())?;
+            if let Some(receipt) = self
+                .provider
+                .get_transaction_receipt(parsed)
+                .await
+                .map_err(|e| e.to_string())?
+            {
+                return Ok(Some((hash, receipt)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Re-signs the transaction with the same nonce and higher fees.
+    async fn bump_fees(&self, tx: &mut ChainTransaction) -> Result<bool, String> {
+        let (old_max_fee, old_priority_fee) = tx.fees();
+        let bump = |fee: u128| fee + (fee * self.config.fee_bump_percent / 100).max(1);
+        let (market_max_fee, market_priority_fee) = self.current_fees().await?;
+        let max_fee = bump(old_max_fee).max(market_max_fee);
+        let priority_fee = bump(old_priority_fee).max(market_priority_fee).min(max_fee);
+
+        let to = Address::from_str(&tx.to).map_err(|e| e.to_string())?;
+        let input = Bytes::from_str(&tx.input).map_err(|e| e.to_string())?;
+        let (raw, tx_hash) = self
+            .sign(to, input, tx.nonce as u64, max_fee, priority_fee)
+            .await?;
+        match self.broadcast(&raw, &tx_hash).await {
+            Ok(()) => {
+                println!("Replaced stuck transaction {} with {}", tx.tx_hash, tx_hash);
+                tx.replace(tx_hash, max_fee, priority_fee);
+                Ok(true)
+            }
+            // Nothing of this transaction reached the node, so another one took its nonce.
+            Err(err) if !tx.broadcast && err.to_lowercase().contains("nonce too low") => {
+                tx.record_dropped(err);
+                Ok(true)
+            }
+            // An earlier broadcast was mined in the meantime; its receipt shows up next pass.
+            Err(err) if err.to_lowercase().contains("nonce too low") => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Signs the call, returning the raw transaction and its hash.
+    async fn sign(
+        &self,
+        to: Address,
+        input: Bytes,
+        nonce: u64,
+        max_fee: u128,
+        priority_fee: u128,
+    ) -> Result<(Bytes, String), String> {
+        let request = TransactionRequest::default()
+            .with_from(self.signer.address())
+            .with_to(to)
+            .with_input(input.clone())
+            .with_nonce(nonce)
+            .with_max_fee_per_gas(max_fee)
+            .with_max_priority_fee_per_gas(priority_fee);
+        let gas_limit = self
+            .provider
+            .estimate_gas(&request)
+            .await
+            .map_err(|e| e.to_string())?;
+        let tx = TxEip1559 {
+            chain_id: self.chain_id,
+            nonce,
+            gas_limit,
+            max_fee_per_gas: max_fee,
+            max_priority_fee_per_gas: priority_fee,
+            to: TxKind::Call(to),
+            value: U256::ZERO,
+            access_list: Default::default(),
+            input,
+        };
+        let raw = self.signer.sign_transaction(tx).await?;
+        let tx_hash = keccak256(&raw).to_string();
+        Ok((raw, tx_hash))
+    }
+
+    /// Sends a signed transaction. A send that errors but still reached the node, such as
+    /// one that timed out waiting for the reply, counts as broadcast.
+    async fn broadcast(&self, raw: &Bytes, tx_hash: &str) -> Result<(), String> {
+        let Err(err) = self.provider.send_raw_transaction(raw).await else {
+            return Ok(());
+        };
+        let hash = B256::from_str(tx_hash).map_err(|e| e.to_string())?;
+        match self.provider.get_transaction_by_hash(hash).await {
+            Ok(Some(_)) => Ok(()),
+            _ => Err(err.to_string()),
+        }
+    }
+
+    async fn current_fees(&self) -> Result<(u128, u128), String> {
+        let gas_price = self
+            .provider
+            .get_gas_price()
+            .await
+            .map_err(|e| e.to_string())?;
+        let priority_fee = self
+            .provider
+            .get_max_priority_fee_per_gas()
+            .await
+            .map_err(|e| e.to_string())?;
+        // Twice the current price leaves room for the base fee to rise before inclusion.
+        let max_fee = gas_price.saturating_mul(2).max(priority_fee);
+        Ok((max_fee, priority_fee))
+    }
+}
+
+/// Follows sent transactions until they are confirmed.
+pub async fn run_tx_monitor(manager: Arc<TxManager>) {
+    loop {
+        if let Err(err) = manager.monitor_once().await {
+            println!(
+                "Transaction monitor could not fetch the block number: {}",
+                err
+            );
+        }
+        sleep(Duration::from_secs(manager.config.poll_interval)).await;
+    }
+}
@@ -1,35 +1,199 @@
-use actix_web::{body::BoxBody, http::StatusCode, web, HttpRequest, HttpResponse, Responder};
+use crate::services::storage::DatabaseResponse;
+use crate::utils::jwt::CookieError;
+use actix_web::{
+    body::BoxBody,
+    cookie::Cookie,
+    error::{InternalError, JsonPayloadError},
+    http::{header::ContentType, StatusCode},
+    HttpRequest, HttpResponse, Responder, ResponseError,
+};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fmt;
 
+/// Machine readable error codes carried in the error envelope.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    BadRequest,
+    ValidationFailed,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    Conflict,
+    WalletNotLinked,
+    QuizNotOpen,
+    HackathonNotOpen,
+    NotEligible,
+    AttemptExpired,
+    OnchainFailure,
+    CoprocessorFailure,
+    InternalError,
+}
+
+impl ErrorCode {
+    pub fn from_status(status_code: u16) -> ErrorCode {
+        match status_code {
+            400 => ErrorCode::BadRequest,
+            401 => ErrorCode::Unauthorized,
+            403 => ErrorCode::Forbidden,
+            404 => ErrorCode::NotFound,
+            409 => ErrorCode::Conflict,
+            422 => ErrorCode::ValidationFailed,
+            _ => ErrorCode::InternalError,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, message: &str) -> Self {
+        FieldError {
+            field: field.to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ApiError {
+    pub code: ErrorCode,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<Vec<FieldError>>,
+}
+
+#[derive(Debug)]
 pub struct ApiResponse {
     pub status_code: u16,
-    pub body: String,
+    pub body: Value,
     pub response_code: StatusCode,
+    pub cookie: Option<Cookie<'static>>,
 }
 
 impl ApiResponse {
-    pub fn new(status_code: u16, body: String) -> Self {
+    /// Serializes `body` as the JSON payload of a successful response.
+    pub fn new<T: Serialize>(status_code: u16, body: T) -> Self {
+        match serde_json::to_value(body) {
+            Ok(body) => ApiResponse {
+                status_code,
+                body,
+                response_code: StatusCode::from_u16(status_code)
+                    .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+                cookie: None,
+            },
+            Err(e) => ApiResponse::error(
+                500,
+                ErrorCode::InternalError,
+                format!("Error serializing response: {}", e),
+            ),
+        }
+    }
+
+    /// A successful response that only carries a human readable message.
+    pub fn message(status_code: u16, message: &str) -> Self {
+        ApiResponse::new(status_code, json!({ "message": message }))
+    }
+
+    pub fn error(status_code: u16, code: ErrorCode, message: impl Into<String>) -> Self {
+        ApiResponse::from_error(
+            status_code,
+            ApiError {
+                code,
+                message: message.into(),
+                details: None,
+            },
+        )
+    }
+
+    pub fn validation_error(message: &str, details: Vec<FieldError>) -> Self {
+        ApiResponse::from_error(
+            422,
+            ApiError {
+                code: ErrorCode::ValidationFailed,
+                message: message.to_string(),
+                details: Some(details),
+            },
+        )
+    }
+
+    pub fn from_error(status_code: u16, error: ApiError) -> Self {
         ApiResponse {
             status_code,
-            body,
-            response_code: StatusCode::from_u16(status_code).unwrap(),
+            body: json!({ "error": error }),
+            response_code: StatusCode::from_u16(status_code)
+                .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+            cookie: None,
         }
     }
 
     pub fn new_from_macro(response: String) -> Self {
         println!("{response}");
-        ApiResponse {
-            status_code: 500,
-            body: response,
-            response_code: StatusCode::from_u16(500).unwrap(),
-        }
+        ApiResponse::error(500, ErrorCode::InternalError, response)
+    }
+
+    pub fn with_cookie(mut self, cookie: Cookie<'static>) -> Self {
+        self.cookie = Some(cookie);
+        self
+    }
+}
+
+impl From<DatabaseResponse> for ApiResponse {
+    fn from(e: DatabaseResponse) -> Self {
+        ApiResponse::error(
+            e.error_code,
+            ErrorCode::from_status(e.error_code),
+            e.message,
+        )
+    }
+}
+
+impl From<CookieError> for ApiResponse {
+    fn from(e: CookieError) -> Self {
+        let status_code = u16::try_from(e.error_code).unwrap_or(401);
+        ApiResponse::error(status_code, ErrorCode::from_status(status_code), e.message)
     }
 }
 
 impl Responder for ApiResponse {
     type Body = BoxBody;
 
-    fn respond_to(self, req: &HttpRequest) -> HttpResponse<Self::Body> {
-        let body = BoxBody::new(web::BytesMut::from(self.body.as_bytes()));
-        HttpResponse::new(self.response_code).set_body(body)
+    fn respond_to(self, _req: &HttpRequest) -> HttpResponse<Self::Body> {
+        let mut response = HttpResponse::build(self.response_code);
+        response.content_type(ContentType::json());
+        if let Some(cookie) = self.cookie {
+            response.cookie(cookie);
+        }
+        response.body(self.body.to_string())
+    }
+}
+
+impl fmt::Display for ApiResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.body)
     }
 }
+
+/// Lets extractors reject a request with the same envelope the handlers return.
+impl ResponseError for ApiResponse {
+    fn status_code(&self) -> StatusCode {
+        self.response_code
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.response_code)
+            .content_type(ContentType::json())
+            .body(self.body.to_string())
+    }
+}
+
+/// Reports malformed JSON request bodies through the same error envelope as the handlers.
+pub fn json_error_handler(err: JsonPayloadError, req: &HttpRequest) -> actix_web::Error {
+    let response = ApiResponse::error(400, ErrorCode::BadRequest, err.to_string()).respond_to(req);
+    InternalError::from_response(err, response).into()
+}
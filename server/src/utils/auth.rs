@@ -0,0 +1,72 @@
+use crate::models::protocol_model::{Permission, Protocol};
+use crate::models::user_model::User;
+use crate::services::storage::Storage;
+use crate::utils::api_response::{ApiResponse, ErrorCode};
+use crate::utils::jwt::{token_from_request, verify_token};
+use actix_web::{dev::Payload, web::Data, FromRequest, HttpRequest};
+use futures::future::LocalBoxFuture;
+
+/// The caller behind a valid session token, with their `User` record loaded.
+///
+/// The token is taken from an `Authorization: Bearer` header or the `token` cookie.
+/// Requests without a valid token are rejected with the standard 401 envelope.
+#[derive(Debug, Clone)]
+pub struct AuthenticatedUser {
+    pub user: User,
+}
+
+impl AuthenticatedUser {
+    pub fn user_uuid(&self) -> &str {
+        &self.user.user_uuid
+    }
+
+    /// Rejects the request with 403 unless the caller holds a staff role on
+    /// `protocol` that grants `permission`.
+    #[allow(clippy::result_large_err)]
+    pub fn require_permission(
+        &self,
+        protocol: &Protocol,
+        permission: Permission,
+    ) -> Result<(), ApiResponse> {
+        if protocol.has_permission(self.user_uuid(), permission) {
+            Ok(())
+        } else {
+            Err(ApiResponse::error(
+                403,
+                ErrorCode::Forbidden,
+                format!(
+                    "Your role on protocol {} does not allow this action",
+                    protocol.name
+                ),
+            ))
+        }
+    }
+}
+
+impl FromRequest for AuthenticatedUser {
+    type Error = ApiResponse;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let token = token_from_request(req);
+        let db = req.app_data::<Data<dyn Storage>>().cloned();
+
+        Box::pin(async move {
+            let user_uuid = verify_token(token).map_err(ApiResponse::from)?;
+            let db = db.ok_or_else(|| {
+                ApiResponse::error(500, ErrorCode::InternalError, "Storage is not configured")
+            })?;
+            match db.get_user_via_uuid(user_uuid).await {
+                Ok(user) => Ok(AuthenticatedUser { user }),
+                Err(e) => match e.error_code {
+                    404 => Err(ApiResponse::error(
+                        401,
+                        ErrorCode::Unauthorized,
+                        "Token does not belong to an existing user",
+                    )),
+                    _ => Err(ApiResponse::from(e)),
+                },
+            }
+        })
+    }
+}
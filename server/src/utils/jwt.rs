@@ -1,4 +1,4 @@
-use actix_web::cookie::Cookie;
+use actix_web::{cookie::Cookie, http::header, HttpRequest};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, TokenData, Validation};
 use serde::{Deserialize, Serialize};
 
@@ -23,58 +23,73 @@ pub fn generate_jwt(
 
     let claims = Claims {
         exp: expiration_time,
-        user_uuid: user_uuid,
+        user_uuid,
     };
 
-    return encode(
+    encode(
         &Header::default(),
         &claims,
         &EncodingKey::from_secret(jwt_secret.as_ref()),
-    );
+    )
 }
 
 pub fn decode_token(
-    token: &String,
-    jwt_secret: &String,
+    token: &str,
+    jwt_secret: &str,
 ) -> Result<TokenData<Claims>, jsonwebtoken::errors::Error> {
-    return decode::<Claims>(
+    decode::<Claims>(
         token,
         &DecodingKey::from_secret(jwt_secret.as_ref()),
         &Validation::default(),
-    );
+    )
 }
 
 pub fn is_valid_token(claim: &Claims) -> bool {
-    return claim.exp > chrono::Utc::now().timestamp();
+    claim.exp > chrono::Utc::now().timestamp()
 }
 
 pub fn verify_cookie(cookie: Option<Cookie>) -> Result<String, CookieError> {
-    if let Some(cookie) = cookie {
-        let cookie_value = cookie.value().to_string();
+    verify_token(cookie.map(|cookie| cookie.value().to_string()))
+}
+
+/// Reads the session token from an `Authorization: Bearer` header, falling back to the
+/// `token` cookie set by `login_user`.
+pub fn token_from_request(req: &HttpRequest) -> Option<String> {
+    let bearer = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty());
+
+    bearer.or_else(|| req.cookie("token").map(|cookie| cookie.value().to_string()))
+}
+
+pub fn verify_token(token: Option<String>) -> Result<String, CookieError> {
+    if let Some(token) = token {
         let jwt_secret = std::env::var("JWT_SECRET").expect("JWT_SECRET not set");
 
-        match decode_token(&cookie_value, &jwt_secret) {
+        match decode_token(&token, &jwt_secret) {
             Ok(token_data) => {
                 if is_valid_token(&token_data.claims) {
-                    return Ok(token_data.claims.user_uuid);
+                    Ok(token_data.claims.user_uuid)
                 } else {
-                    return Err(CookieError {
+                    Err(CookieError {
                         error_code: 401,
                         message: "Invalid or expired token".to_string(),
-                    });
+                    })
                 }
             }
-            Err(_) => {
-                return Err(CookieError {
-                    error_code: 401,
-                    message: "Invalid token".to_string(),
-                });
-            }
+            Err(_) => Err(CookieError {
+                error_code: 401,
+                message: "Invalid token".to_string(),
+            }),
         }
     } else {
-        return Err(CookieError {
+        Err(CookieError {
             error_code: 401,
             message: "Missing token".to_string(),
-        });
+        })
     }
 }
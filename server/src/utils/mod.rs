@@ -1,2 +1,3 @@
 pub mod api_response;
+pub mod auth;
 pub mod jwt;
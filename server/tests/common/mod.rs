@@ -0,0 +1,212 @@
+// Each test binary only uses part of this module.
+#![allow(dead_code)]
+
+use dapp::grading::encode_quiz_response;
+use openquest_types::{QuizResponse, RewardData, TokenAmount};
+use server::models::{
+    chain_model::ChainConfig,
+    protocol_model::Protocol,
+    quiz_model::{
+        DifficultyLevel, OptionIndex, Question, Quiz, QuizAccess, QuizAnswer, QuizOption,
+        RewardType,
+    },
+    settlement_model::{RetryPolicy, SettlementJob, SettlementTarget},
+    user_model::{User, Wallet},
+};
+use server::services::{
+    chain::ChainClient,
+    chain_registry::ChainRegistry,
+    indexer::{index_new_blocks, IndexerConfig},
+    storage::Storage,
+};
+use std::sync::Arc;
+
+pub const CREATOR_WALLET: &str = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8";
+pub const PARTICIPANT_WALLET: &str = "0x3C44CdDdB6a900fa2b585dd299e03d12FA4293BC";
+/// Anvil's chain id.
+pub const TEST_CHAIN_ID: u64 = 31337;
+
+pub struct SeededQuiz {
+    pub creator: User,
+    pub participant: User,
+    pub protocol: Protocol,
+    pub quiz: Quiz,
+}
+
+/// The seeded quiz pays out 10 tokens of an 18-decimal token.
+pub fn reward_pool() -> TokenAmount {
+    TokenAmount::parse_units("10", 18).unwrap()
+}
+
+/// Retries straight away so a test can drive a job through every stage in a few passes.
+pub fn immediate_retries() -> RetryPolicy {
+    RetryPolicy {
+        max_attempts: 5,
+        base_delay_secs: 0,
+        max_delay_secs: 0,
+    }
+}
+
+fn user(name: &str, wallet: &str) -> User {
+    let mut user = User::new(
+        name.to_string(),
+        format!("{}@openquest.test", name),
+        "correct-horse-42".to_string(),
+    )
+    .expect("valid test user");
+    user.wallet = Wallet::new(wallet.to_string());
+    user
+}
+
+pub fn chain_config(chain_id: u64, rpc_url: &str, factory_address: &str) -> ChainConfig {
+    ChainConfig {
+        chain_id,
+        name: format!("Test chain {}", chain_id),
+        rpc_url: rpc_url.to_string(),
+        factory_address: Some(factory_address.to_string()),
+        task_issuer: None,
+        explorer_url: None,
+        reward_token_decimals: None,
+    }
+}
+
+/// A registry holding only `client` as `TEST_CHAIN_ID`.
+pub fn single_chain(
+    rpc_url: &str,
+    factory_address: &str,
+    client: Arc<dyn ChainClient>,
+) -> ChainRegistry {
+    let mut registry = ChainRegistry::new();
+    registry.register(
+        chain_config(TEST_CHAIN_ID, rpc_url, factory_address),
+        client,
+    );
+    registry
+}
+
+/// Indexes everything up to the chain head without waiting for confirmations.
+pub fn indexer_config() -> IndexerConfig {
+    IndexerConfig {
+        start_block: 0,
+        replay_from: None,
+        confirmations: 0,
+        batch_blocks: 100,
+        poll_interval: 0,
+    }
+}
+
+/// Registers a protocol on `chain_id`, plus a closed one-question quiz that a single
+/// participant answered correctly, and queues the quiz for settlement.
+pub async fn seed_closed_quiz(
+    db: &Arc<dyn Storage>,
+    registry: &ChainRegistry,
+    chain_id: u64,
+) -> SeededQuiz {
+    let creator = user("creator", CREATOR_WALLET);
+    let participant = user("participant", PARTICIPANT_WALLET);
+    db.create_user(creator.clone()).await.unwrap();
+    db.create_user(participant.clone()).await.unwrap();
+
+    let protocol = Protocol::new(
+        "Devnet Protocol".to_string(),
+        creator.user_uuid.clone(),
+        chain_id,
+    );
+    registry
+        .resolve(Some(chain_id))
+        .unwrap()
+        .client
+        .create_protocol(&protocol.name, &protocol.protocol_uuid)
+        .await
+        .expect("protocol deployment sent");
+    db.create_protocol(protocol.clone()).await.unwrap();
+
+    // The contract address only arrives with the factory's PtotocolCreated log.
+    index_new_blocks(db, registry, chain_id, &indexer_config(), None)
+        .await
+        .unwrap();
+    let mut protocol = db
+        .get_protocol_via_id(protocol.protocol_uuid.clone())
+        .await
+        .unwrap();
+
+    let option = |text: &str, option_index: OptionIndex| QuizOption {
+        text: text.to_string(),
+        option_index,
+    };
+    let now = chrono::Utc::now().timestamp();
+    let mut quiz = Quiz::new(
+        "Devnet Quiz".to_string(),
+        DifficultyLevel::Easy,
+        "Settled end to end".to_string(),
+        protocol.name.clone(),
+        creator.user_uuid.clone(),
+        vec![Question {
+            id: 0,
+            question_text: "Which network is this?".to_string(),
+            options: [
+                option("Mainnet", OptionIndex::A),
+                option("Anvil", OptionIndex::B),
+                option("Sepolia", OptionIndex::C),
+                option("Holesky", OptionIndex::D),
+            ],
+            correct_answer: OptionIndex::B,
+        }],
+        QuizAccess::Public,
+        reward_pool(),
+        reward_pool(),
+        18,
+        60,
+        now - 120,
+        RewardType::DistributedEqullyToTopFive,
+        None,
+    );
+    quiz.add_participant(
+        participant.user_uuid.clone(),
+        PARTICIPANT_WALLET.to_string(),
+    );
+    let attempt = quiz.participants.last_mut().unwrap();
+    attempt.start_time = now - 110;
+    attempt.submission_time = now - 100;
+    attempt.answered_questions = vec![QuizAnswer {
+        question_id: 0,
+        answer: OptionIndex::B,
+    }];
+
+    protocol.add_quiz(quiz.uuid.clone());
+    db.update_protocol(protocol.clone()).await.unwrap();
+    db.add_quiz(quiz.clone()).await.unwrap();
+    db.create_settlement_job(SettlementJob::new(
+        SettlementTarget::Quiz,
+        quiz.uuid.clone(),
+        quiz.settles_at(),
+    ))
+    .await
+    .unwrap();
+
+    SeededQuiz {
+        creator,
+        participant,
+        protocol,
+        quiz,
+    }
+}
+
+/// The notice the coprocessor publishes for `seed_closed_quiz`.
+pub fn expected_notice(seeded: &SeededQuiz) -> Vec<u8> {
+    encode_quiz_response(
+        QuizResponse {
+            uuid: seeded.quiz.uuid.clone(),
+            protocol: seeded.protocol.name.clone(),
+            results: vec![RewardData {
+                user_address: PARTICIPANT_WALLET.to_string(),
+                reward_amount: reward_pool(),
+                leader_boar_addition: 5.0,
+                quiz_score: 100.0,
+            }],
+            results_root: None,
+        }
+        .commit_results(),
+    )
+    .unwrap()
+}
@@ -0,0 +1,263 @@
+//! Runs create-protocol, create-quiz, grade and settle against the real contracts on a
+//! local anvil node. Skipped when `anvil` or `forge` is not on the `PATH`.
+
+mod common;
+
+use alloy::{
+    network::TransactionBuilder,
+    primitives::{keccak256, Address, Bytes, B256},
+    providers::{Provider, ProviderBuilder},
+    rpc::types::TransactionRequest,
+    sol,
+    sol_types::{SolCall, SolValue},
+};
+use common::{
+    chain_config, expected_notice, immediate_retries, reward_pool, seed_closed_quiz, single_chain,
+    TEST_CHAIN_ID,
+};
+use server::models::{
+    settlement_model::{SettlementJob, SettlementStage},
+    transaction_model::{TransactionPurpose, TransactionStatus},
+    user_model::RewardToken,
+};
+use server::services::{
+    alloy_chain::AlloyChainClient, chain_registry::ChainRegistry, memory_db::MemoryDatabase,
+    settlement_queue::settle_due_jobs, signer::LocalSigner, storage::Storage,
+    tx_manager::TxManagerConfig,
+};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::Arc;
+use std::time::Duration;
+
+sol! {
+    interface ICoprocessorCallback {
+        function coprocessorCallbackOutputsOnly(
+            bytes32 machineHash,
+            bytes32 payloadHash,
+            bytes[] calldata outputs
+        ) external;
+    }
+
+    interface ICoprocessorOutputs {
+        function Notice(bytes calldata payload) external;
+    }
+}
+
+/// First of anvil's default dev accounts.
+const DEPLOYER_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+const MACHINE_HASH: B256 = B256::repeat_byte(0x11);
+
+/// Kills the node when the test ends, pass or fail.
+struct Anvil(Child);
+
+impl Drop for Anvil {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+    }
+}
+
+fn tool_available(tool: &str) -> bool {
+    Command::new(tool)
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+fn contracts_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../contracts")
+}
+
+/// Creation bytecode of `contract` from the forge build output.
+fn artifact_bytecode(source: &str, contract: &str) -> Vec<u8> {
+    let path = contracts_dir()
+        .join("out")
+        .join(source)
+        .join(format!("{}.json", contract));
+    let artifact: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&path).expect("forge artifact")).unwrap();
+    let bytecode = artifact["bytecode"]["object"].as_str().unwrap();
+    hex::decode(bytecode.trim_start_matches("0x")).unwrap()
+}
+
+async fn deploy<P: Provider>(provider: &P, code: Vec<u8>) -> Address {
+    let tx = TransactionRequest::default().with_deploy_code(code);
+    provider
+        .send_transaction(tx)
+        .await
+        .unwrap()
+        .get_receipt()
+        .await
+        .unwrap()
+        .contract_address
+        .expect("deployed contract address")
+}
+
+async fn settle(db: &Arc<dyn Storage>, registry: &ChainRegistry, quiz_uuid: &str) -> SettlementJob {
+    let now = chrono::Utc::now().timestamp();
+    settle_due_jobs(db, registry, &immediate_retries(), now).await;
+    db.get_settlement_job_via_target(quiz_uuid.to_string())
+        .await
+        .unwrap()
+}
+
+#[actix_web::test]
+async fn settles_a_quiz_on_anvil() {
+    if !tool_available("anvil") || !tool_available("forge") {
+        eprintln!("skipping devnet test: anvil and forge must be on the PATH");
+        return;
+    }
+
+    let status = Command::new("forge")
+        .arg("build")
+        .current_dir(contracts_dir())
+        .status()
+        .unwrap();
+    assert!(
+        status.success(),
+        "forge build failed; are the contract submodules checked out?"
+    );
+
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let _anvil = Anvil(
+        Command::new("anvil")
+            .args(["--port", &port.to_string(), "--silent"])
+            .spawn()
+            .unwrap(),
+    );
+    let rpc_url = format!("http://127.0.0.1:{}", port);
+
+    // A plain provider for the impersonated coprocessor; the deployer signs everything else.
+    let provider = ProviderBuilder::new().on_http(rpc_url.parse().unwrap());
+    let mut ready = false;
+    for _ in 0..50 {
+        if provider.get_block_number().await.is_ok() {
+            ready = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(ready, "anvil did not start");
+
+    let deployer = ProviderBuilder::new()
+        .wallet(alloy::network::EthereumWallet::from(
+            DEPLOYER_KEY
+                .parse::<alloy::signers::local::PrivateKeySigner>()
+                .unwrap(),
+        ))
+        .on_http(rpc_url.parse().unwrap());
+    let task_issuer = deploy(
+        &deployer,
+        artifact_bytecode("dummyTaskIssuer.sol", "TaskIssuer"),
+    )
+    .await;
+    let mut factory_code = artifact_bytecode("protocol_factory.sol", "Factory");
+    factory_code.extend((Address::ZERO, task_issuer, MACHINE_HASH).abi_encode_params());
+    let factory = deploy(&deployer, factory_code).await;
+
+    let db: Arc<dyn Storage> = Arc::new(MemoryDatabase::new());
+    let tx_config = TxManagerConfig {
+        confirmations: 0,
+        stuck_after_secs: 120,
+        fee_bump_percent: 20,
+        max_fee_bumps: 5,
+        poll_interval: 1,
+    };
+    let config = chain_config(TEST_CHAIN_ID, &rpc_url, &factory.to_string());
+    let signer = Arc::new(LocalSigner::from_private_key(DEPLOYER_KEY).unwrap());
+    let client = AlloyChainClient::new(&config, signer, db.clone(), tx_config).unwrap();
+    let tx_manager = client.tx_manager();
+    let registry = single_chain(&rpc_url, &factory.to_string(), Arc::new(client));
+    let seeded = seed_closed_quiz(&db, &registry, TEST_CHAIN_ID).await;
+    assert!(provider
+        .get_code_at(seeded.protocol.contract_address.parse().unwrap())
+        .await
+        .is_ok_and(|code| !code.is_empty()));
+
+    let job = settle(&db, &registry, &seeded.quiz.uuid).await;
+    assert_eq!(job.stage, SettlementStage::SubmittedOnchain);
+    let job = settle(&db, &registry, &seeded.quiz.uuid).await;
+    assert_eq!(job.stage, SettlementStage::AwaitingResult);
+
+    // Anvil mines on send: one pass records the receipts, the next confirms them.
+    tx_manager.monitor_once().await.unwrap();
+    tx_manager.monitor_once().await.unwrap();
+    let transactions = db
+        .get_transactions_via_protocol(seeded.protocol.name.clone())
+        .await
+        .unwrap();
+    assert_eq!(transactions.len(), 2);
+    assert!(transactions
+        .iter()
+        .all(|tx| tx.status == TransactionStatus::Confirmed));
+    let grading = db
+        .get_transactions_via_target(seeded.quiz.uuid.clone())
+        .await
+        .unwrap();
+    assert_eq!(grading.len(), 1);
+    assert_eq!(grading[0].purpose, TransactionPurpose::GradeQuiz);
+    assert_eq!(job.tx_hash.as_deref(), Some(grading[0].tx_hash.as_str()));
+    assert_eq!(
+        grading[0].nonce,
+        transactions[0].nonce.max(transactions[1].nonce)
+    );
+
+    // Deliver the coprocessor's notice the way the task issuer would.
+    let payload: Bytes = job.payload.clone().unwrap().parse().unwrap();
+    let notice = ICoprocessorOutputs::NoticeCall {
+        payload: expected_notice(&seeded).into(),
+    }
+    .abi_encode();
+    let callback = ICoprocessorCallback::coprocessorCallbackOutputsOnlyCall {
+        machineHash: MACHINE_HASH,
+        payloadHash: keccak256(&payload),
+        outputs: vec![notice.into()],
+    };
+    provider
+        .raw_request::<_, ()>("anvil_impersonateAccount".into(), (task_issuer,))
+        .await
+        .unwrap();
+    provider
+        .raw_request::<_, ()>(
+            "anvil_setBalance".into(),
+            (task_issuer, "0xde0b6b3a7640000"),
+        )
+        .await
+        .unwrap();
+    let tx = TransactionRequest::default()
+        .with_from(task_issuer)
+        .with_to(seeded.protocol.contract_address.parse().unwrap())
+        .with_input(callback.abi_encode());
+    let receipt = provider
+        .send_transaction(tx)
+        .await
+        .unwrap()
+        .get_receipt()
+        .await
+        .unwrap();
+    assert!(receipt.status());
+
+    let job = settle(&db, &registry, &seeded.quiz.uuid).await;
+    assert_eq!(job.stage, SettlementStage::ApplyingResults);
+    let job = settle(&db, &registry, &seeded.quiz.uuid).await;
+    assert_eq!(job.stage, SettlementStage::Settled);
+
+    let participant = db
+        .get_user_via_uuid(seeded.participant.user_uuid.clone())
+        .await
+        .unwrap();
+    let token = RewardToken {
+        chain_id: Some(TEST_CHAIN_ID),
+        decimals: seeded.quiz.token_decimals,
+    };
+    assert_eq!(participant.total_reward_in(token), reward_pool());
+    assert_eq!(participant.quizes[0].token, token);
+    assert_eq!(participant.quizes[0].quiz_uuid, seeded.quiz.uuid);
+}
@@ -0,0 +1,150 @@
+//! Grading previews, answered by a stand-in node that grades inspect requests with the
+//! Coprocessor program's own library.
+
+mod common;
+
+use actix_web::{
+    test as actix_test,
+    web::{self, Bytes, Data},
+    App, HttpResponse, HttpServer,
+};
+use common::{reward_pool, seed_closed_quiz, single_chain, PARTICIPANT_WALLET, TEST_CHAIN_ID};
+use dapp::grading::{encode_quiz_response, grade_payload};
+use openquest_types::{decode_payload, GradingPayload};
+use serde_json::{json, Value};
+use server::routes::quizes_routes::protocol_quiz_route::preview_quiz_grading;
+use server::services::{
+    fake_chain::FakeChainClient, inspect::InspectClient, memory_db::MemoryDatabase,
+    storage::Storage,
+};
+use server::utils::jwt::generate_jwt;
+use std::sync::Arc;
+
+const JWT_SECRET: &str = "grading-preview-test-secret";
+
+/// Answers `/inspect` the way a rollups node does, with the program's reports.
+async fn stand_in(body: Bytes) -> HttpResponse {
+    let (status, report) = match decode_payload::<GradingPayload>(&body) {
+        Ok(payload) => (
+            "Accepted",
+            encode_quiz_response(grade_payload(payload)).unwrap(),
+        ),
+        Err(e) => ("Rejected", e.to_string().into_bytes()),
+    };
+    HttpResponse::Ok().json(json!({
+        "status": status,
+        "exception_payload": null,
+        "reports": [{ "payload": format!("0x{}", hex::encode(report)) }],
+        "processed_input_count": 0,
+    }))
+}
+
+/// Serves the stand-in on a free local port and returns its inspect URL.
+fn start_stand_in() -> String {
+    let server = HttpServer::new(|| App::new().route("/inspect", web::post().to(stand_in)))
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+    let url = format!("http://{}/inspect", server.addrs()[0]);
+    actix_web::rt::spawn(server.run());
+    url
+}
+
+fn bearer(user_uuid: &str) -> (&'static str, String) {
+    let token = generate_jwt(user_uuid.to_string(), JWT_SECRET.to_string()).unwrap();
+    ("Authorization", format!("Bearer {}", token))
+}
+
+#[actix_web::test]
+async fn previews_the_grading_of_a_quiz_to_its_staff() {
+    std::env::set_var("JWT_SECRET", JWT_SECRET);
+    let db: Arc<dyn Storage> = Arc::new(MemoryDatabase::new());
+    let fake = Arc::new(FakeChainClient::new());
+    let registry = single_chain("http://fake", &fake.factory_address(), fake.clone());
+    let seeded = seed_closed_quiz(&db, &registry, TEST_CHAIN_ID).await;
+    let uri = format!("/quiz/by-id/{}/grading-preview", seeded.quiz.uuid);
+
+    let app = actix_test::init_service(
+        App::new()
+            .app_data(Data::from(db.clone()))
+            .app_data(Data::new(InspectClient::new(Some(start_stand_in()))))
+            .service(preview_quiz_grading),
+    )
+    .await;
+    let request = actix_test::TestRequest::get()
+        .uri(&uri)
+        .insert_header(bearer(&seeded.creator.user_uuid))
+        .to_request();
+    let response = actix_test::call_service(&app, request).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let preview: Value = actix_test::read_body_json(response).await;
+    assert_eq!(preview["uuid"], seeded.quiz.uuid);
+    assert_eq!(
+        preview["results"],
+        json!([{
+            "user_address": PARTICIPANT_WALLET,
+            "reward_amount": reward_pool().to_string(),
+            "leader_boar_addition": 110.0,
+            "quiz_score": 1.0,
+        }])
+    );
+    // Previewing settles nothing.
+    assert!(fake.submissions().is_empty());
+    let quiz = db
+        .get_quiz_via_uuid(seeded.quiz.uuid.clone())
+        .await
+        .unwrap();
+    assert!(!quiz.submited);
+
+    // Participants are not protocol staff.
+    let request = actix_test::TestRequest::get()
+        .uri(&uri)
+        .insert_header(bearer(&seeded.participant.user_uuid))
+        .to_request();
+    let response = actix_test::call_service(&app, request).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    // A quiz the coprocessor would reject is reported without asking it.
+    let mut quiz = db
+        .get_quiz_via_uuid(seeded.quiz.uuid.clone())
+        .await
+        .unwrap();
+    quiz.participants.clear();
+    db.update_quiz(quiz).await.unwrap();
+    let request = actix_test::TestRequest::get()
+        .uri(&uri)
+        .insert_header(bearer(&seeded.creator.user_uuid))
+        .to_request();
+    let response = actix_test::call_service(&app, request).await;
+    assert_eq!(response.status().as_u16(), 422);
+    let body: Value = actix_test::read_body_json(response).await;
+    assert_eq!(
+        body["error"]["details"],
+        json!([{ "field": "participants", "message": "nobody took part" }])
+    );
+}
+
+#[actix_web::test]
+async fn reports_when_previews_are_not_configured() {
+    std::env::set_var("JWT_SECRET", JWT_SECRET);
+    let db: Arc<dyn Storage> = Arc::new(MemoryDatabase::new());
+    let fake = Arc::new(FakeChainClient::new());
+    let registry = single_chain("http://fake", &fake.factory_address(), fake);
+    let seeded = seed_closed_quiz(&db, &registry, TEST_CHAIN_ID).await;
+
+    let app = actix_test::init_service(
+        App::new()
+            .app_data(Data::from(db.clone()))
+            .app_data(Data::new(InspectClient::new(None)))
+            .service(preview_quiz_grading),
+    )
+    .await;
+    let request = actix_test::TestRequest::get()
+        .uri(&format!("/quiz/by-id/{}/grading-preview", seeded.quiz.uuid))
+        .insert_header(bearer(&seeded.creator.user_uuid))
+        .to_request();
+    let response = actix_test::call_service(&app, request).await;
+    assert_eq!(response.status().as_u16(), 503);
+    let body: Value = actix_test::read_body_json(response).await;
+    assert_eq!(body["error"]["code"], "COPROCESSOR_FAILURE");
+}
@@ -0,0 +1,126 @@
+//! Compressed grading payloads, and payloads published to the blob store with only
+//! their hash sent on-chain.
+
+mod common;
+
+use actix_web::{test as actix_test, web::Data, App};
+use common::{immediate_retries, seed_closed_quiz, single_chain, TEST_CHAIN_ID};
+use openquest_types::{
+    blob_ref, decode_payload, encode_payload, verify_blob, GradingPayload, WireError,
+};
+use server::routes::blob_routes::get_blob;
+use server::services::{
+    blob_store::BlobStore, fake_chain::FakeChainClient, memory_db::MemoryDatabase,
+    settlement_queue::settle_due_jobs, storage::Storage,
+};
+use std::sync::Arc;
+
+fn quiz_uuid(payload: GradingPayload) -> String {
+    match payload {
+        GradingPayload::Quiz(quiz) => quiz.uuid,
+        GradingPayload::Hackathon(hackathon) => hackathon.uuid,
+    }
+}
+
+#[actix_web::test]
+async fn compresses_quizzes_with_many_participants() {
+    let db: Arc<dyn Storage> = Arc::new(MemoryDatabase::new());
+    let fake = Arc::new(FakeChainClient::new());
+    let registry = single_chain("http://fake", &fake.factory_address(), fake);
+    let mut quiz = seed_closed_quiz(&db, &registry, TEST_CHAIN_ID).await.quiz;
+    for index in 0..500 {
+        quiz.add_participant(
+            format!("participant-{}", index),
+            format!("0x{:040x}", index + 1),
+        );
+    }
+    let offchain = quiz.into_offchain_quiz_data();
+
+    let encoded = encode_payload(&GradingPayload::Quiz(offchain.clone())).unwrap();
+    assert!(blob_ref(&encoded).unwrap().is_none());
+    // 500 participants carry well over 50 bytes each before compression.
+    assert!(encoded.len() < 500 * 50, "{} bytes", encoded.len());
+    let decoded: GradingPayload = decode_payload(&encoded).unwrap();
+    match decoded {
+        GradingPayload::Quiz(decoded) => {
+            assert_eq!(decoded.uuid, offchain.uuid);
+            assert_eq!(decoded.participants.len(), 501);
+            assert_eq!(
+                decoded.participants[500].wallet_address,
+                offchain.participants[500].wallet_address
+            );
+        }
+        GradingPayload::Hackathon(_) => panic!("decoded a hackathon"),
+    }
+
+    let mut unknown = encoded.clone();
+    unknown[4] = 9;
+    assert_eq!(
+        decode_payload::<GradingPayload>(&unknown).unwrap_err(),
+        WireError::UnknownEncoding(9)
+    );
+    let mut old_version = encoded;
+    old_version[3] = 4;
+    assert!(matches!(
+        decode_payload::<GradingPayload>(&old_version),
+        Err(WireError::UnsupportedVersion { found: 4, .. })
+    ));
+}
+
+#[actix_web::test]
+async fn publishes_payloads_over_the_threshold_as_blobs() {
+    let dir = std::env::temp_dir().join(format!("openquest-blobs-{}", uuid::Uuid::new_v4()));
+    std::env::set_var("PAYLOAD_BLOB_THRESHOLD_BYTES", "0");
+    std::env::set_var("BLOB_STORE_DIR", &dir);
+    let db: Arc<dyn Storage> = Arc::new(MemoryDatabase::new());
+    let fake = Arc::new(FakeChainClient::new());
+    let registry = single_chain("http://fake", &fake.factory_address(), fake.clone());
+    let seeded = seed_closed_quiz(&db, &registry, TEST_CHAIN_ID).await;
+
+    settle_due_jobs(
+        &db,
+        &registry,
+        &immediate_retries(),
+        chrono::Utc::now().timestamp(),
+    )
+    .await;
+    let submissions = fake.submissions();
+    assert_eq!(submissions.len(), 1);
+    let onchain = hex::decode(submissions[0].payload.trim_start_matches("0x")).unwrap();
+    let reference = blob_ref(&onchain)
+        .unwrap()
+        .expect("payload was published as a blob");
+    assert_eq!(
+        decode_payload::<GradingPayload>(&onchain).unwrap_err(),
+        WireError::UnresolvedBlob
+    );
+
+    let store = BlobStore::new(&dir);
+    let blob = store.get(&reference.hash_hex()).unwrap().unwrap();
+    verify_blob(&reference, &blob).unwrap();
+    let payload: GradingPayload = decode_payload(&blob).unwrap();
+    assert_eq!(quiz_uuid(payload), seeded.quiz.uuid);
+    let mut tampered = blob.clone();
+    *tampered.last_mut().unwrap() ^= 1;
+    assert_eq!(
+        verify_blob(&reference, &tampered).unwrap_err(),
+        WireError::BlobMismatch
+    );
+
+    let app =
+        actix_test::init_service(App::new().app_data(Data::new(store)).service(get_blob)).await;
+    let request = actix_test::TestRequest::get()
+        .uri(&format!("/blobs/0x{}", reference.hash_hex()))
+        .to_request();
+    let served = actix_test::call_and_read_body(&app, request).await;
+    assert_eq!(served.as_ref(), blob.as_slice());
+    for (uri, status) in [
+        (format!("/blobs/{}", "ab".repeat(32)), 404),
+        ("/blobs/..%2F..%2Fetc%2Fpasswd".to_string(), 400),
+    ] {
+        let request = actix_test::TestRequest::get().uri(&uri).to_request();
+        let response = actix_test::call_service(&app, request).await;
+        assert_eq!(response.status().as_u16(), status, "{}", uri);
+    }
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,165 @@
+//! Joining and submitting a quiz: each user joins once and answers each question once.
+
+mod common;
+
+use actix_web::{test as actix_test, web::Data, App};
+use common::{seed_closed_quiz, single_chain, TEST_CHAIN_ID};
+use serde_json::{json, Value};
+use server::models::{quiz_model::Status, settlement_model::SettlementStage};
+use server::routes::quizes_routes::hacker_quize_route::{start_quiz, submit_quiz};
+use server::services::{fake_chain::FakeChainClient, memory_db::MemoryDatabase, storage::Storage};
+use server::utils::jwt::generate_jwt;
+use std::sync::Arc;
+
+const JWT_SECRET: &str = "quiz-attempts-test-secret";
+
+fn bearer(user_uuid: &str) -> (&'static str, String) {
+    let token = generate_jwt(user_uuid.to_string(), JWT_SECRET.to_string()).unwrap();
+    ("Authorization", format!("Bearer {}", token))
+}
+
+#[actix_web::test]
+async fn joins_once_and_rejects_answers_the_quiz_cannot_grade() {
+    std::env::set_var("JWT_SECRET", JWT_SECRET);
+    let db: Arc<dyn Storage> = Arc::new(MemoryDatabase::new());
+    let fake = Arc::new(FakeChainClient::new());
+    let registry = single_chain("http://fake", &fake.factory_address(), fake);
+    let seeded = seed_closed_quiz(&db, &registry, TEST_CHAIN_ID).await;
+
+    // Reopen the seeded quiz with nobody in it yet.
+    let mut quiz = seeded.quiz.clone();
+    quiz.status = Status::Ongoing;
+    quiz.end_time = chrono::Utc::now().timestamp() + 600;
+    quiz.participants.clear();
+    db.update_quiz(quiz).await.unwrap();
+
+    let app = actix_test::init_service(
+        App::new()
+            .app_data(Data::from(db.clone()))
+            .service(start_quiz)
+            .service(submit_quiz),
+    )
+    .await;
+    let participant = &seeded.participant.user_uuid;
+
+    for _ in 0..2 {
+        let request = actix_test::TestRequest::post()
+            .uri("/quiz/join")
+            .insert_header(bearer(participant))
+            .set_json(json!({ "quiz_uuid": seeded.quiz.uuid }))
+            .to_request();
+        let response = actix_test::call_service(&app, request).await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+    let quiz = db
+        .get_quiz_via_uuid(seeded.quiz.uuid.clone())
+        .await
+        .unwrap();
+    assert_eq!(quiz.participants.len(), 1);
+
+    let submit = |answers: Value| {
+        actix_test::TestRequest::post()
+            .uri("/quiz/submit")
+            .insert_header(bearer(participant))
+            .set_json(json!({ "quiz_uuid": seeded.quiz.uuid, "answers": answers }))
+            .to_request()
+    };
+    let unknown = json!([{ "question_id": 7, "answer": "B" }]);
+    let repeated = json!([
+        { "question_id": 0, "answer": "B" },
+        { "question_id": 0, "answer": "C" },
+    ]);
+    for answers in [unknown, repeated] {
+        let response = actix_test::call_service(&app, submit(answers)).await;
+        assert_eq!(response.status().as_u16(), 422);
+        let body: Value = actix_test::read_body_json(response).await;
+        assert_eq!(body["error"]["code"], "VALIDATION_FAILED");
+    }
+    let quiz = db
+        .get_quiz_via_uuid(seeded.quiz.uuid.clone())
+        .await
+        .unwrap();
+    assert!(quiz.participants[0].answered_questions.is_empty());
+
+    let answers = json!([{ "question_id": 0, "answer": "B" }]);
+    let response = actix_test::call_service(&app, submit(answers)).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let quiz = db
+        .get_quiz_via_uuid(seeded.quiz.uuid.clone())
+        .await
+        .unwrap();
+    assert_eq!(quiz.participants[0].answered_questions.len(), 1);
+}
+
+#[actix_web::test]
+async fn refuses_submissions_once_grading_has_started() {
+    std::env::set_var("JWT_SECRET", JWT_SECRET);
+    let db: Arc<dyn Storage> = Arc::new(MemoryDatabase::new());
+    let fake = Arc::new(FakeChainClient::new());
+    let registry = single_chain("http://fake", &fake.factory_address(), fake);
+    let seeded = seed_closed_quiz(&db, &registry, TEST_CHAIN_ID).await;
+
+    let mut quiz = seeded.quiz.clone();
+    quiz.status = Status::Ongoing;
+    quiz.end_time = chrono::Utc::now().timestamp() + 600;
+    quiz.participants[0].start_time = chrono::Utc::now().timestamp();
+    db.update_quiz(quiz).await.unwrap();
+    let mut job = db
+        .get_settlement_job_via_target(seeded.quiz.uuid.clone())
+        .await
+        .unwrap();
+    job.advance(SettlementStage::SubmittedOnchain);
+    db.update_settlement_job(job).await.unwrap();
+
+    let app = actix_test::init_service(
+        App::new()
+            .app_data(Data::from(db.clone()))
+            .service(submit_quiz),
+    )
+    .await;
+    let request = actix_test::TestRequest::post()
+        .uri("/quiz/submit")
+        .insert_header(bearer(&seeded.participant.user_uuid))
+        .set_json(json!({
+            "quiz_uuid": seeded.quiz.uuid,
+            "answers": [{ "question_id": 0, "answer": "A" }],
+        }))
+        .to_request();
+    let response = actix_test::call_service(&app, request).await;
+    assert_eq!(response.status().as_u16(), 400);
+    let body: Value = actix_test::read_body_json(response).await;
+    assert_eq!(body["error"]["code"], "QUIZ_NOT_OPEN");
+}
+
+#[actix_web::test]
+async fn refuses_joins_after_the_quiz_closed() {
+    std::env::set_var("JWT_SECRET", JWT_SECRET);
+    let db: Arc<dyn Storage> = Arc::new(MemoryDatabase::new());
+    let fake = Arc::new(FakeChainClient::new());
+    let registry = single_chain("http://fake", &fake.factory_address(), fake);
+    let seeded = seed_closed_quiz(&db, &registry, TEST_CHAIN_ID).await;
+
+    let mut quiz = seeded.quiz.clone();
+    quiz.status = Status::Ongoing;
+    quiz.participants.clear();
+    db.update_quiz(quiz).await.unwrap();
+
+    let app = actix_test::init_service(
+        App::new()
+            .app_data(Data::from(db.clone()))
+            .service(start_quiz),
+    )
+    .await;
+    let request = actix_test::TestRequest::post()
+        .uri("/quiz/join")
+        .insert_header(bearer(&seeded.participant.user_uuid))
+        .set_json(json!({ "quiz_uuid": seeded.quiz.uuid }))
+        .to_request();
+    let response = actix_test::call_service(&app, request).await;
+    assert_eq!(response.status().as_u16(), 400);
+    let quiz = db
+        .get_quiz_via_uuid(seeded.quiz.uuid.clone())
+        .await
+        .unwrap();
+    assert!(quiz.participants.is_empty());
+}
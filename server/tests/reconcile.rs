@@ -0,0 +1,231 @@
+//! Protocol address repair and backfill against `FakeChainClient`.
+
+mod common;
+
+use common::{indexer_config, single_chain, CREATOR_WALLET, TEST_CHAIN_ID};
+use server::models::{
+    protocol_model::{Protocol, StaffRole},
+    transaction_model::{ChainTransaction, TransactionPurpose},
+    user_model::User,
+};
+use server::services::{
+    chain::ChainClient,
+    chain_registry::ChainRegistry,
+    fake_chain::FakeChainClient,
+    indexer::index_new_blocks,
+    memory_db::MemoryDatabase,
+    protocol_reconciler::{reconcile_protocols, ReconcileAction, ReconcileOptions},
+    storage::Storage,
+};
+use std::sync::Arc;
+
+fn setup() -> (Arc<dyn Storage>, Arc<FakeChainClient>, ChainRegistry) {
+    let fake = Arc::new(FakeChainClient::new());
+    let registry = single_chain("http://fake", &fake.factory_address(), fake.clone());
+    (Arc::new(MemoryDatabase::new()), fake, registry)
+}
+
+async fn owner(db: &Arc<dyn Storage>) -> User {
+    let owner = User::new(
+        "owner".to_string(),
+        "owner@openquest.test".to_string(),
+        "correct-horse-42".to_string(),
+    )
+    .unwrap();
+    db.create_user(owner.clone()).await.unwrap();
+    owner
+}
+
+#[actix_web::test]
+async fn repairs_missing_and_wrong_contract_addresses() {
+    let (db, fake, registry) = setup();
+    let owner = owner(&db).await;
+
+    let pending = Protocol::new(
+        "Pending".to_string(),
+        owner.user_uuid.clone(),
+        TEST_CHAIN_ID,
+    );
+    let mut wrong = Protocol::new("Wrong".to_string(), owner.user_uuid.clone(), TEST_CHAIN_ID);
+    wrong.contract_address = CREATOR_WALLET.to_string();
+    let never_sent = Protocol::new("Never sent".to_string(), owner.user_uuid, TEST_CHAIN_ID);
+    for protocol in [&pending, &wrong] {
+        fake.create_protocol(&protocol.name, &protocol.protocol_uuid)
+            .await
+            .unwrap();
+    }
+    for protocol in [&pending, &wrong, &never_sent] {
+        db.create_protocol(protocol.clone()).await.unwrap();
+    }
+
+    let outcomes = reconcile_protocols(&db, &registry, &ReconcileOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(outcomes.len(), 3);
+    let deployed = fake.protocols();
+    for (protocol, previous) in [(&pending, ""), (&wrong, CREATOR_WALLET)] {
+        let outcome = outcomes
+            .iter()
+            .find(|outcome| outcome.protocol_id == protocol.protocol_uuid)
+            .unwrap();
+        assert_eq!(
+            outcome.action,
+            ReconcileAction::Repaired {
+                previous: previous.to_string()
+            }
+        );
+        let saved = db
+            .get_protocol_via_id(protocol.protocol_uuid.clone())
+            .await
+            .unwrap();
+        let expected = deployed
+            .iter()
+            .find(|d| d.protocol_id == protocol.protocol_uuid)
+            .unwrap();
+        assert_eq!(saved.contract_address, expected.contract_address);
+    }
+    let outcome = outcomes
+        .iter()
+        .find(|outcome| outcome.protocol_id == never_sent.protocol_uuid)
+        .unwrap();
+    assert_eq!(outcome.action, ReconcileAction::NotDeployed);
+
+    // A second pass finds nothing left to fix.
+    let outcomes = reconcile_protocols(&db, &registry, &ReconcileOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(
+        outcomes
+            .iter()
+            .filter(|outcome| outcome.action == ReconcileAction::Unchanged)
+            .count(),
+        2
+    );
+}
+
+#[actix_web::test]
+async fn backfills_deployments_that_were_never_saved() {
+    let (db, fake, registry) = setup();
+    let owner = owner(&db).await;
+
+    // The deployment went out but the protocol record was never written.
+    let protocol_id = "lost-protocol-id";
+    let tx_hash = fake.create_protocol("Lost", protocol_id).await.unwrap();
+    db.create_transaction(ChainTransaction::new(
+        TEST_CHAIN_ID,
+        TransactionPurpose::CreateProtocol,
+        protocol_id.to_string(),
+        "Lost".to_string(),
+        CREATOR_WALLET.to_string(),
+        fake.factory_address(),
+        "0x".to_string(),
+        0,
+        tx_hash,
+        1,
+        1,
+    ))
+    .await
+    .unwrap();
+    // Listed explicitly as well, which must not produce a second outcome.
+    let mut options = ReconcileOptions {
+        protocol_ids: vec![protocol_id.to_string(), "unknown-id".to_string()],
+        ..ReconcileOptions::default()
+    };
+
+    let outcomes = reconcile_protocols(&db, &registry, &options).await.unwrap();
+    assert_eq!(outcomes.len(), 2);
+    assert_eq!(outcomes[0].protocol_id, protocol_id);
+    assert_eq!(outcomes[0].action, ReconcileAction::Orphaned);
+    assert_eq!(outcomes[0].chain_id, Some(TEST_CHAIN_ID));
+    assert_eq!(outcomes[1].action, ReconcileAction::NotDeployed);
+
+    options.owner_uuid = Some(owner.user_uuid.clone());
+    options.dry_run = true;
+    let outcomes = reconcile_protocols(&db, &registry, &options).await.unwrap();
+    assert_eq!(outcomes[0].action, ReconcileAction::Backfilled);
+    assert!(db
+        .get_protocol_via_id(protocol_id.to_string())
+        .await
+        .is_err());
+
+    options.dry_run = false;
+    let outcomes = reconcile_protocols(&db, &registry, &options).await.unwrap();
+    assert_eq!(outcomes[0].action, ReconcileAction::Backfilled);
+    let saved = db
+        .get_protocol_via_id(protocol_id.to_string())
+        .await
+        .unwrap();
+    assert_eq!(saved.name, "Lost");
+    assert_eq!(saved.chain_id, Some(TEST_CHAIN_ID));
+    assert_eq!(saved.contract_address, fake.protocols()[0].contract_address);
+    assert_eq!(saved.role_of(&owner.user_uuid), Some(StaffRole::Owner));
+}
+
+#[actix_web::test]
+async fn only_trusts_deployments_the_server_sent() {
+    let (db, fake, registry) = setup();
+    let owner = owner(&db).await;
+
+    // Someone else reuses a saved protocol's id, and another's name, on the factory.
+    let hijacked = Protocol::new(
+        "Hijacked".to_string(),
+        owner.user_uuid.clone(),
+        TEST_CHAIN_ID,
+    );
+    let renamed = Protocol::new(
+        "Renamed".to_string(),
+        owner.user_uuid.clone(),
+        TEST_CHAIN_ID,
+    );
+    fake.create_protocol_as(CREATOR_WALLET, &hijacked.name, &hijacked.protocol_uuid)
+        .unwrap();
+    fake.create_protocol_as(CREATOR_WALLET, &renamed.name, "some-other-id")
+        .unwrap();
+    fake.create_protocol_as(CREATOR_WALLET, "Stolen", "stolen-id")
+        .unwrap();
+    for protocol in [&hijacked, &renamed] {
+        db.create_protocol(protocol.clone()).await.unwrap();
+    }
+    // The factory's logs are not trusted with it either.
+    index_new_blocks(&db, &registry, TEST_CHAIN_ID, &indexer_config(), None)
+        .await
+        .unwrap();
+
+    let options = ReconcileOptions {
+        owner_uuid: Some(owner.user_uuid.clone()),
+        protocol_ids: vec!["stolen-id".to_string()],
+        ..ReconcileOptions::default()
+    };
+    let outcomes = reconcile_protocols(&db, &registry, &options).await.unwrap();
+    assert_eq!(outcomes.len(), 3);
+    for outcome in outcomes.iter() {
+        assert!(matches!(outcome.action, ReconcileAction::Unverified { .. }));
+    }
+    for protocol in [&hijacked, &renamed] {
+        let saved = db
+            .get_protocol_via_id(protocol.protocol_uuid.clone())
+            .await
+            .unwrap();
+        assert_eq!(saved.contract_address, protocol.contract_address);
+    }
+    assert!(db
+        .get_protocol_via_id("stolen-id".to_string())
+        .await
+        .is_err());
+
+    // The server's own deployment under the same id is taken.
+    fake.create_protocol(&hijacked.name, &hijacked.protocol_uuid)
+        .await
+        .unwrap();
+    index_new_blocks(&db, &registry, TEST_CHAIN_ID, &indexer_config(), None)
+        .await
+        .unwrap();
+    let saved = db
+        .get_protocol_via_id(hijacked.protocol_uuid.clone())
+        .await
+        .unwrap();
+    assert_eq!(
+        saved.contract_address,
+        fake.protocols().last().unwrap().contract_address
+    );
+}
@@ -0,0 +1,163 @@
+//! Proofs of settled quiz results against the Merkle root committed to in the notice.
+
+mod common;
+
+use actix_web::{test as actix_test, web::Data, App};
+use common::{
+    immediate_retries, reward_pool, seed_closed_quiz, single_chain, SeededQuiz, CREATOR_WALLET,
+    PARTICIPANT_WALLET, TEST_CHAIN_ID,
+};
+use dapp::grading::encode_quiz_response;
+use openquest_types::{
+    merkle::{hash_from_hex, result_leaf},
+    verify_proof, QuizResponse, RewardData, TokenAmount,
+};
+use serde_json::Value;
+use server::models::settlement_model::{SettlementJob, SettlementStage};
+use server::routes::quizes_routes::protocol_quiz_route::get_result_proof;
+use server::services::{
+    chain_registry::ChainRegistry, fake_chain::FakeChainClient, memory_db::MemoryDatabase,
+    settlement_queue::settle_due_jobs, storage::Storage,
+};
+use std::sync::Arc;
+
+const OTHER_WALLETS: [&str; 2] = [
+    "0x90F79bf6EB2c4f870365E785982E1f101E93b906",
+    "0x15d34AAf54267DB7D7c367839AAf71A00a2C6A65",
+];
+
+fn result(address: &str, reward: TokenAmount, score: f64) -> RewardData {
+    RewardData {
+        user_address: address.to_string(),
+        reward_amount: reward,
+        leader_boar_addition: 10.0 + score * 50.0,
+        quiz_score: score,
+    }
+}
+
+fn notice(seeded: &SeededQuiz) -> QuizResponse {
+    QuizResponse {
+        uuid: seeded.quiz.uuid.clone(),
+        protocol: seeded.protocol.name.clone(),
+        results: vec![
+            result(PARTICIPANT_WALLET, reward_pool(), 2.0),
+            result(OTHER_WALLETS[0], TokenAmount::ZERO, 1.0),
+            result(OTHER_WALLETS[1], TokenAmount::ZERO, 0.0),
+        ],
+        results_root: None,
+    }
+    .commit_results()
+}
+
+/// Runs one pass of the settlement queue.
+async fn settle(db: &Arc<dyn Storage>, registry: &ChainRegistry) {
+    let now = chrono::Utc::now().timestamp();
+    settle_due_jobs(db, registry, &immediate_retries(), now).await;
+}
+
+/// Submits the seeded quiz, answers it with `notice` and runs the queue until the job
+/// settles or fails.
+async fn settle_with(
+    db: &Arc<dyn Storage>,
+    registry: &ChainRegistry,
+    fake: &FakeChainClient,
+    notice: &QuizResponse,
+) -> SettlementJob {
+    settle(db, registry).await;
+    let payload = fake.submissions()[0].payload.clone();
+    fake.respond(&payload, encode_quiz_response(notice.clone()).unwrap())
+        .unwrap();
+    settle(db, registry).await;
+    settle(db, registry).await;
+    db.get_settlement_job_via_target(notice.uuid.clone())
+        .await
+        .unwrap()
+}
+
+async fn get_proof(db: &Arc<dyn Storage>, quiz_uuid: &str, address: &str) -> (u16, Value) {
+    let app = actix_test::init_service(
+        App::new()
+            .app_data(Data::from(db.clone()))
+            .service(get_result_proof),
+    )
+    .await;
+    let request = actix_test::TestRequest::get()
+        .uri(&format!("/quiz/by-id/{}/proof/{}", quiz_uuid, address))
+        .to_request();
+    let response = actix_test::call_service(&app, request).await;
+    let status = response.status().as_u16();
+    (status, actix_test::read_body_json(response).await)
+}
+
+#[actix_web::test]
+async fn every_settled_result_is_proven_against_the_notice_root() {
+    let db: Arc<dyn Storage> = Arc::new(MemoryDatabase::new());
+    let fake = Arc::new(FakeChainClient::new());
+    let registry = single_chain("http://fake", &fake.factory_address(), fake.clone());
+    let seeded = seed_closed_quiz(&db, &registry, TEST_CHAIN_ID).await;
+    let notice = notice(&seeded);
+    let job = settle_with(&db, &registry, &fake, &notice).await;
+    assert_eq!(job.stage, SettlementStage::Settled);
+
+    let root = hash_from_hex(notice.results_root.as_deref().unwrap()).unwrap();
+    for (index, expected) in notice.results.iter().enumerate() {
+        // Addresses are matched regardless of case.
+        let (status, body) = get_proof(
+            &db,
+            &seeded.quiz.uuid,
+            &expected.user_address.to_lowercase(),
+        )
+        .await;
+        assert_eq!(status, 200);
+        assert_eq!(body["index"], index);
+        assert_eq!(body["root"], notice.results_root.clone().unwrap());
+
+        let result: RewardData = serde_json::from_value(body["result"].clone()).unwrap();
+        assert!(result
+            .user_address
+            .eq_ignore_ascii_case(&expected.user_address));
+        assert_eq!(result.reward_amount, expected.reward_amount);
+        let proof: Vec<[u8; 32]> = body["proof"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|sibling| hash_from_hex(sibling.as_str().unwrap()).unwrap())
+            .collect();
+        assert!(verify_proof(&root, &result_leaf(&result), &proof));
+
+        // A proof only holds for the result it was made for.
+        let mut tampered = result.clone();
+        tampered.reward_amount = TokenAmount::from(1);
+        assert!(!verify_proof(&root, &result_leaf(&tampered), &proof));
+    }
+
+    let (status, _) = get_proof(&db, &seeded.quiz.uuid, CREATOR_WALLET).await;
+    assert_eq!(status, 404);
+}
+
+#[actix_web::test]
+async fn results_that_do_not_match_their_root_cannot_be_proven() {
+    let db: Arc<dyn Storage> = Arc::new(MemoryDatabase::new());
+    let fake = Arc::new(FakeChainClient::new());
+    let registry = single_chain("http://fake", &fake.factory_address(), fake.clone());
+    let seeded = seed_closed_quiz(&db, &registry, TEST_CHAIN_ID).await;
+
+    let (status, _) = get_proof(&db, &seeded.quiz.uuid, PARTICIPANT_WALLET).await;
+    assert_eq!(status, 404);
+
+    let mut notice = notice(&seeded);
+    notice.results[1].quiz_score = 3.0;
+    let job = settle_with(&db, &registry, &fake, &notice).await;
+
+    // Results that cannot be proven are not settled.
+    assert_eq!(job.stage, SettlementStage::Failed);
+    assert!(job.last_error.unwrap().contains("do not match their root"));
+    let quiz = db
+        .get_quiz_via_uuid(seeded.quiz.uuid.clone())
+        .await
+        .unwrap();
+    assert!(!quiz.submited);
+    assert!(quiz.results_tree.is_none());
+    let (status, _) = get_proof(&db, &seeded.quiz.uuid, PARTICIPANT_WALLET).await;
+    assert_eq!(status, 404);
+}
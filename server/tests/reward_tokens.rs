@@ -0,0 +1,134 @@
+//! Rewards are priced in the reward token of the chain they are paid on.
+
+mod common;
+
+use actix_web::{test as actix_test, web::Data, App};
+use common::{chain_config, TEST_CHAIN_ID};
+use mongodb::bson::{from_document, to_bson, to_document};
+use serde_json::{json, Value};
+use server::models::{
+    protocol_model::Protocol,
+    quiz_model::{OptionIndex, Question, QuizOption, TokenAmount},
+    user_model::{RewardToken, User},
+};
+use server::routes::quizes_routes::protocol_quiz_route::create_quiz;
+use server::services::{
+    chain_registry::ChainRegistry, fake_chain::FakeChainClient, memory_db::MemoryDatabase,
+    storage::Storage,
+};
+use server::utils::jwt::generate_jwt;
+use std::sync::Arc;
+
+const JWT_SECRET: &str = "reward-tokens-test-secret";
+
+#[actix_web::test]
+async fn quizzes_are_priced_in_their_chain_token() {
+    std::env::set_var("JWT_SECRET", JWT_SECRET);
+    let db: Arc<dyn Storage> = Arc::new(MemoryDatabase::new());
+    let fake = Arc::new(FakeChainClient::new());
+    let mut config = chain_config(TEST_CHAIN_ID, "http://fake", &fake.factory_address());
+    config.reward_token_decimals = Some(6);
+    let mut registry = ChainRegistry::new();
+    registry.register(config, fake);
+
+    let creator = User::new(
+        "creator".to_string(),
+        "creator@openquest.test".to_string(),
+        "correct-horse-42".to_string(),
+    )
+    .unwrap();
+    db.create_user(creator.clone()).await.unwrap();
+    let protocol = Protocol::new(
+        "Six Decimals".to_string(),
+        creator.user_uuid.clone(),
+        TEST_CHAIN_ID,
+    );
+    db.create_protocol(protocol.clone()).await.unwrap();
+
+    let app = actix_test::init_service(
+        App::new()
+            .app_data(Data::from(db.clone()))
+            .app_data(Data::new(registry))
+            .service(create_quiz),
+    )
+    .await;
+    let option = |text: &str, option_index: OptionIndex| QuizOption {
+        text: text.to_string(),
+        option_index,
+    };
+    let question = Question {
+        id: 0,
+        question_text: "How many decimals does this token have?".to_string(),
+        options: [
+            option("6", OptionIndex::A),
+            option("8", OptionIndex::B),
+            option("12", OptionIndex::C),
+            option("18", OptionIndex::D),
+        ],
+        correct_answer: OptionIndex::A,
+    };
+    let token = generate_jwt(creator.user_uuid.clone(), JWT_SECRET.to_string()).unwrap();
+    let request = actix_test::TestRequest::post()
+        .uri("/quiz/create")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .set_json(json!({
+            "name": "Priced quiz",
+            "difficulty": "easy",
+            "description": "Pays in the chain's token",
+            "protocol": protocol.name,
+            "questions": [question],
+            "access": "Public",
+            "total_reward": "12.5",
+            "max_reward_per_user": "2.5",
+            // Ignored; the chain decides.
+            "token_decimals": 18,
+            "duration_in_sec_timestamp": 600,
+            "start_time": chrono::Utc::now().timestamp(),
+            "reward_type": "DistributedEqullyToTopFive",
+        }))
+        .to_request();
+    let response = actix_test::call_service(&app, request).await;
+    assert_eq!(response.status().as_u16(), 201);
+    let body: Value = actix_test::read_body_json(response).await;
+
+    let quiz = db
+        .get_quiz_via_uuid(body["quiz_uuid"].as_str().unwrap().to_string())
+        .await
+        .unwrap();
+    assert_eq!(quiz.token_decimals, 6);
+    assert_eq!(
+        quiz.total_reward,
+        TokenAmount::parse_units("12.5", 6).unwrap()
+    );
+}
+
+#[test]
+fn legacy_totals_are_read_back_in_the_legacy_token() {
+    let amount = TokenAmount::parse_units("1.5", 18).unwrap();
+    let user = User::new(
+        "legacy".to_string(),
+        "legacy@openquest.test".to_string(),
+        "correct-horse-42".to_string(),
+    )
+    .unwrap();
+    let mut stored = to_document(&user).unwrap();
+    stored.insert("total_reward", to_bson(&amount).unwrap());
+
+    let mut read_back: User = from_document(stored).unwrap();
+    assert_eq!(read_back.total_reward_in(RewardToken::legacy()), amount);
+
+    let other_chain = RewardToken {
+        chain_id: Some(TEST_CHAIN_ID),
+        decimals: 6,
+    };
+    read_back
+        .update_total_reward(TokenAmount::parse_units("3", 6).unwrap(), other_chain)
+        .unwrap();
+    let totals: Vec<String> = read_back
+        .profile()
+        .total_reward
+        .into_iter()
+        .map(|total| total.amount)
+        .collect();
+    assert_eq!(totals, ["1.5", "3"]);
+}
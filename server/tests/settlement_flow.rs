@@ -0,0 +1,477 @@
+//! Settlement driven end to end against `FakeChainClient` and the in-memory store.
+
+mod common;
+
+use alloy::primitives::Bytes;
+use common::{
+    chain_config, expected_notice, immediate_retries, indexer_config, reward_pool,
+    seed_closed_quiz, single_chain, SeededQuiz, CREATOR_WALLET, TEST_CHAIN_ID,
+};
+use dapp::grading::encode_quiz_response;
+use openquest_types::{InputProblem, QuizResponse, RejectionReport, RewardData};
+use server::models::{
+    protocol_model::Protocol,
+    quiz_model::QuizAudience,
+    settlement_model::{SettlementJob, SettlementStage},
+    transaction_model::{ChainTransaction, TransactionPurpose, TransactionStatus},
+    user_model::RewardToken,
+};
+use server::services::{
+    chain::{ChainClient, OpenQuestEvents::ResultReceived},
+    chain_registry::ChainRegistry,
+    fake_chain::FakeChainClient,
+    indexer::index_new_blocks,
+    memory_db::MemoryDatabase,
+    settlement_queue::settle_due_jobs,
+    storage::Storage,
+};
+use std::sync::Arc;
+
+struct Harness {
+    db: Arc<dyn Storage>,
+    fake: Arc<FakeChainClient>,
+    registry: ChainRegistry,
+}
+
+impl Harness {
+    fn new() -> Self {
+        let fake = Arc::new(FakeChainClient::new());
+        Harness {
+            db: Arc::new(MemoryDatabase::new()),
+            registry: single_chain("http://fake", &fake.factory_address(), fake.clone()),
+            fake,
+        }
+    }
+
+    async fn seed(&self) -> SeededQuiz {
+        seed_closed_quiz(&self.db, &self.registry, TEST_CHAIN_ID).await
+    }
+
+    async fn index(&self) {
+        index_new_blocks(
+            &self.db,
+            &self.registry,
+            TEST_CHAIN_ID,
+            &indexer_config(),
+            None,
+        )
+        .await
+        .unwrap();
+    }
+
+    /// Runs one pass of the settlement queue and returns the quiz's job afterwards.
+    async fn settle(&self, seeded: &SeededQuiz) -> SettlementJob {
+        let now = chrono::Utc::now().timestamp();
+        settle_due_jobs(&self.db, &self.registry, &immediate_retries(), now).await;
+        self.db
+            .get_settlement_job_via_target(seeded.quiz.uuid.clone())
+            .await
+            .unwrap()
+    }
+
+    async fn assert_settled(&self, seeded: &SeededQuiz) {
+        let quiz = self
+            .db
+            .get_quiz_via_uuid(seeded.quiz.uuid.clone())
+            .await
+            .unwrap();
+        assert!(quiz.submited);
+        assert!(quiz.results_tree.is_some());
+
+        let participant = self
+            .db
+            .get_user_via_uuid(seeded.participant.user_uuid.clone())
+            .await
+            .unwrap();
+        let job = self
+            .db
+            .get_settlement_job_via_target(seeded.quiz.uuid.clone())
+            .await
+            .unwrap();
+        let token = RewardToken {
+            chain_id: job.chain_id,
+            decimals: seeded.quiz.token_decimals,
+        };
+        assert_eq!(participant.total_reward_in(token), reward_pool());
+        assert_eq!(participant.quizes[0].token, token);
+        assert_eq!(participant.quizes.len(), 1);
+        assert_eq!(participant.quizes[0].quiz_uuid, seeded.quiz.uuid);
+    }
+}
+
+#[actix_web::test]
+async fn settles_a_quiz_from_the_indexed_result() {
+    let harness = Harness::new();
+    let seeded = harness.seed().await;
+
+    let job = harness.settle(&seeded).await;
+    assert_eq!(job.stage, SettlementStage::SubmittedOnchain);
+    let submissions = harness.fake.submissions();
+    assert_eq!(submissions.len(), 1);
+    assert_eq!(submissions[0].uuid, seeded.quiz.uuid);
+    assert_eq!(
+        submissions[0].contract_address,
+        seeded.protocol.contract_address
+    );
+
+    harness
+        .fake
+        .respond(&submissions[0].payload, expected_notice(&seeded))
+        .unwrap();
+    index_new_blocks(
+        &harness.db,
+        &harness.registry,
+        TEST_CHAIN_ID,
+        &indexer_config(),
+        None,
+    )
+    .await
+    .unwrap();
+
+    let events = harness
+        .db
+        .get_contract_events(seeded.protocol.contract_address.clone())
+        .await
+        .unwrap();
+    assert_eq!(events.len(), 1);
+
+    // The indexed ResultReceived hands the notice to the job, so the next pass applies it.
+    let job = harness.settle(&seeded).await;
+    assert_eq!(job.stage, SettlementStage::Settled);
+    harness.assert_settled(&seeded).await;
+}
+
+#[actix_web::test]
+async fn retries_chain_failures_and_polls_for_the_result() {
+    let harness = Harness::new();
+    let seeded = harness.seed().await;
+
+    harness.fake.fail_next("nonce too low");
+    let job = harness.settle(&seeded).await;
+    assert_eq!(job.stage, SettlementStage::Queued);
+    assert_eq!(job.attempts, 1);
+    assert_eq!(job.last_error.as_deref(), Some("nonce too low"));
+
+    let job = harness.settle(&seeded).await;
+    assert_eq!(job.stage, SettlementStage::SubmittedOnchain);
+
+    let job = harness.settle(&seeded).await;
+    assert_eq!(job.stage, SettlementStage::AwaitingResult);
+
+    let payload = harness.fake.submissions()[0].payload.clone();
+    harness
+        .fake
+        .respond(&payload, expected_notice(&seeded))
+        .unwrap();
+    let job = harness.settle(&seeded).await;
+    assert_eq!(job.stage, SettlementStage::ApplyingResults);
+
+    let job = harness.settle(&seeded).await;
+    assert_eq!(job.stage, SettlementStage::Settled);
+    harness.assert_settled(&seeded).await;
+}
+
+#[actix_web::test]
+async fn reuses_an_open_grade_quiz_transaction() {
+    let harness = Harness::new();
+    let seeded = harness.seed().await;
+
+    // An earlier attempt broadcast gradeQuiz but failed before the job recorded it.
+    let tx = ChainTransaction::new(
+        TEST_CHAIN_ID,
+        TransactionPurpose::GradeQuiz,
+        seeded.quiz.uuid.clone(),
+        seeded.protocol.name.clone(),
+        CREATOR_WALLET.to_string(),
+        seeded.protocol.contract_address.clone(),
+        "0x".to_string(),
+        0,
+        "0x01".to_string(),
+        1,
+        1,
+    );
+    harness.db.create_transaction(tx.clone()).await.unwrap();
+
+    let job = harness.settle(&seeded).await;
+    assert_eq!(job.stage, SettlementStage::SubmittedOnchain);
+    assert_eq!(job.tx_hash, Some(tx.tx_hash.clone()));
+    assert!(harness.fake.submissions().is_empty());
+
+    // Once it has been dropped the quiz is graded with a new transaction.
+    let mut dropped = tx;
+    dropped.record_dropped("nonce too low".to_string());
+    harness.db.update_transaction(dropped).await.unwrap();
+    let mut job = job;
+    job.stage = SettlementStage::Queued;
+    harness.db.update_settlement_job(job).await.unwrap();
+    let job = harness.settle(&seeded).await;
+    assert_eq!(job.stage, SettlementStage::SubmittedOnchain);
+    assert_eq!(harness.fake.submissions().len(), 1);
+}
+
+#[actix_web::test]
+async fn waits_for_results_without_using_up_attempts() {
+    let harness = Harness::new();
+    let seeded = harness.seed().await;
+    let job = harness.settle(&seeded).await;
+    let tx_hash = job.tx_hash.clone().unwrap();
+
+    for _ in 0..immediate_retries().max_attempts * 2 {
+        let job = harness.settle(&seeded).await;
+        assert_eq!(job.stage, SettlementStage::AwaitingResult);
+        assert_eq!(job.attempts, 0);
+    }
+
+    // Real failures still run the job out of attempts.
+    for _ in 0..immediate_retries().max_attempts {
+        harness.fake.fail_next("connection refused");
+        harness.settle(&seeded).await;
+    }
+    let job = harness.settle(&seeded).await;
+    assert_eq!(job.stage, SettlementStage::Failed);
+
+    // Its transaction was mined, so the result that turns up afterwards still settles it.
+    let mut tx = ChainTransaction::new(
+        TEST_CHAIN_ID,
+        TransactionPurpose::GradeQuiz,
+        seeded.quiz.uuid.clone(),
+        seeded.protocol.name.clone(),
+        CREATOR_WALLET.to_string(),
+        seeded.protocol.contract_address.clone(),
+        "0x".to_string(),
+        0,
+        tx_hash,
+        1,
+        1,
+    );
+    tx.status = TransactionStatus::Mined;
+    harness.db.create_transaction(tx).await.unwrap();
+    let payload = harness.fake.submissions()[0].payload.clone();
+    harness
+        .fake
+        .respond(&payload, expected_notice(&seeded))
+        .unwrap();
+    index_new_blocks(
+        &harness.db,
+        &harness.registry,
+        TEST_CHAIN_ID,
+        &indexer_config(),
+        None,
+    )
+    .await
+    .unwrap();
+
+    let job = harness.settle(&seeded).await;
+    assert_eq!(job.stage, SettlementStage::Settled);
+    harness.assert_settled(&seeded).await;
+}
+
+#[actix_web::test]
+async fn ignores_polled_notices_the_coprocessor_did_not_settle() {
+    let harness = Harness::new();
+    let seeded = harness.seed().await;
+    harness.settle(&seeded).await;
+    let payload = harness.fake.submissions()[0].payload.clone();
+    let forged = forged_notice(&seeded);
+
+    harness
+        .fake
+        .plant_response(&payload, forged.clone())
+        .unwrap();
+    let job = harness.settle(&seeded).await;
+    assert_eq!(job.stage, SettlementStage::AwaitingResult);
+    assert!(job.result.is_none());
+
+    // Planted over the real notice after the quiz settled, it no longer matches the root.
+    harness
+        .fake
+        .respond(&payload, expected_notice(&seeded))
+        .unwrap();
+    harness.fake.plant_response(&payload, forged).unwrap();
+    let job = harness.settle(&seeded).await;
+    assert_eq!(job.stage, SettlementStage::AwaitingResult);
+    assert!(job.result.is_none());
+
+    index_new_blocks(
+        &harness.db,
+        &harness.registry,
+        TEST_CHAIN_ID,
+        &indexer_config(),
+        None,
+    )
+    .await
+    .unwrap();
+    let job = harness.settle(&seeded).await;
+    assert_eq!(job.stage, SettlementStage::Settled);
+    harness.assert_settled(&seeded).await;
+}
+
+#[actix_web::test]
+async fn ignores_results_emitted_by_other_protocols() {
+    let harness = Harness::new();
+    let seeded = harness.seed().await;
+    harness.settle(&seeded).await;
+
+    let rogue = Protocol::new(
+        "Rogue Protocol".to_string(),
+        seeded.creator.user_uuid.clone(),
+        TEST_CHAIN_ID,
+    );
+    harness
+        .fake
+        .create_protocol(&rogue.name, &rogue.protocol_uuid)
+        .await
+        .unwrap();
+    harness.db.create_protocol(rogue.clone()).await.unwrap();
+    harness.index().await;
+    let rogue = harness
+        .db
+        .get_protocol_via_id(rogue.protocol_uuid)
+        .await
+        .unwrap();
+
+    let event = ResultReceived {
+        data: Bytes::from(expected_notice(&seeded)),
+    };
+    harness.fake.emit(&rogue.contract_address, &event).unwrap();
+    harness.index().await;
+    let job = harness.settle(&seeded).await;
+    assert_eq!(job.stage, SettlementStage::AwaitingResult);
+    assert!(job.result.is_none());
+
+    let payload = harness.fake.submissions()[0].payload.clone();
+    harness
+        .fake
+        .respond(&payload, expected_notice(&seeded))
+        .unwrap();
+    harness.index().await;
+    let job = harness.settle(&seeded).await;
+    assert_eq!(job.stage, SettlementStage::Settled);
+    harness.assert_settled(&seeded).await;
+}
+
+#[actix_web::test]
+async fn ignores_results_of_payloads_the_job_did_not_submit() {
+    let harness = Harness::new();
+    let seeded = harness.seed().await;
+    harness.settle(&seeded).await;
+
+    // What a `gradeQuiz` of someone else's payload for the same uuid would emit.
+    let event = ResultReceived {
+        data: Bytes::from(forged_notice(&seeded)),
+    };
+    harness
+        .fake
+        .emit(&seeded.protocol.contract_address, &event)
+        .unwrap();
+    harness.index().await;
+    let job = harness.settle(&seeded).await;
+    assert_eq!(job.stage, SettlementStage::AwaitingResult);
+    assert!(job.result.is_none());
+
+    let payload = harness.fake.submissions()[0].payload.clone();
+    harness
+        .fake
+        .respond(&payload, expected_notice(&seeded))
+        .unwrap();
+    harness.index().await;
+    let job = harness.settle(&seeded).await;
+    assert_eq!(job.stage, SettlementStage::Settled);
+    harness.assert_settled(&seeded).await;
+}
+
+#[actix_web::test]
+async fn routes_settlement_to_the_protocol_chain() {
+    let mut harness = Harness::new();
+    let other = Arc::new(FakeChainClient::new());
+    harness.registry.register(
+        chain_config(10, "http://other", &other.factory_address()),
+        other.clone(),
+    );
+    let seeded = seed_closed_quiz(&harness.db, &harness.registry, 10).await;
+    assert_eq!(seeded.protocol.chain_id, Some(10));
+
+    let job = harness.settle(&seeded).await;
+    assert_eq!(job.stage, SettlementStage::SubmittedOnchain);
+    assert_eq!(job.chain_id, Some(10));
+    assert!(harness.fake.submissions().is_empty());
+    let submissions = other.submissions();
+    assert_eq!(submissions.len(), 1);
+    assert_eq!(
+        submissions[0].contract_address,
+        seeded.protocol.contract_address
+    );
+
+    other
+        .respond(&submissions[0].payload, expected_notice(&seeded))
+        .unwrap();
+    index_new_blocks(&harness.db, &harness.registry, 10, &indexer_config(), None)
+        .await
+        .unwrap();
+    let job = harness.settle(&seeded).await;
+    assert_eq!(job.stage, SettlementStage::Settled);
+    harness.assert_settled(&seeded).await;
+}
+
+#[actix_web::test]
+async fn refuses_quizzes_the_coprocessor_would_reject() {
+    let harness = Harness::new();
+    let seeded = harness.seed().await;
+    let mut quiz = harness
+        .db
+        .get_quiz_via_uuid(seeded.quiz.uuid.clone())
+        .await
+        .unwrap();
+    let mut twin = quiz.participants[0].clone();
+    twin.answered_questions[0].question_id = 7;
+    quiz.participants.push(twin);
+    harness.db.update_quiz(quiz).await.unwrap();
+
+    let job = harness.settle(&seeded).await;
+    assert_eq!(job.stage, SettlementStage::Failed);
+    assert!(harness.fake.submissions().is_empty());
+    let quiz = harness
+        .db
+        .get_quiz_via_uuid(seeded.quiz.uuid.clone())
+        .await
+        .unwrap();
+    let participant = seeded.participant.user_uuid.clone();
+    assert_eq!(
+        quiz.grading_rejection,
+        Some(RejectionReport::new(
+            Some(seeded.quiz.uuid.clone()),
+            vec![
+                InputProblem::DuplicateParticipant {
+                    participant: participant.clone(),
+                },
+                InputProblem::UnknownQuestion {
+                    participant,
+                    question_id: 7,
+                },
+            ],
+        ))
+    );
+    // Only staff see why.
+    let staff = quiz.view(&QuizAudience::Staff);
+    assert_eq!(staff.grading_rejection, quiz.grading_rejection);
+    assert!(quiz.view(&QuizAudience::Public).grading_rejection.is_none());
+}
+
+/// A notice for the seeded quiz paying its reward to the creator instead.
+fn forged_notice(seeded: &SeededQuiz) -> Vec<u8> {
+    encode_quiz_response(
+        QuizResponse {
+            uuid: seeded.quiz.uuid.clone(),
+            protocol: seeded.protocol.name.clone(),
+            results: vec![RewardData {
+                user_address: CREATOR_WALLET.to_string(),
+                reward_amount: reward_pool(),
+                leader_boar_addition: 5.0,
+                quiz_score: 100.0,
+            }],
+            results_root: None,
+        }
+        .commit_results(),
+    )
+    .unwrap()
+}
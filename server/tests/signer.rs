@@ -0,0 +1,172 @@
+//! Signer backends: a plain key, an encrypted keystore, and a remote JSON-RPC signer
+//! served by a local stand-in.
+
+use actix_web::{
+    web::{self, Data, Json},
+    App, HttpResponse, HttpServer,
+};
+use alloy::{
+    consensus::{SignableTransaction, TxEip1559, TxEnvelope},
+    eips::eip2718::{Decodable2718, Encodable2718},
+    network::TxSignerSync,
+    primitives::{address, Address, Bytes, TxKind, U256},
+    rpc::types::TransactionRequest,
+    signers::local::PrivateKeySigner,
+};
+use serde_json::{json, Value};
+use server::services::signer::{
+    signer_from_env, LocalSigner, RemoteSigner, SignerBackend, TransactionSigner,
+};
+use std::path::PathBuf;
+
+// Anvil's second development account, which the keystore fixture also holds.
+const KEY: &str = "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+const ADDRESS: Address = address!("70997970C51812dc3A010C7d01b50e0d17dc79C8");
+const KEYSTORE_PASSWORD: &str = "openquest-test";
+
+fn keystore_path() -> String {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/keystore.json")
+        .to_string_lossy()
+        .to_string()
+}
+
+fn transaction() -> TxEip1559 {
+    TxEip1559 {
+        chain_id: 31337,
+        nonce: 7,
+        gas_limit: 120_000,
+        max_fee_per_gas: 2_000_000_000,
+        max_priority_fee_per_gas: 1_000_000_000,
+        to: TxKind::Call(address!("5FbDB2315678afecb367f032d93F642f64180aa3")),
+        value: U256::ZERO,
+        access_list: Default::default(),
+        input: Bytes::from(vec![0xde, 0xad, 0xbe, 0xef]),
+    }
+}
+
+/// Decodes a signed transaction and returns who signed it.
+fn signed_by(raw: &Bytes, expected: &TxEip1559) -> Address {
+    let envelope = TxEnvelope::decode_2718(&mut raw.as_ref()).unwrap();
+    let signed = envelope.as_eip1559().unwrap();
+    assert_eq!(signed.tx(), expected);
+    signed
+        .signature()
+        .recover_address_from_prehash(&signed.signature_hash())
+        .unwrap()
+}
+
+struct StandIn {
+    signer: PrivateKeySigner,
+    /// Sign a different nonce than the one asked for.
+    tamper: bool,
+}
+
+/// Answers `eth_signTransaction` the way web3signer does, with the raw signed transaction.
+async fn stand_in(state: Data<StandIn>, body: Json<Value>) -> HttpResponse {
+    assert_eq!(body["method"], "eth_signTransaction");
+    let request: TransactionRequest = serde_json::from_value(body["params"][0].clone()).unwrap();
+    assert_eq!(request.from, Some(state.signer.address()));
+    // web3signer reads the calldata from `data`.
+    let data = body["params"][0]["data"].as_str().unwrap();
+    let mut tx = TxEip1559 {
+        chain_id: request.chain_id.unwrap(),
+        nonce: request.nonce.unwrap(),
+        gas_limit: request.gas.unwrap(),
+        max_fee_per_gas: request.max_fee_per_gas.unwrap(),
+        max_priority_fee_per_gas: request.max_priority_fee_per_gas.unwrap(),
+        to: request.to.unwrap(),
+        value: request.value.unwrap_or_default(),
+        access_list: Default::default(),
+        input: data.parse().unwrap(),
+    };
+    if state.tamper {
+        tx.nonce += 1;
+    }
+    let signature = state.signer.sign_transaction_sync(&mut tx).unwrap();
+    let raw = Bytes::from(TxEnvelope::from(tx.into_signed(signature)).encoded_2718());
+    HttpResponse::Ok().json(json!({ "jsonrpc": "2.0", "id": body["id"], "result": raw }))
+}
+
+/// Serves the stand-in on a free local port and returns its URL.
+fn start_stand_in(tamper: bool) -> String {
+    let state = Data::new(StandIn {
+        signer: KEY.parse().unwrap(),
+        tamper,
+    });
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(state.clone())
+            .route("/", web::post().to(stand_in))
+    })
+    .workers(1)
+    .bind(("127.0.0.1", 0))
+    .unwrap();
+    let url = format!("http://{}", server.addrs()[0]);
+    actix_web::rt::spawn(server.run());
+    url
+}
+
+#[actix_web::test]
+async fn local_key_and_keystore_sign_for_the_same_address() {
+    let plain = LocalSigner::from_private_key(KEY).unwrap();
+    let keystore = LocalSigner::from_keystore(&keystore_path(), KEYSTORE_PASSWORD).unwrap();
+    for signer in [&plain, &keystore] {
+        assert_eq!(signer.address(), ADDRESS);
+        let raw = signer.sign_transaction(transaction()).await.unwrap();
+        assert_eq!(signed_by(&raw, &transaction()), ADDRESS);
+    }
+    assert!(LocalSigner::from_keystore(&keystore_path(), "wrong password").is_err());
+}
+
+#[actix_web::test]
+async fn remote_signer_returns_the_transaction_it_was_asked_for() {
+    let signer = RemoteSigner::new(&start_stand_in(false), &ADDRESS.to_string()).unwrap();
+    let raw = signer.sign_transaction(transaction()).await.unwrap();
+    assert_eq!(signed_by(&raw, &transaction()), ADDRESS);
+
+    // A signer that alters the transaction is refused.
+    let signer = RemoteSigner::new(&start_stand_in(true), &ADDRESS.to_string()).unwrap();
+    let err = signer.sign_transaction(transaction()).await.unwrap_err();
+    assert!(err.contains("changed the transaction"), "{}", err);
+
+    // As is one holding a different key than configured.
+    let other = address!("3C44CdDdB6a900fa2b585dd299e03d12FA4293BC");
+    let signer = RemoteSigner::new(&start_stand_in(false), &other.to_string()).unwrap();
+    assert!(signer.sign_transaction(transaction()).await.is_err());
+}
+
+#[actix_web::test]
+async fn backend_is_chosen_from_the_environment() {
+    let password_file = std::env::temp_dir().join("openquest-keystore-password");
+    std::fs::write(&password_file, format!("{}\n", KEYSTORE_PASSWORD)).unwrap();
+    std::env::set_var("SIGNER_BACKEND", "keystore");
+    std::env::set_var("KEYSTORE_PATH", keystore_path());
+    std::env::set_var("KEYSTORE_PASSWORD_FILE", &password_file);
+    assert!(matches!(
+        SignerBackend::from_env(),
+        Ok(SignerBackend::Keystore { .. })
+    ));
+    assert_eq!(signer_from_env().unwrap().address(), ADDRESS);
+
+    std::env::set_var("SIGNER_BACKEND", "remote");
+    std::env::set_var("REMOTE_SIGNER_URL", "http://127.0.0.1:9000");
+    std::env::set_var("REMOTE_SIGNER_ADDRESS", ADDRESS.to_string());
+    assert_eq!(signer_from_env().unwrap().address(), ADDRESS);
+
+    std::env::set_var("SIGNER_BACKEND", "hsm");
+    assert!(SignerBackend::from_env().is_err());
+
+    // Even with a key at hand, the plain-key backend has to be asked for by name.
+    std::env::set_var("PRIVATE_KEY", format!("0x{}", "11".repeat(32)));
+    std::env::remove_var("SIGNER_BACKEND");
+    assert_eq!(
+        SignerBackend::from_env().err(),
+        Some("SIGNER_BACKEND must be set".to_string())
+    );
+    std::env::set_var("SIGNER_BACKEND", "private_key");
+    assert!(matches!(
+        SignerBackend::from_env(),
+        Ok(SignerBackend::PrivateKey { .. })
+    ));
+}
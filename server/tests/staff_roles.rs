@@ -0,0 +1,66 @@
+//! Staff roles read back from protocols saved before roles existed.
+
+use mongodb::bson::{doc, from_document};
+use server::models::protocol_model::{Permission, Protocol, StaffRole};
+
+fn legacy_protocol(staffs: Vec<&str>) -> Protocol {
+    from_document(doc! {
+        "protocol_uuid": "protocol-1",
+        "name": "Legacy",
+        "staffs": staffs,
+        "protocol_users": [],
+        "created_at": "2024-01-01 00:00:00",
+        "created_by": "creator",
+        "quizes": [],
+        "total_expense": "0",
+        "contract_address": " ",
+    })
+    .unwrap()
+}
+
+#[test]
+fn legacy_creator_becomes_owner() {
+    let protocol = legacy_protocol(vec!["creator", "helper"]);
+
+    assert_eq!(protocol.role_of("creator"), Some(StaffRole::Owner));
+    assert_eq!(protocol.role_of("helper"), Some(StaffRole::Analyst));
+    assert!(protocol.has_permission("creator", Permission::ManageAdmins));
+    assert!(protocol.has_permission("helper", Permission::ViewAnalytics));
+    assert!(!protocol.has_permission("helper", Permission::CreateQuiz));
+}
+
+#[test]
+fn owner_promotes_legacy_staff() {
+    let mut protocol = legacy_protocol(vec!["creator", "helper"]);
+
+    assert!(protocol
+        .update_staff_role("helper", StaffRole::Admin)
+        .is_ok());
+    assert!(protocol.has_permission("helper", Permission::ManageStaff));
+}
+
+#[test]
+fn legacy_protocol_without_creator_in_staff_gains_owner() {
+    let mut protocol = legacy_protocol(vec!["helper"]);
+
+    assert_eq!(protocol.role_of("creator"), Some(StaffRole::Owner));
+    assert!(protocol
+        .update_staff_role("helper", StaffRole::Owner)
+        .is_ok());
+    assert_eq!(protocol.staffs.len(), 2);
+}
+
+#[test]
+fn owners_are_kept_when_roles_are_stored() {
+    let mut protocol = Protocol::new("Current".to_string(), "creator".to_string(), 1);
+    protocol.add_staff("second".to_string(), StaffRole::Owner);
+    protocol
+        .update_staff_role("creator", StaffRole::Analyst)
+        .unwrap();
+
+    let stored = mongodb::bson::to_document(&protocol).unwrap();
+    let read_back: Protocol = from_document(stored).unwrap();
+
+    assert_eq!(read_back, protocol);
+    assert_eq!(read_back.role_of("creator"), Some(StaffRole::Analyst));
+}
@@ -0,0 +1,286 @@
+//! Wallet ownership challenges: address checks, signature recovery, one-time nonces
+//! and signing in with a wallet.
+
+mod common;
+
+use actix_web::{test as actix_test, web::Data, App};
+use alloy::signers::{local::PrivateKeySigner, SignerSync};
+use common::single_chain;
+use serde_json::{json, Value};
+use server::models::{user_model::User, wallet_challenge_model::ChallengePurpose};
+use server::routes::user_routes::{
+    link_wallet_address, request_siwe_nonce, request_wallet_challenge, siwe_login,
+};
+use server::services::{
+    fake_chain::FakeChainClient,
+    memory_db::MemoryDatabase,
+    siwe::{issue_challenge, parse_wallet_address, verify_challenge_signature, SiweConfig},
+    storage::Storage,
+};
+use server::utils::jwt::generate_jwt;
+use std::sync::Arc;
+
+// Anvil's first two development accounts.
+const KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+const OTHER_KEY: &str = "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+
+fn config() -> SiweConfig {
+    SiweConfig {
+        domain: "openquest.test".to_string(),
+        uri: "https://openquest.test".to_string(),
+        challenge_ttl_secs: 300,
+    }
+}
+
+fn sign(key: &str, message: &str) -> String {
+    let signer: PrivateKeySigner = key.parse().unwrap();
+    let signature = signer.sign_message_sync(message.as_bytes()).unwrap();
+    format!("0x{}", alloy::hex::encode(signature.as_bytes()))
+}
+
+#[test]
+fn validates_address_format_and_checksum() {
+    let checksummed = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+    assert!(parse_wallet_address(checksummed).is_ok());
+    assert!(parse_wallet_address(&checksummed.to_lowercase()).is_ok());
+    // One letter's case flipped breaks the EIP-55 checksum.
+    assert!(parse_wallet_address("0xF39Fd6e51aad88F6F4ce6aB8827279cffFb92266").is_err());
+    assert!(parse_wallet_address("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266").is_err());
+    assert!(parse_wallet_address("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb9226").is_err());
+    assert!(parse_wallet_address("0x0000000000000000000000000000000000000000").is_err());
+}
+
+#[test]
+fn accepts_only_signatures_from_the_challenged_address() {
+    let address = parse_wallet_address("0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266").unwrap();
+    let challenge = issue_challenge(
+        &config(),
+        ChallengePurpose::LinkWallet,
+        Some("user".to_string()),
+        address,
+        31337,
+        1_700_000_000,
+    );
+    assert_eq!(
+        challenge.address,
+        "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"
+    );
+    assert!(challenge
+        .message
+        .starts_with("openquest.test wants you to sign in with your Ethereum account:\n"));
+    assert!(challenge
+        .message
+        .contains(&format!("Nonce: {}", challenge.nonce)));
+    assert!(challenge.message.contains("Chain ID: 31337"));
+    assert!(!challenge.is_expired(1_700_000_299));
+    assert!(challenge.is_expired(1_700_000_300));
+
+    assert!(verify_challenge_signature(&challenge, &sign(KEY, &challenge.message)).is_ok());
+    assert!(verify_challenge_signature(&challenge, &sign(OTHER_KEY, &challenge.message)).is_err());
+    assert!(verify_challenge_signature(&challenge, &sign(KEY, "something else")).is_err());
+    assert!(verify_challenge_signature(&challenge, "0x1234").is_err());
+}
+
+#[actix_web::test]
+async fn challenges_can_be_redeemed_once_by_their_user() {
+    let db = MemoryDatabase::new();
+    let address = parse_wallet_address("0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266").unwrap();
+    let user = Some("user".to_string());
+    let challenge = issue_challenge(
+        &config(),
+        ChallengePurpose::LinkWallet,
+        user.clone(),
+        address,
+        1,
+        0,
+    );
+    db.create_wallet_challenge(challenge.clone()).await.unwrap();
+
+    for (purpose, user_uuid) in [
+        (
+            ChallengePurpose::LinkWallet,
+            Some("someone-else".to_string()),
+        ),
+        (ChallengePurpose::SignIn, None),
+    ] {
+        let err = db
+            .consume_wallet_challenge(challenge.nonce.clone(), purpose, user_uuid)
+            .await
+            .unwrap_err();
+        assert_eq!(err.error_code, 404);
+    }
+    let redeemed = db
+        .consume_wallet_challenge(
+            challenge.nonce.clone(),
+            ChallengePurpose::LinkWallet,
+            user.clone(),
+        )
+        .await
+        .unwrap();
+    assert!(redeemed.used);
+    let err = db
+        .consume_wallet_challenge(challenge.nonce, ChallengePurpose::LinkWallet, user)
+        .await
+        .unwrap_err();
+    assert_eq!(err.error_code, 404);
+}
+
+#[actix_web::test]
+async fn signs_in_with_a_wallet_and_creates_the_account_once() {
+    std::env::set_var("JWT_SECRET", "wallet-link-test-secret");
+    let db: Arc<dyn Storage> = Arc::new(MemoryDatabase::new());
+    let fake = Arc::new(FakeChainClient::new());
+    let registry = single_chain("http://fake", &fake.factory_address(), fake);
+    let app = actix_test::init_service(
+        App::new()
+            .app_data(Data::from(db.clone()))
+            .app_data(Data::new(registry))
+            .service(request_siwe_nonce)
+            .service(siwe_login),
+    )
+    .await;
+    let address = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+
+    let mut signed_in = Vec::new();
+    for expected_status in [201, 200] {
+        let request = actix_test::TestRequest::post()
+            .uri("/user/siwe/nonce")
+            .set_json(json!({ "wallet_address": address }))
+            .to_request();
+        let challenge: Value = actix_test::call_and_read_body_json(&app, request).await;
+        let message = challenge["message"].as_str().unwrap();
+        assert!(message.contains("Chain ID: 31337"));
+        let login = json!({
+            "nonce": challenge["nonce"],
+            "signature": sign(KEY, message),
+        });
+
+        let request = actix_test::TestRequest::post()
+            .uri("/user/siwe/login")
+            .set_json(&login)
+            .to_request();
+        let response = actix_test::call_service(&app, request).await;
+        assert_eq!(response.status().as_u16(), expected_status);
+        assert!(response
+            .response()
+            .cookies()
+            .any(|cookie| cookie.name() == "token"));
+        let body: Value = actix_test::read_body_json(response).await;
+        assert!(body["token"].as_str().is_some());
+        assert!(body["user"]["email"].is_null());
+        signed_in.push(body["user"]["user_uuid"].clone());
+
+        // The nonce was spent by the first attempt.
+        let request = actix_test::TestRequest::post()
+            .uri("/user/siwe/login")
+            .set_json(&login)
+            .to_request();
+        let response = actix_test::call_service(&app, request).await;
+        assert_eq!(response.status().as_u16(), 400);
+    }
+    assert_eq!(signed_in[0], signed_in[1]);
+    let user = db.get_user_via_address(address.to_string()).await.unwrap();
+    assert!(user.email.is_none() && user.wallet.verified);
+    assert!(!user.compare_password(String::new()));
+}
+
+fn unverified_user(name: &str, address: &str) -> User {
+    let mut user = User::new(
+        name.to_string(),
+        format!("{}@openquest.test", name),
+        "correct-horse-42".to_string(),
+    )
+    .unwrap();
+    user.wallet.update_wallet(address.to_string());
+    user
+}
+
+#[actix_web::test]
+async fn signatures_take_over_wallets_linked_without_one() {
+    std::env::set_var("JWT_SECRET", "wallet-link-test-secret");
+    let db: Arc<dyn Storage> = Arc::new(MemoryDatabase::new());
+    let fake = Arc::new(FakeChainClient::new());
+    let registry = single_chain("http://fake", &fake.factory_address(), fake);
+    let address = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+    let other_address = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8";
+    let legacy = unverified_user("legacy", address);
+    let other_legacy = unverified_user("other-legacy", other_address);
+    let claimer = User::new(
+        "claimer".to_string(),
+        "claimer@openquest.test".to_string(),
+        "correct-horse-42".to_string(),
+    )
+    .unwrap();
+    let late = User::new(
+        "late".to_string(),
+        "late@openquest.test".to_string(),
+        "correct-horse-42".to_string(),
+    )
+    .unwrap();
+    for user in [&legacy, &other_legacy, &claimer, &late] {
+        db.create_user(user.clone()).await.unwrap();
+    }
+    let app = actix_test::init_service(
+        App::new()
+            .app_data(Data::from(db.clone()))
+            .app_data(Data::new(registry))
+            .service(request_wallet_challenge)
+            .service(link_wallet_address)
+            .service(request_siwe_nonce)
+            .service(siwe_login),
+    )
+    .await;
+    let bearer = |user: &User| {
+        let token = generate_jwt(
+            user.user_uuid.clone(),
+            "wallet-link-test-secret".to_string(),
+        )
+        .unwrap();
+        ("Authorization", format!("Bearer {}", token))
+    };
+
+    // Linking an address another account only claimed moves it to the signer.
+    for (user, expected_status) in [(&claimer, 200), (&late, 409)] {
+        let request = actix_test::TestRequest::post()
+            .uri("/user/wallet/challenge")
+            .insert_header(bearer(user))
+            .set_json(json!({ "wallet_address": address }))
+            .to_request();
+        let challenge: Value = actix_test::call_and_read_body_json(&app, request).await;
+        let request = actix_test::TestRequest::post()
+            .uri("/user/wallet")
+            .insert_header(bearer(user))
+            .set_json(json!({
+                "nonce": challenge["nonce"],
+                "signature": sign(KEY, challenge["message"].as_str().unwrap()),
+            }))
+            .to_request();
+        let response = actix_test::call_service(&app, request).await;
+        assert_eq!(response.status().as_u16(), expected_status);
+    }
+    let legacy = db.get_user_via_uuid(legacy.user_uuid).await.unwrap();
+    assert!(legacy.wallet.wallet_address.is_none());
+    let owner = db.get_user_via_address(address.to_string()).await.unwrap();
+    assert_eq!(owner.user_uuid, claimer.user_uuid);
+    assert!(owner.wallet.verified);
+
+    // Signing in with such an address gives the signer a fresh account instead.
+    let request = actix_test::TestRequest::post()
+        .uri("/user/siwe/nonce")
+        .set_json(json!({ "wallet_address": other_address }))
+        .to_request();
+    let challenge: Value = actix_test::call_and_read_body_json(&app, request).await;
+    let request = actix_test::TestRequest::post()
+        .uri("/user/siwe/login")
+        .set_json(json!({
+            "nonce": challenge["nonce"],
+            "signature": sign(OTHER_KEY, challenge["message"].as_str().unwrap()),
+        }))
+        .to_request();
+    let response = actix_test::call_service(&app, request).await;
+    assert_eq!(response.status().as_u16(), 201);
+    let body: Value = actix_test::read_body_json(response).await;
+    assert_ne!(body["user"]["user_uuid"], json!(other_legacy.user_uuid));
+    let other_legacy = db.get_user_via_uuid(other_legacy.user_uuid).await.unwrap();
+    assert!(other_legacy.wallet.wallet_address.is_none());
+}